@@ -1,8 +1,12 @@
 // Tauri IPC command handlers bridging the frontend and backend.
 
-use crate::database::{self, Task};
+use crate::database::{
+    self, DependencyMode, FileTouch, TagCount, Task, TaskRun, TaskSettings, ToolInvocation,
+    ToolStat, ToolStatsSort,
+};
 use crate::providers::Provider;
-use crate::session::{Session, SessionDetail, UsageStats};
+use crate::session::{Session, SessionDetail, SessionSearchHit, SessionTree, UsageStats};
+use crate::task_graph::TaskGraph;
 use std::fmt::Write;
 use tauri::{AppHandle, Emitter, Manager, Window};
 
@@ -54,11 +58,10 @@ fn merge_hooks_into_settings(
 
 #[tauri::command]
 pub async fn get_sessions(
-    app: AppHandle,
     project: Option<String>,
     limit: Option<i64>,
 ) -> Result<Vec<Session>, String> {
-    database::get_sessions(&app, project.as_deref(), limit.unwrap_or(50)).map_err(str_err)
+    database::get_sessions(project.as_deref(), limit.unwrap_or(50)).map_err(str_err)
 }
 
 #[tauri::command(rename_all = "camelCase")]
@@ -69,6 +72,14 @@ pub async fn get_session_detail(
     database::get_session_detail(&app, &session_id).map_err(str_err)
 }
 
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_session_tree(
+    app: AppHandle,
+    session_id: String,
+) -> Result<SessionTree, String> {
+    database::get_session_tree(&app, &session_id).map_err(str_err)
+}
+
 #[tauri::command(rename_all = "camelCase")]
 pub async fn get_session_images(
     app: AppHandle,
@@ -100,37 +111,74 @@ pub async fn get_usage_stats(
     .map_err(str_err)
 }
 
+#[tauri::command]
+pub async fn get_tool_stats(
+    project: Option<String>,
+    provider: Option<String>,
+    sort: Option<ToolStatsSort>,
+    limit: Option<i64>,
+) -> Result<Vec<ToolStat>, String> {
+    database::get_tool_stats(
+        project.as_deref(),
+        provider.as_deref(),
+        sort.unwrap_or_default(),
+        limit.unwrap_or(20),
+    )
+    .map_err(str_err)
+}
+
+#[tauri::command]
+pub async fn get_shell_commands(
+    project: Option<String>,
+    provider: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<ToolInvocation>, String> {
+    database::get_shell_commands(project.as_deref(), provider.as_deref(), limit.unwrap_or(50))
+        .map_err(str_err)
+}
+
+#[tauri::command]
+pub async fn get_file_sessions(
+    path: String,
+    project: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<FileTouch>, String> {
+    database::get_file_touches(&path, project.as_deref(), limit.unwrap_or(50))
+        .map_err(str_err)
+}
+
 #[tauri::command]
 pub async fn search_sessions(
-    app: AppHandle,
     query: String,
     project: Option<String>,
     limit: Option<i64>,
-) -> Result<Vec<Session>, String> {
-    database::search_sessions(&app, &query, project.as_deref(), limit.unwrap_or(20))
+) -> Result<Vec<SessionSearchHit>, String> {
+    let tokenizer = crate::config::load_config().search_tokenizer;
+    database::search_sessions(&query, project.as_deref(), limit.unwrap_or(20), tokenizer)
         .map_err(str_err)
 }
 
 #[tauri::command(rename_all = "camelCase")]
 pub async fn search_sessions_filtered(
-    app: AppHandle,
     query: Option<String>,
     project: Option<String>,
     status: Option<String>,
     model: Option<String>,
     date_from: Option<String>,
     date_to: Option<String>,
+    tags: Option<Vec<String>>,
     limit: Option<i64>,
-) -> Result<Vec<Session>, String> {
+) -> Result<Vec<SessionSearchHit>, String> {
     database::search_sessions_filtered(
-        &app,
         query.as_deref(),
         project.as_deref(),
         status.as_deref(),
         model.as_deref(),
         date_from.as_deref(),
         date_to.as_deref(),
+        tags.as_deref(),
         limit.unwrap_or(50),
+        crate::config::load_config().search_tokenizer,
     )
     .map_err(str_err)
 }
@@ -154,6 +202,69 @@ pub async fn update_session_label(
     database::update_session_label(&app, &session_id, label.as_deref()).map_err(str_err)
 }
 
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_session_pinned(
+    session_id: String,
+    pinned: bool,
+) -> Result<(), String> {
+    database::set_session_pinned(&session_id, pinned).map_err(str_err)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_session_notes(
+    session_id: String,
+    notes: Option<String>,
+) -> Result<(), String> {
+    database::set_session_notes(&session_id, notes.as_deref()).map_err(str_err)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_session_archived(
+    session_id: String,
+    archived: bool,
+) -> Result<(), String> {
+    database::set_session_archived(&session_id, archived).map_err(str_err)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn add_session_tags(
+    session_id: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    database::add_session_tags(&session_id, &tags).map_err(str_err)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn remove_session_tags(
+    session_id: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    database::remove_session_tags(&session_id, &tags).map_err(str_err)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_session_tags(
+    session_id: String,
+    tags: Vec<String>,
+) -> Result<Vec<String>, String> {
+    database::set_session_tags(&session_id, &tags).map_err(str_err)
+}
+
+#[tauri::command]
+pub async fn get_tags() -> Result<Vec<TagCount>, String> {
+    database::get_tag_counts().map_err(str_err)
+}
+
+#[tauri::command]
+pub async fn rename_tag(from: String, to: String) -> Result<usize, String> {
+    database::rename_tag(&from, &to).map_err(str_err)
+}
+
+#[tauri::command]
+pub async fn delete_tag(tag: String) -> Result<usize, String> {
+    database::delete_tag(&tag).map_err(str_err)
+}
+
 #[tauri::command(rename_all = "camelCase")]
 pub async fn delete_session(app: AppHandle, session_id: String) -> Result<(), String> {
     database::delete_session(&app, &session_id).map_err(str_err)
@@ -184,8 +295,8 @@ pub async fn debug_get_codex_dirs() -> Result<Vec<String>, String> {
 }
 
 #[tauri::command]
-pub async fn debug_codex_db_sessions(app: AppHandle) -> Result<Vec<String>, String> {
-    let sessions = database::get_sessions(&app, None, 5000).map_err(str_err)?;
+pub async fn debug_codex_db_sessions() -> Result<Vec<String>, String> {
+    let sessions = database::get_sessions(None, 5000).map_err(str_err)?;
 
     Ok(sessions
         .into_iter()
@@ -232,9 +343,22 @@ fn format_session_markdown(detail: &SessionDetail) -> String {
         let role = match msg.role.as_str() {
             "user" => "\u{1f464} User",
             "assistant" => "\u{1f916} Assistant",
+            "tool" if msg.is_error => "\u{26a0}\u{fe0f} Tool Error",
+            "tool" => "\u{1f527} Tool Result",
             _ => &msg.role,
         };
-        let _ = write!(md, "### {}\n\n{}\n\n", role, msg.content);
+        let _ = write!(md, "### {}\n\n", role);
+        if msg.role == "tool" {
+            let _ = write!(md, "```\n{}\n```\n\n", msg.content);
+        } else if !msg.content.is_empty() {
+            let _ = write!(md, "{}\n\n", msg.content);
+        }
+        for call in &msg.tool_calls {
+            let _ = writeln!(md, "- `{}` {}", call.name, call.input);
+        }
+        if !msg.tool_calls.is_empty() {
+            md.push('\n');
+        }
     }
 
     md
@@ -254,13 +378,23 @@ pub async fn get_tasks(
     database::get_tasks(&app, status, project.as_deref()).map_err(str_err)
 }
 
-#[tauri::command]
+/// Parse a dependency mode argument, defaulting to all-of
+fn parse_dependency_mode(mode: Option<String>) -> Result<DependencyMode, String> {
+    match mode {
+        Some(m) => m.parse().map_err(|_| format!("Unknown dependency mode: {}", m)),
+        None => Ok(DependencyMode::default()),
+    }
+}
+
+#[tauri::command(rename_all = "camelCase")]
 pub async fn create_task(
     app: AppHandle,
     prompt: String,
     project: Option<String>,
     priority: Option<String>,
     notes: Option<String>,
+    depends_on: Option<Vec<String>>,
+    dependency_mode: Option<String>,
 ) -> Result<Task, String> {
     database::create_task(
         &app,
@@ -268,10 +402,28 @@ pub async fn create_task(
         project.as_deref(),
         priority.as_deref(),
         notes.as_deref(),
+        &depends_on.unwrap_or_default(),
+        parse_dependency_mode(dependency_mode)?,
     )
     .map_err(str_err)
 }
 
+#[tauri::command(rename_all = "camelCase")]
+pub async fn set_task_dependencies(
+    app: AppHandle,
+    id: String,
+    depends_on: Vec<String>,
+    dependency_mode: Option<String>,
+) -> Result<Task, String> {
+    let mode = parse_dependency_mode(dependency_mode)?;
+    database::set_task_dependencies(&app, &id, &depends_on, mode).map_err(str_err)
+}
+
+#[tauri::command]
+pub async fn get_task_graph(app: AppHandle, project: Option<String>) -> Result<TaskGraph, String> {
+    database::get_task_graph(&app, project.as_deref()).map_err(str_err)
+}
+
 #[tauri::command]
 pub async fn update_task(
     app: AppHandle,
@@ -286,9 +438,63 @@ pub async fn update_task(
         .map_err(str_err)
 }
 
+#[tauri::command]
+pub async fn update_task_settings(id: String, settings: TaskSettings) -> Result<Task, String> {
+    database::update_task_settings(&id, &settings).map_err(str_err)
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn remove_task_worktree(id: String, delete_branch: Option<bool>) -> Result<Task, String> {
+    crate::worktree::remove_task_worktree(&id, delete_branch.unwrap_or(false)).await
+}
+
+#[tauri::command(rename_all = "camelCase")]
+pub async fn get_task_runs(
+    app: AppHandle,
+    task_id: String,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<TaskRun>, String> {
+    database::get_task_runs(&app, &task_id, limit, offset).map_err(str_err)
+}
+
+/// Read a run's log from `offset`, or its tail when no offset is given.
+/// Poll with the returned offset to follow a live run.
+#[tauri::command(rename_all = "camelCase")]
+pub async fn tail_task_log(
+    run_id: i64,
+    offset: Option<u64>,
+    max_bytes: Option<u64>,
+) -> Result<crate::task_log::TaskLogChunk, String> {
+    let run = database::get_task_run(run_id).map_err(str_err)?;
+    let path = run.log_path.ok_or("Run has no log")?;
+    let (content, offset) =
+        crate::task_log::read_chunk(path.as_ref(), offset, max_bytes.unwrap_or(64 * 1024))
+            .map_err(str_err)?;
+    Ok(crate::task_log::TaskLogChunk {
+        content,
+        offset,
+        finished: run.finished_at.is_some(),
+    })
+}
+
+/// Delete a task that is not running, with its logs and worktree. The
+/// worktree's branch is kept, as it may hold work not yet reviewed.
 #[tauri::command]
 pub async fn delete_task(app: AppHandle, id: String) -> Result<(), String> {
-    database::delete_task(&app, &id).map_err(str_err)
+    let task = database::get_task(&id).map_err(str_err)?;
+    if task.status == database::TaskStatus::Running {
+        return Err(format!("Task {} is running, cancel it first", id));
+    }
+    let worktree = (task.project_path.as_deref(), task.worktree_path.as_deref());
+    if let (Some(project), Some(path)) = worktree {
+        if let Err(e) = crate::worktree::remove(project, Some(path), None).await {
+            tracing::warn!("Failed to remove worktree of task {}: {}", id, e);
+        }
+    }
+    database::delete_task(&app, &id).map_err(str_err)?;
+    crate::task_log::remove_task_logs(&id);
+    Ok(())
 }
 
 #[tauri::command(rename_all = "camelCase")]
@@ -452,9 +658,14 @@ pub async fn start_queue(app: AppHandle) -> Result<QueueStartResult, String> {
     })
 }
 
+#[tauri::command(rename_all = "camelCase")]
+pub async fn stop_queue(cancel_running: Option<bool>) -> Result<(), String> {
+    crate::queue::stop_queue(cancel_running.unwrap_or(false)).await
+}
+
 #[tauri::command]
-pub async fn stop_queue() -> Result<(), String> {
-    crate::queue::stop_queue().await
+pub async fn cancel_task(id: String) -> Result<(), String> {
+    crate::queue::cancel_task(&id).await
 }
 
 #[tauri::command]
@@ -529,7 +740,11 @@ pub async fn update_config(
     key: String,
     value: serde_json::Value,
 ) -> Result<crate::config::AppConfig, String> {
-    crate::config::update_config_value(&key, value)
+    let config = crate::config::update_config_value(&key, value)?;
+    if key == "search_tokenizer" {
+        database::rebuild_search_index(config.search_tokenizer).map_err(str_err)?;
+    }
+    Ok(config)
 }
 
 #[derive(serde::Serialize)]
@@ -567,7 +782,7 @@ pub struct ScanResult {
 
 #[tauri::command]
 pub async fn scan_claude_directory(app: AppHandle) -> Result<ScanResult, String> {
-    let sessions = database::get_sessions(&app, None, 10000).map_err(str_err)?;
+    let sessions = database::get_sessions(None, 10000).map_err(str_err)?;
     let projects = database::get_projects(&app).map_err(str_err)?;
     let total_tokens: i64 = sessions.iter().map(|s| s.total_tokens).sum();
 
@@ -612,6 +827,10 @@ pub async fn install_hooks() -> Result<HooksInstallResult, String> {
         "PreToolUse": [{
             "type": "command",
             "command": crate::platform::get_pre_tool_use_hook_command()
+        }],
+        "Notification": [{
+            "type": "command",
+            "command": crate::platform::get_hook_command("notification", true)
         }]
     });
 
@@ -729,11 +948,11 @@ fn current_platform() -> &'static str {
 }
 
 #[tauri::command]
-pub async fn get_onboarding_status(app: AppHandle) -> OnboardingStatus {
+pub async fn get_onboarding_status() -> OnboardingStatus {
     let config = crate::config::load_config();
     let credentials = crate::usage::read_claude_credentials();
 
-    let existing_sessions_count = database::get_sessions(&app, None, 10000)
+    let existing_sessions_count = database::get_sessions(None, 10000)
         .map(|s| s.len())
         .unwrap_or(0);
 
@@ -1,8 +1,10 @@
 // Configuration management for Alice
 
+use chrono::NaiveTime;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Mutex;
 
 // ---------------------------------------------------------------------------
 // Serde default-value helpers
@@ -16,6 +18,34 @@ fn default_auto_action_delay() -> u32 {
     5
 }
 
+fn default_max_concurrent_tasks() -> usize {
+    3
+}
+
+fn default_max_tasks_per_project() -> usize {
+    1
+}
+
+fn default_max_attempts() -> u32 {
+    1
+}
+
+fn default_retry_delay_secs() -> u64 {
+    60
+}
+
+fn default_max_retry_delay_secs() -> u64 {
+    1800
+}
+
+fn default_pause_at_usage_percent() -> f64 {
+    95.0
+}
+
+fn default_timeout_minutes() -> u64 {
+    120
+}
+
 fn default_hook_server_port() -> u16 {
     39512
 }
@@ -72,6 +102,17 @@ pub enum TerminalApp {
     Custom,
 }
 
+/// FTS5 tokenizer used by the session search index
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchTokenizer {
+    /// Word-based; best for space-separated languages
+    #[default]
+    Unicode61,
+    /// Substring-based; matches Chinese/Japanese text without word boundaries
+    Trigram,
+}
+
 // ---------------------------------------------------------------------------
 // Config structs
 // ---------------------------------------------------------------------------
@@ -96,6 +137,127 @@ impl Default for AutoActionConfig {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueConfig {
+    /// Tasks run at once across all projects
+    #[serde(default = "default_max_concurrent_tasks")]
+    pub max_concurrent_tasks: usize,
+    /// Tasks run at once in one project; more than 1 risks clobbering its working tree.
+    /// Tasks in their own worktree do not count.
+    #[serde(default = "default_max_tasks_per_project")]
+    pub max_tasks_per_project: usize,
+    /// Runs a failing task gets, unless the task sets its own
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubles with each further attempt
+    #[serde(default = "default_retry_delay_secs")]
+    pub retry_delay_secs: u64,
+    /// Upper bound on the backoff between attempts
+    #[serde(default = "default_max_retry_delay_secs")]
+    pub max_retry_delay_secs: u64,
+    /// Local times of day tasks may start in; empty means any time
+    #[serde(default, deserialize_with = "deserialize_run_windows")]
+    pub run_windows: Vec<RunWindow>,
+    /// Run tasks in a git worktree on their own branch, unless the task says otherwise
+    #[serde(default)]
+    pub use_worktrees: bool,
+    /// Keep the worktrees of failed and cancelled runs for inspection
+    #[serde(default)]
+    pub keep_failed_worktrees: bool,
+    /// Minutes a task may run before it is stopped, unless the task sets its own; 0 = no limit
+    #[serde(default = "default_timeout_minutes")]
+    pub timeout_minutes: u64,
+    /// Command run in a project after each of its tasks, keyed by project path;
+    /// a non-zero exit fails the task. A task's own command takes precedence.
+    #[serde(default)]
+    pub verify_commands: HashMap<String, String>,
+    /// Follow-up tasks queued to fix a task's failed verification; 0 = none
+    #[serde(default)]
+    pub max_fix_tasks: u32,
+    /// Hold a provider's tasks once its session or weekly usage reaches this
+    /// percent, until the usage resets; 0 = never
+    #[serde(default = "default_pause_at_usage_percent")]
+    pub pause_at_usage_percent: f64,
+    /// What becomes of tasks whose run was cut short by Alice exiting.
+    /// Runs still going in a terminal window are followed again instead.
+    #[serde(default)]
+    pub interrupted_tasks: InterruptedTaskAction,
+    /// Start the queue on launch if it was running when Alice exited
+    #[serde(default)]
+    pub resume_after_restart: bool,
+}
+
+/// How a task interrupted by Alice exiting is recovered on the next launch
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InterruptedTaskAction {
+    /// Queue the task to run again
+    #[default]
+    Requeue,
+    /// Mark the task failed
+    Fail,
+}
+
+/// A daily span of local time, such as 01:00-07:00; may cross midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunWindow {
+    /// "HH:MM"
+    pub start: String,
+    /// "HH:MM"
+    pub end: String,
+}
+
+impl RunWindow {
+    /// The window's start and end times; None if either is not "HH:MM"
+    pub fn bounds(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let parse = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+        Some((parse(&self.start)?, parse(&self.end)?))
+    }
+}
+
+/// Read run windows, leaving out malformed ones. Each is reported once,
+/// however often the config is loaded.
+fn deserialize_run_windows<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<RunWindow>, D::Error> {
+    static REPORTED: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+    let windows: Vec<RunWindow> = Vec::deserialize(deserializer)?;
+    Ok(windows
+        .into_iter()
+        .filter(|w| {
+            let valid = w.bounds().is_some();
+            let mut reported = REPORTED.lock().unwrap_or_else(|e| e.into_inner());
+            let key = (w.start.clone(), w.end.clone());
+            if !valid && !reported.contains(&key) {
+                tracing::warn!("Ignoring invalid run window {}-{}", w.start, w.end);
+                reported.push(key);
+            }
+            valid
+        })
+        .collect())
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_tasks: default_max_concurrent_tasks(),
+            max_tasks_per_project: default_max_tasks_per_project(),
+            max_attempts: default_max_attempts(),
+            retry_delay_secs: default_retry_delay_secs(),
+            max_retry_delay_secs: default_max_retry_delay_secs(),
+            run_windows: Vec::new(),
+            use_worktrees: false,
+            keep_failed_worktrees: false,
+            timeout_minutes: default_timeout_minutes(),
+            verify_commands: HashMap::new(),
+            max_fix_tasks: 0,
+            pause_at_usage_percent: default_pause_at_usage_percent(),
+            interrupted_tasks: InterruptedTaskAction::default(),
+            resume_after_restart: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationConfig {
     #[serde(default = "default_true")]
@@ -104,6 +266,8 @@ pub struct NotificationConfig {
     pub on_task_error: bool,
     #[serde(default = "default_true")]
     pub on_needs_input: bool,
+    #[serde(default = "default_true")]
+    pub on_budget_exceeded: bool,
     #[serde(default)]
     pub on_queue_started: bool,
     #[serde(default = "default_true")]
@@ -116,6 +280,7 @@ impl Default for NotificationConfig {
             on_task_completed: true,
             on_task_error: true,
             on_needs_input: true,
+            on_budget_exceeded: true,
             on_queue_started: false,
             on_daily_report: true,
         }
@@ -206,6 +371,8 @@ pub struct AppConfig {
     pub terminal_choice_made: bool,
     #[serde(default)]
     pub auto_action: AutoActionConfig,
+    #[serde(default)]
+    pub queue: QueueConfig,
     #[serde(default = "default_environments")]
     pub claude_environments: Vec<ClaudeEnvironment>,
     #[serde(default)]
@@ -217,6 +384,8 @@ pub struct AppConfig {
     pub hook_server_port: u16,
     #[serde(default)]
     pub gemini_hooks_installed: bool,
+    #[serde(default)]
+    pub search_tokenizer: SearchTokenizer,
 }
 
 impl Default for AppConfig {
@@ -237,11 +406,13 @@ impl Default for AppConfig {
             custom_terminal_command: String::new(),
             terminal_choice_made: false,
             auto_action: AutoActionConfig::default(),
+            queue: QueueConfig::default(),
             claude_environments: default_environments(),
             active_environment_id: None,
             provider_configs: default_provider_configs(),
             hook_server_port: default_hook_server_port(),
             gemini_hooks_installed: false,
+            search_tokenizer: SearchTokenizer::default(),
         }
     }
 }
@@ -336,6 +507,10 @@ pub fn update_config_value(key: &str, value: serde_json::Value) -> Result<AppCon
                 "dark"  => Theme::Dark,
                 _       => Theme::System,
             },
+            "search_tokenizer" => config.search_tokenizer = match value.as_str().unwrap_or("unicode61") {
+                "trigram" => SearchTokenizer::Trigram,
+                _         => SearchTokenizer::Unicode61,
+            },
             "terminal_app" => config.terminal_app = match value.as_str().unwrap_or("system") {
                 "background"       => TerminalApp::Background,
                 "iterm2"           => TerminalApp::ITerm2,
@@ -1,8 +1,10 @@
 // SQLite database management
 
-use crate::session::{Session, SessionDetail, SessionMessage, SessionStatus, UsageStats, DailyUsage, ProjectUsage};
-use rusqlite::{Connection, params};
+use crate::config::SearchTokenizer;
+use crate::session::{AgentNode, Session, KeyHistory, SessionCursor, SessionDetail, SessionMessage, SessionSearchHit, SessionStatus, SessionTree, UsageStats, DailyUsage, ProjectUsage};
+use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::AppHandle;
@@ -14,8 +16,14 @@ pub enum DatabaseError {
     Sqlite(#[from] rusqlite::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
     #[error("Not found: {0}")]
     NotFound(String),
+    #[error("Invalid dependencies: {0}")]
+    InvalidDependencies(String),
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
 }
 
 /// Task status
@@ -28,6 +36,7 @@ pub enum TaskStatus {
     Completed,
     Failed,
     Skipped,
+    Cancelled,
 }
 
 impl std::fmt::Display for TaskStatus {
@@ -39,6 +48,7 @@ impl std::fmt::Display for TaskStatus {
             TaskStatus::Completed => write!(f, "completed"),
             TaskStatus::Failed => write!(f, "failed"),
             TaskStatus::Skipped => write!(f, "skipped"),
+            TaskStatus::Cancelled => write!(f, "cancelled"),
         }
     }
 }
@@ -53,6 +63,92 @@ impl std::str::FromStr for TaskStatus {
             "completed" => Ok(TaskStatus::Completed),
             "failed" => Ok(TaskStatus::Failed),
             "skipped" => Ok(TaskStatus::Skipped),
+            "cancelled" => Ok(TaskStatus::Cancelled),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How a task's run relates to earlier sessions
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// Start a fresh session
+    #[default]
+    New,
+    /// Resume the task's session (or a dependency's)
+    Resume,
+    /// Continue the most recent session in the project
+    Continue,
+    /// Resume into a new session, leaving the original untouched
+    Fork,
+}
+
+impl std::fmt::Display for ExecutionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionMode::New => write!(f, "new"),
+            ExecutionMode::Resume => write!(f, "resume"),
+            ExecutionMode::Continue => write!(f, "continue"),
+            ExecutionMode::Fork => write!(f, "fork"),
+        }
+    }
+}
+
+impl std::str::FromStr for ExecutionMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "new" => Ok(ExecutionMode::New),
+            "resume" => Ok(ExecutionMode::Resume),
+            "continue" => Ok(ExecutionMode::Continue),
+            "fork" => Ok(ExecutionMode::Fork),
+            _ => Err(()),
+        }
+    }
+}
+
+impl rusqlite::types::ToSql for ExecutionMode {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.to_string().into())
+    }
+}
+
+impl rusqlite::types::FromSql for ExecutionMode {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let mode = value.as_str()?;
+        mode.parse().map_err(|_| {
+            rusqlite::types::FromSqlError::Other(format!("Unknown execution mode: {}", mode).into())
+        })
+    }
+}
+
+/// Which of a task's dependencies must complete before it can run
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyMode {
+    /// Every dependency
+    #[default]
+    All,
+    /// At least one dependency
+    Any,
+}
+
+impl std::fmt::Display for DependencyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyMode::All => write!(f, "all"),
+            DependencyMode::Any => write!(f, "any"),
+        }
+    }
+}
+
+impl std::str::FromStr for DependencyMode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(DependencyMode::All),
+            "any" => Ok(DependencyMode::Any),
             _ => Err(()),
         }
     }
@@ -66,8 +162,11 @@ pub struct Task {
     pub project_path: Option<String>,
     pub status: TaskStatus,
     pub priority: String,
-    pub execution_mode: String,
-    pub depends_on: Option<String>,
+    pub execution_mode: ExecutionMode,
+    /// IDs of the tasks this one waits for (stored as a JSON array)
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub dependency_mode: DependencyMode,
     pub session_id: Option<String>,
     pub system_prompt: Option<String>,
     pub allowed_tools: Option<String>,
@@ -80,12 +179,210 @@ pub struct Task {
     pub result_output: Option<String>,
     pub result_tokens: Option<i64>,
     pub result_cost_usd: Option<f64>,
+    /// Why Alice stopped the run (e.g. budget exceeded)
+    #[serde(default)]
+    pub result_error: Option<String>,
     pub created_at: String,
     pub started_at: Option<String>,
     pub completed_at: Option<String>,
     /// Provider that will execute this task
     #[serde(default)]
     pub provider: crate::providers::ProviderId,
+    /// Runs allowed before a failure sticks (None = queue default)
+    #[serde(default)]
+    pub max_attempts: Option<i32>,
+    /// Delay before the first retry (None = queue default)
+    #[serde(default)]
+    pub retry_delay_secs: Option<i64>,
+    /// Runs started so far
+    #[serde(default)]
+    pub attempt_count: i32,
+    /// Earliest time the queue may run the task again
+    #[serde(default)]
+    pub next_attempt_at: Option<String>,
+    /// When the scheduler next queues the task
+    #[serde(default)]
+    pub run_at: Option<String>,
+    /// Cron expression the task repeats on
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    /// Run in a git worktree (None = queue default)
+    #[serde(default)]
+    pub use_worktree: Option<bool>,
+    /// Worktree the task ran in
+    #[serde(default)]
+    pub worktree_path: Option<String>,
+    /// Branch holding the task's changes
+    #[serde(default)]
+    pub worktree_branch: Option<String>,
+    /// Minutes the task may run (None = queue default, 0 = no limit)
+    #[serde(default)]
+    pub timeout_minutes: Option<i32>,
+    /// Command whose exit code decides whether a run succeeded
+    /// (None = the project's, empty = no verification)
+    #[serde(default)]
+    pub verify_command: Option<String>,
+    /// Task whose failed verification this task was queued to fix
+    #[serde(default)]
+    pub fix_of: Option<String>,
+    /// Session the latest run wrote to, which dependents resume from
+    #[serde(default)]
+    pub output_session_id: Option<String>,
+}
+
+/// A change to a task's settings: omitted fields are left as they are, and
+/// a null clears a setting back to its default
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TaskSettings {
+    #[serde(default)]
+    pub execution_mode: Option<ExecutionMode>,
+    /// Session the task resumes or forks
+    #[serde(default, deserialize_with = "present")]
+    pub session_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub max_budget_usd: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub max_attempts: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub retry_delay_secs: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub timeout_minutes: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "present")]
+    pub verify_command: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub use_worktree: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "present")]
+    pub run_at: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub recurrence: Option<Option<String>>,
+}
+
+/// Deserialize a field that was given, null included, as `Some`
+fn present<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// How one attempt at running a task ended
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskRunStatus {
+    Running,
+    Completed,
+    Failed,
+    /// Stopped by a provider usage limit; the task waits for the reset
+    RateLimited,
+    Cancelled,
+}
+
+impl std::fmt::Display for TaskRunStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskRunStatus::Running => write!(f, "running"),
+            TaskRunStatus::Completed => write!(f, "completed"),
+            TaskRunStatus::Failed => write!(f, "failed"),
+            TaskRunStatus::RateLimited => write!(f, "rate_limited"),
+            TaskRunStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::str::FromStr for TaskRunStatus {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "running" => Ok(TaskRunStatus::Running),
+            "completed" => Ok(TaskRunStatus::Completed),
+            "failed" => Ok(TaskRunStatus::Failed),
+            "rate_limited" => Ok(TaskRunStatus::RateLimited),
+            "cancelled" => Ok(TaskRunStatus::Cancelled),
+            _ => Err(()),
+        }
+    }
+}
+
+/// One attempt at running a task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub id: i64,
+    pub task_id: String,
+    /// 1 for the first attempt since the task was last queued
+    pub attempt: i32,
+    pub status: TaskRunStatus,
+    pub exit_code: Option<i32>,
+    pub error: Option<String>,
+    pub tokens: Option<i64>,
+    pub cost_usd: Option<f64>,
+    pub session_id: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    /// Stdout and stderr of the run, interleaved and tagged with their stream
+    pub log_path: Option<String>,
+    /// Process running the provider CLI, or its terminal wrapper script
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// When that process started, to tell it from a later one given its PID
+    #[serde(default)]
+    pub pid_started_at: Option<String>,
+}
+
+/// A single tool call recorded in `tool_invocations`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    pub session_id: String,
+    pub project_path: String,
+    pub provider: crate::providers::ProviderId,
+    pub tool_name: String,
+    pub input_summary: Option<String>,
+    /// Full shell command, for Bash-like tools
+    pub command: Option<String>,
+    pub started_at: Option<i64>,
+    /// None while the call has no result yet
+    pub duration_ms: Option<i64>,
+    pub is_error: bool,
+}
+
+/// A tool call that wrote a file, with the session and prompt behind it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileTouch {
+    pub session_id: String,
+    /// Parent of a sub-agent session, for jumping to the main conversation
+    pub parent_session_id: Option<String>,
+    pub project_path: String,
+    pub provider: crate::providers::ProviderId,
+    pub file_path: String,
+    pub tool_name: String,
+    pub touched_at: Option<i64>,
+    /// User message the edit was made in response to
+    pub prompt: Option<String>,
+    pub session_first_prompt: Option<String>,
+}
+
+/// Aggregated usage of one tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStat {
+    pub tool_name: String,
+    pub call_count: i64,
+    pub error_count: i64,
+    pub failure_rate: f64,
+    pub avg_duration_ms: Option<f64>,
+    pub max_duration_ms: Option<i64>,
+}
+
+/// Ordering for `get_tool_stats`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolStatsSort {
+    /// Most used first
+    #[default]
+    Calls,
+    /// Highest share of failed calls first
+    FailureRate,
+    /// Slowest on average first
+    Duration,
 }
 
 /// Global database connection (thread-safe)
@@ -96,10 +393,47 @@ static DB: once_cell::sync::OnceCell<Mutex<Connection>> = once_cell::sync::OnceC
 // ============================================================================
 
 /// The canonical SELECT column list for sessions, used by all session queries.
+/// Selected from `session_view`, which joins in the user-owned `session_metadata`.
 const SESSION_COLUMNS: &str =
     "session_id, project_path, project_name, first_prompt, label, tags,
      started_at, last_active_at, last_human_message_at, message_count, total_tokens, total_cost_usd,
-     input_tokens, output_tokens, cache_read_tokens, cache_write_tokens, model, status, provider";
+     input_tokens, output_tokens, cache_read_tokens, cache_write_tokens, model, status, provider,
+     parent_session_id, pinned, notes, archived";
+
+/// Number of columns in `SESSION_COLUMNS`; extra selected columns start at this index.
+const SESSION_COLUMN_COUNT: usize = 23;
+
+/// The canonical SELECT column list for tasks, in the order `map_task_row` reads them.
+const TASK_COLUMNS: &str =
+    "id, prompt, project_path, status, priority, execution_mode, depends_on, session_id,
+     system_prompt, allowed_tools, max_budget_usd, max_turns, notes, tags, sort_order,
+     result_exit_code, result_output, result_tokens, result_cost_usd, created_at, started_at,
+     completed_at, provider, result_error, dependency_mode, max_attempts, retry_delay_secs,
+     attempt_count, next_attempt_at, run_at, recurrence, use_worktree, worktree_path,
+     worktree_branch, timeout_minutes, verify_command, fix_of, output_session_id";
+
+/// The canonical SELECT column list for task runs, in the order `map_task_run_row` reads them.
+const TASK_RUN_COLUMNS: &str =
+    "id, task_id, attempt, status, exit_code, error, tokens, cost_usd, session_id, started_at,
+     finished_at, log_path, pid, pid_started_at";
+
+/// Parser-owned columns of the `sessions` table, written by `upsert_session`.
+const SESSION_TABLE_COLUMNS: &str =
+    "session_id, project_path, project_name, first_prompt,
+     started_at, last_active_at, last_human_message_at, message_count, total_tokens, total_cost_usd,
+     input_tokens, output_tokens, cache_read_tokens, cache_write_tokens, model, status, provider,
+     parent_session_id";
+
+/// Sessions joined with their user metadata. Recreated at startup so it
+/// follows column changes.
+const SESSION_VIEW_SQL: &str = "CREATE VIEW session_view AS
+    SELECT s.session_id, s.project_path, s.project_name, s.first_prompt, m.label, m.tags,
+           s.started_at, s.last_active_at, s.last_human_message_at, s.message_count,
+           s.total_tokens, s.total_cost_usd, s.input_tokens, s.output_tokens,
+           s.cache_read_tokens, s.cache_write_tokens, s.model, s.status, s.provider,
+           s.parent_session_id, COALESCE(m.pinned, 0) AS pinned, m.notes,
+           COALESCE(m.archived, 0) AS archived
+    FROM sessions s LEFT JOIN session_metadata m ON m.session_id = s.session_id";
 
 // ============================================================================
 // SessionStatus helpers (keeps conversion logic in one place)
@@ -134,13 +468,26 @@ fn get_db_path() -> PathBuf {
     crate::platform::get_alice_dir().join("alice.db")
 }
 
-/// Initialize the database
-pub fn init_database(_app: &AppHandle) -> Result<(), DatabaseError> {
+/// Initialize the database, indexing sessions for search with `tokenizer`
+pub fn init_database(_app: &AppHandle, tokenizer: SearchTokenizer) -> Result<(), DatabaseError> {
     let alice_dir = crate::platform::get_alice_dir();
     std::fs::create_dir_all(&alice_dir)?;
 
     let db_path = get_db_path();
     let conn = Connection::open(&db_path)?;
+    prepare_database(&conn, tokenizer)?;
+
+    DB.set(Mutex::new(conn))
+        .map_err(|_| DatabaseError::NotFound("Database already initialized".to_string()))?;
+
+    tracing::info!("Database initialized at {:?}", db_path);
+    Ok(())
+}
+
+/// Create the schema on a connection and bring it up to date
+fn prepare_database(conn: &Connection, tokenizer: SearchTokenizer) -> Result<(), DatabaseError> {
+    // Transcripts and usage records go with their session through ON DELETE CASCADE
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
 
     conn.execute_batch(
         r#"
@@ -164,13 +511,8 @@ pub fn init_database(_app: &AppHandle) -> Result<(), DatabaseError> {
             cache_read_tokens INTEGER DEFAULT 0,
             cache_write_tokens INTEGER DEFAULT 0,
             model TEXT,
-            status TEXT
-        );
-
-        -- Full-text search for sessions
-        CREATE VIRTUAL TABLE IF NOT EXISTS sessions_fts USING fts5(
-            first_prompt, all_prompts, label, tags,
-            content=sessions, content_rowid=rowid
+            status TEXT,
+            parent_session_id TEXT
         );
 
         -- Session messages
@@ -183,6 +525,10 @@ pub fn init_database(_app: &AppHandle) -> Result<(), DatabaseError> {
             tokens_in INTEGER,
             tokens_out INTEGER,
             model TEXT,
+            message_id TEXT,
+            tool_calls TEXT,
+            tool_use_id TEXT,
+            is_error INTEGER DEFAULT 0,
             FOREIGN KEY (session_id) REFERENCES sessions(session_id) ON DELETE CASCADE
         );
 
@@ -234,6 +580,23 @@ pub fn init_database(_app: &AppHandle) -> Result<(), DatabaseError> {
         CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status, sort_order);
         CREATE INDEX IF NOT EXISTS idx_tasks_project ON tasks(project_path, status);
 
+        -- One row per attempt at running a task
+        CREATE TABLE IF NOT EXISTS task_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            exit_code INTEGER,
+            error TEXT,
+            tokens INTEGER,
+            cost_usd REAL,
+            session_id TEXT,
+            started_at TEXT NOT NULL,
+            finished_at TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_task_runs_task ON task_runs(task_id, attempt);
+
         -- Daily reports
         CREATE TABLE IF NOT EXISTS daily_reports (
             id TEXT PRIMARY KEY,
@@ -269,19 +632,107 @@ pub fn init_database(_app: &AppHandle) -> Result<(), DatabaseError> {
         );
 
         CREATE INDEX IF NOT EXISTS idx_favorites_sort ON favorites(sort_order);
+
+        -- Per-file parse cursors for incremental session parsing
+        CREATE TABLE IF NOT EXISTS session_file_cursors (
+            file_path TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            byte_offset INTEGER NOT NULL,
+            state TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
+        -- Keys read from session files, kept out of the cursors so saving one
+        -- does not grow with the file
+        CREATE TABLE IF NOT EXISTS session_file_keys (
+            file_path TEXT NOT NULL REFERENCES session_file_cursors(file_path) ON DELETE CASCADE,
+            digest INTEGER NOT NULL,
+            PRIMARY KEY (file_path, digest)
+        ) WITHOUT ROWID;
+
+        -- Tool invocations derived from stored transcripts (analytics)
+        CREATE TABLE IF NOT EXISTS tool_invocations (
+            id INTEGER PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            tool_use_id TEXT,
+            tool_name TEXT NOT NULL,
+            input_summary TEXT,
+            command TEXT,
+            started_at INTEGER,
+            duration_ms INTEGER,
+            is_error INTEGER DEFAULT 0
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_tool_invocations_session ON tool_invocations(session_id);
+        CREATE INDEX IF NOT EXISTS idx_tool_invocations_project ON tool_invocations(project_path, tool_name);
+
+        -- Files written by tool calls, for "which sessions touched this file"
+        CREATE TABLE IF NOT EXISTS session_file_touches (
+            id INTEGER PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            project_path TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            tool_name TEXT NOT NULL,
+            touched_at INTEGER,
+            prompt TEXT,
+            tool_use_id TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_file_touches_path ON session_file_touches(file_path);
+        CREATE INDEX IF NOT EXISTS idx_file_touches_session ON session_file_touches(session_id);
+
+        -- User-owned session metadata (never written by the parsers)
+        CREATE TABLE IF NOT EXISTS session_metadata (
+            session_id TEXT PRIMARY KEY,
+            label TEXT,
+            tags TEXT,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            notes TEXT,
+            archived INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL
+        );
         "#,
     )?;
 
     // Run migrations (each silently ignores "duplicate column" errors)
-    run_migrations(&conn);
+    run_migrations(conn);
 
-    DB.set(Mutex::new(conn))
-        .map_err(|_| DatabaseError::NotFound("Database already initialized".to_string()))?;
-
-    tracing::info!("Database initialized at {:?}", db_path);
+    ensure_search_index(conn, tokenizer)?;
+    // After the index is built, as the fix updates the project names in it
+    fix_claude_project_paths(conn, &crate::platform::get_claude_dir().join("projects"));
+    backfill_tool_index(conn);
     Ok(())
 }
 
+/// Add tool rows for transcripts stored before tool calls were indexed.
+/// Sessions indexed up to their last message are skipped, so this is a
+/// no-op once every transcript has been through `update_tool_index`.
+fn backfill_tool_index(conn: &Connection) {
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT k.session_id FROM session_index_state k
+         WHERE k.tool_message_id < (SELECT COALESCE(MAX(m.id), 0) FROM session_messages m
+                                    WHERE m.session_id = k.session_id)",
+    ) else {
+        return;
+    };
+    let pending: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map(|rows| rows.filter_map(|r| r.ok()).collect())
+        .unwrap_or_default();
+
+    for session_id in &pending {
+        if let Err(e) = update_tool_index(conn, session_id) {
+            tracing::warn!("Failed to index tool calls of {}: {}", session_id, e);
+        }
+    }
+    if !pending.is_empty() {
+        tracing::info!("Indexed tool calls of {} stored transcripts", pending.len());
+    }
+}
+
 /// Run all schema migrations. Each ALTER TABLE silently fails if the column
 /// already exists, so these are safe to run repeatedly.
 fn run_migrations(conn: &Connection) {
@@ -292,7 +743,31 @@ fn run_migrations(conn: &Connection) {
         "ALTER TABLE sessions ADD COLUMN cache_read_tokens INTEGER DEFAULT 0",
         "ALTER TABLE sessions ADD COLUMN cache_write_tokens INTEGER DEFAULT 0",
         "ALTER TABLE sessions ADD COLUMN provider TEXT NOT NULL DEFAULT 'claude'",
+        "ALTER TABLE sessions ADD COLUMN parent_session_id TEXT",
         "ALTER TABLE tasks ADD COLUMN provider TEXT NOT NULL DEFAULT 'claude'",
+        "ALTER TABLE tasks ADD COLUMN result_error TEXT",
+        "ALTER TABLE tasks ADD COLUMN dependency_mode TEXT NOT NULL DEFAULT 'all'",
+        "ALTER TABLE tasks ADD COLUMN max_attempts INTEGER",
+        "ALTER TABLE tasks ADD COLUMN retry_delay_secs INTEGER",
+        "ALTER TABLE tasks ADD COLUMN attempt_count INTEGER NOT NULL DEFAULT 0",
+        "ALTER TABLE tasks ADD COLUMN next_attempt_at TEXT",
+        "ALTER TABLE tasks ADD COLUMN run_at TEXT",
+        "ALTER TABLE tasks ADD COLUMN recurrence TEXT",
+        "ALTER TABLE tasks ADD COLUMN use_worktree INTEGER",
+        "ALTER TABLE tasks ADD COLUMN worktree_path TEXT",
+        "ALTER TABLE tasks ADD COLUMN worktree_branch TEXT",
+        "ALTER TABLE tasks ADD COLUMN timeout_minutes INTEGER",
+        "ALTER TABLE tasks ADD COLUMN verify_command TEXT",
+        "ALTER TABLE tasks ADD COLUMN fix_of TEXT",
+        "ALTER TABLE tasks ADD COLUMN output_session_id TEXT",
+        "ALTER TABLE task_runs ADD COLUMN log_path TEXT",
+        "ALTER TABLE task_runs ADD COLUMN pid INTEGER",
+        "ALTER TABLE task_runs ADD COLUMN pid_started_at TEXT",
+        "ALTER TABLE session_messages ADD COLUMN message_id TEXT",
+        "ALTER TABLE session_messages ADD COLUMN tool_calls TEXT",
+        "ALTER TABLE session_messages ADD COLUMN tool_use_id TEXT",
+        "ALTER TABLE session_messages ADD COLUMN is_error INTEGER DEFAULT 0",
+        "ALTER TABLE session_index_state ADD COLUMN tool_message_id INTEGER NOT NULL DEFAULT 0",
     ];
 
     for sql in &alter_statements {
@@ -314,6 +789,188 @@ fn run_migrations(conn: &Connection) {
         "CREATE INDEX IF NOT EXISTS idx_tasks_provider ON tasks(provider)",
         [],
     );
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_session_messages_session ON session_messages(session_id)",
+        [],
+    );
+    let _ = conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_sessions_parent ON sessions(parent_session_id)",
+        [],
+    );
+
+    // Superseded by `session_search` (see `ensure_search_index`)
+    let _ = conn.execute("DROP TABLE IF EXISTS sessions_fts", []);
+
+    // Labels and tags used to live on `sessions`, where every re-parse wiped them
+    let _ = conn.execute(
+        "INSERT OR IGNORE INTO session_metadata (session_id, label, tags, updated_at)
+         SELECT session_id, label, tags, ?1 FROM sessions
+         WHERE label IS NOT NULL OR (tags IS NOT NULL AND tags NOT IN ('', '[]'))",
+        params![chrono::Utc::now().timestamp_millis()],
+    );
+    let _ = conn.execute(
+        "UPDATE sessions SET label = NULL, tags = NULL WHERE label IS NOT NULL OR tags IS NOT NULL",
+        [],
+    );
+
+    let _ = conn.execute("DROP VIEW IF EXISTS session_view", []);
+    if let Err(e) = conn.execute(SESSION_VIEW_SQL, []) {
+        tracing::error!("Failed to create session_view: {}", e);
+    }
+}
+
+/// Older versions stored the encoded `~/.claude/projects/<dir>` path as a Claude
+/// session's project path. Point those rows at the working directory recorded in
+/// their transcript. Rows already fixed no longer match, so this runs on every
+/// start; rows without a recorded directory are left for the next parse.
+fn fix_claude_project_paths(conn: &Connection, projects_dir: &std::path::Path) {
+    let prefix = format!("{}{}", projects_dir.to_string_lossy(), std::path::MAIN_SEPARATOR);
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT session_id, project_path FROM sessions
+         WHERE provider = 'claude' AND substr(project_path, 1, length(?1)) = ?1",
+    ) else {
+        return;
+    };
+    let rows: Vec<(String, String)> = stmt
+        .query_map(params![prefix], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map(|rows| rows.filter_map(|r| r.ok()).collect())
+        .unwrap_or_default();
+
+    let mut fixed = 0;
+    for (session_id, stored_path) in rows {
+        let dir = std::path::Path::new(&stored_path);
+        if dir.parent() != Some(projects_dir) {
+            continue;
+        }
+        let session_file = dir.join(format!("{}.jsonl", session_id));
+        let Some(project_path) = crate::session::read_session_cwd(&session_file) else {
+            continue;
+        };
+
+        let update = || -> Result<(), rusqlite::Error> {
+            let tx = conn.unchecked_transaction()?;
+            tx.execute(
+                "UPDATE sessions SET project_path = ?1, project_name = ?2 WHERE session_id = ?3",
+                params![
+                    project_path,
+                    crate::session::extract_project_name(&project_path),
+                    session_id
+                ],
+            )?;
+            update_session_fts(&tx, &session_id)?;
+            // The session's cursor snapshot predates the fix and lacks the recorded cwd
+            tx.execute(
+                "DELETE FROM session_file_cursors WHERE session_id = ?1",
+                params![session_id],
+            )?;
+            tx.commit()
+        };
+        match update() {
+            Ok(()) => fixed += 1,
+            Err(e) => tracing::warn!("Failed to migrate project path of {}: {}", session_id, e),
+        }
+    }
+
+    if fixed > 0 {
+        tracing::info!("Migrated project paths of {} Claude sessions", fixed);
+    }
+}
+
+// ============================================================================
+// Full-text search index
+// ============================================================================
+
+/// FTS5 `tokenize` argument for each tokenizer option
+fn tokenizer_spec(tokenizer: SearchTokenizer) -> &'static str {
+    match tokenizer {
+        SearchTokenizer::Unicode61 => "unicode61 remove_diacritics 2",
+        SearchTokenizer::Trigram => "trigram",
+    }
+}
+
+/// Columns of `session_search` after `session_id`, also used for bm25 weights
+const SEARCH_INDEX_COLUMNS: &str = "first_prompt, label, tags, project_name, transcript";
+
+/// bm25 weights per column (session_id, first_prompt, label, tags, project_name, transcript)
+const SEARCH_RANK_WEIGHTS: &str = "0.0, 4.0, 5.0, 3.0, 2.0, 1.0";
+
+/// Select the metadata rows of the index, keyed below zero by `session_index_state`
+const SEARCH_METADATA_SELECT: &str =
+    "SELECT -k.id, s.session_id, s.first_prompt, s.label, s.tags, s.project_name
+     FROM session_view s JOIN session_index_state k ON k.session_id = s.session_id";
+
+/// Select the transcript rows of the index: one per prompt or reply, keyed by message
+const SEARCH_MESSAGE_SELECT: &str = "SELECT id, session_id, content FROM session_messages
+     WHERE role IN ('user', 'assistant')";
+
+/// Whether a table exists
+fn table_exists(conn: &Connection, name: &str) -> bool {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![name],
+        |_| Ok(()),
+    )
+    .is_ok()
+}
+
+/// Create the search index with the configured tokenizer, rebuilding it
+/// from stored sessions when it is missing or uses a different tokenizer.
+///
+/// A session has a metadata row in the index, keyed by the negated ID of its
+/// `session_index_state` row, and a row per prompt and reply keyed by the
+/// message's ID. The state also records how far each session's messages are
+/// indexed, so that only new ones are added on each update.
+fn ensure_search_index(conn: &Connection, tokenizer: SearchTokenizer) -> Result<(), DatabaseError> {
+    let spec = tokenizer_spec(tokenizer);
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'session_search'",
+            [],
+            |row| row.get(0),
+        )
+        .ok();
+    let has_state = table_exists(conn, "session_index_state");
+
+    if has_state && existing.is_some_and(|sql| sql.contains(&format!("tokenize = '{}'", spec))) {
+        return Ok(());
+    }
+
+    tracing::info!("Building session search index (tokenizer: {})", spec);
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS session_index_state (
+             id INTEGER PRIMARY KEY,
+             session_id TEXT NOT NULL UNIQUE,
+             search_message_id INTEGER NOT NULL DEFAULT 0,
+             tool_message_id INTEGER NOT NULL DEFAULT 0
+         );",
+    )?;
+    conn.execute_batch(&format!(
+        "INSERT OR IGNORE INTO session_index_state (session_id) SELECT session_id FROM sessions;
+         DROP TABLE IF EXISTS session_search;
+         CREATE VIRTUAL TABLE session_search USING fts5(
+             session_id UNINDEXED, {columns},
+             tokenize = '{spec}'
+         );
+         INSERT INTO session_search (rowid, session_id, first_prompt, label, tags, project_name)
+         {metadata};
+         INSERT INTO session_search (rowid, session_id, transcript) {messages};
+         UPDATE session_index_state SET search_message_id = COALESCE(
+             (SELECT MAX(id) FROM session_messages m
+              WHERE m.session_id = session_index_state.session_id),
+             0
+         );",
+        columns = SEARCH_INDEX_COLUMNS,
+        spec = spec,
+        metadata = SEARCH_METADATA_SELECT,
+        messages = SEARCH_MESSAGE_SELECT,
+    ))?;
+    Ok(())
+}
+
+/// Rebuild the search index after the tokenizer setting changes
+pub fn rebuild_search_index(tokenizer: SearchTokenizer) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+    ensure_search_index(&conn, tokenizer)
 }
 
 // ============================================================================
@@ -329,6 +986,105 @@ fn get_db() -> Result<std::sync::MutexGuard<'static, Connection>, DatabaseError>
         })
 }
 
+/// A fresh in-memory database with the current schema
+#[cfg(test)]
+pub(crate) fn test_connection() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    prepare_database(&conn, SearchTokenizer::default()).unwrap();
+    conn
+}
+
+/// Point the global database at a fresh in-memory one. Tests that use it hold
+/// the returned guard, so they run one at a time.
+#[cfg(test)]
+pub(crate) fn reset_test_database() -> std::sync::MutexGuard<'static, ()> {
+    static TEST_DB_LOCK: Mutex<()> = Mutex::new(());
+    let guard = TEST_DB_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let db = DB.get_or_init(|| Mutex::new(Connection::open_in_memory().unwrap()));
+    *db.lock().unwrap_or_else(|e| e.into_inner()) = test_connection();
+    guard
+}
+
+/// A queued task with every optional setting left unset
+#[cfg(test)]
+pub(crate) fn test_task(id: &str) -> Task {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "prompt": "Fix the build",
+        "project_path": "/work/app",
+        "status": "queued",
+        "priority": "medium",
+        "execution_mode": "new",
+        "depends_on": [],
+        "sort_order": 0,
+        "created_at": "2025-06-01T10:00:00Z",
+    }))
+    .unwrap()
+}
+
+/// Store a task as given, bypassing the defaults applied on creation
+#[cfg(test)]
+pub(crate) fn insert_test_task(task: &Task) {
+    let conn = get_db().unwrap();
+    let placeholders: Vec<String> = (1..=38).map(|i| format!("?{}", i)).collect();
+    let sql = format!("INSERT INTO tasks ({}) VALUES ({})", TASK_COLUMNS, placeholders.join(", "));
+    conn.execute(
+        &sql,
+        params![
+            task.id,
+            task.prompt,
+            task.project_path,
+            task.status.to_string(),
+            task.priority,
+            task.execution_mode,
+            serde_json::to_string(&task.depends_on).unwrap(),
+            task.session_id,
+            task.system_prompt,
+            task.allowed_tools,
+            task.max_budget_usd,
+            task.max_turns,
+            task.notes,
+            task.tags,
+            task.sort_order,
+            task.result_exit_code,
+            task.result_output,
+            task.result_tokens,
+            task.result_cost_usd,
+            task.created_at,
+            task.started_at,
+            task.completed_at,
+            task.provider.to_string().to_lowercase(),
+            task.result_error,
+            task.dependency_mode.to_string(),
+            task.max_attempts,
+            task.retry_delay_secs,
+            task.attempt_count,
+            task.next_attempt_at,
+            task.run_at,
+            task.recurrence,
+            task.use_worktree,
+            task.worktree_path,
+            task.worktree_branch,
+            task.timeout_minutes,
+            task.verify_command,
+            task.fix_of,
+            task.output_session_id,
+        ],
+    )
+    .unwrap();
+}
+
+/// Point a run at a log elsewhere, as a test's temporary directory
+#[cfg(test)]
+pub(crate) fn set_test_run_log_path(run_id: i64, log_path: &std::path::Path) {
+    let conn = get_db().unwrap();
+    conn.execute(
+        "UPDATE task_runs SET log_path = ?1 WHERE id = ?2",
+        params![log_path.to_string_lossy(), run_id],
+    )
+    .unwrap();
+}
+
 // ============================================================================
 // Dynamic WHERE clause builder
 // ============================================================================
@@ -383,22 +1139,58 @@ impl WhereBuilder {
 // Search query parsing
 // ============================================================================
 
-/// The set of columns searched against for text queries.
-const SEARCH_FIELDS: [&str; 3] = ["s.first_prompt", "s.label", "s.project_name"];
-
-/// Build a LIKE/NOT LIKE condition across all search fields.
-/// `negate = false` produces `(field1 LIKE ? OR field2 LIKE ? ...)`.
-/// `negate = true`  produces `(field1 NOT LIKE ? AND field2 NOT LIKE ? ...)`.
+/// Index columns matched with LIKE for terms the tokenizer cannot index.
+const SEARCH_FIELDS: [&str; 5] = [
+    "session_search.first_prompt",
+    "session_search.label",
+    "session_search.tags",
+    "session_search.project_name",
+    "session_search.transcript",
+];
+
+/// Build a condition matching sessions with (or, negated, without) an index
+/// row where any search field is LIKE the pattern.
 fn like_across_fields(pattern: &str, negate: bool, params: &mut Vec<String>) -> String {
-    let (op, join) = if negate { ("NOT LIKE", " AND ") } else { ("LIKE", " OR ") };
     let conditions: Vec<String> = SEARCH_FIELDS
         .iter()
         .map(|field| {
             params.push(pattern.to_string());
-            format!("{} {} ?", field, op)
+            format!("{} LIKE ?", field)
         })
         .collect();
-    format!("({})", conditions.join(join))
+    format!(
+        "s.session_id {}IN (SELECT session_id FROM session_search WHERE {})",
+        if negate { "NOT " } else { "" },
+        conditions.join(" OR ")
+    )
+}
+
+/// Condition matching sessions with (or, negated, without) an index row that
+/// matches the FTS5 expression bound to `?`. Terms are matched this way, as a
+/// session's prompts, replies and metadata are indexed in separate rows.
+fn match_condition(negate: bool) -> String {
+    format!(
+        "s.session_id {}IN (SELECT session_id FROM session_search WHERE session_search MATCH ?)",
+        if negate { "NOT " } else { "" }
+    )
+}
+
+/// Condition matching sessions that carry (or, negated, lack) the tag bound to `?`.
+fn tag_condition(negate: bool) -> String {
+    format!(
+        "{}EXISTS (SELECT 1 FROM json_each(s.tags) WHERE json_each.value = ? COLLATE NOCASE)",
+        if negate { "NOT " } else { "" }
+    )
+}
+
+/// Quote a term as an FTS5 phrase so operators and punctuation are taken literally.
+fn fts_phrase(term: &str) -> String {
+    format!("\"{}\"", term.replace('"', "\"\""))
+}
+
+/// The trigram tokenizer cannot match terms shorter than three characters.
+fn is_indexable(term: &str, tokenizer: SearchTokenizer) -> bool {
+    tokenizer != SearchTokenizer::Trigram || term.chars().count() >= 3
 }
 
 /// Tokenize search query, preserving quoted strings as single tokens.
@@ -446,6 +1238,35 @@ fn tokenize_search_query(query: &str) -> Vec<String> {
     tokens
 }
 
+/// A search query translated for the `session_search` index.
+#[derive(Default)]
+struct SearchPlan {
+    /// FTS5 MATCH expression ranking the hits: any of the positive terms
+    match_expr: Option<String>,
+    /// SQL conditions: each positive term when there are several, exclusions
+    /// and terms the tokenizer cannot index
+    conditions: Vec<String>,
+    params: Vec<String>,
+}
+
+impl SearchPlan {
+    fn is_empty(&self) -> bool {
+        self.match_expr.is_none() && self.conditions.is_empty()
+    }
+
+    /// Add the plan's conditions to a WHERE builder, returning the MATCH
+    /// expression that ranks the hits.
+    fn apply(self, wb: &mut WhereBuilder) -> Option<String> {
+        for condition in self.conditions {
+            wb.push_condition(condition);
+        }
+        for param in self.params {
+            wb.push_param(param);
+        }
+        self.match_expr
+    }
+}
+
 /// Parse search query with advanced syntax support.
 ///
 /// Syntax:
@@ -453,56 +1274,91 @@ fn tokenize_search_query(query: &str) -> Vec<String> {
 /// - OR: `term1|term2` (pipe-separated, any can match)
 /// - NOT: `-term` (exclude matches containing this term)
 /// - LITERAL: `"term|with|pipes"` (quoted text, ignoring operators)
+/// - TAG: `tag:foo` / `-tag:foo` (sessions with / without the tag)
 ///
-/// Returns (SQL condition string, parameter values).
-fn parse_search_query(query: &str) -> (String, Vec<String>) {
-    let mut and_conditions: Vec<String> = Vec::new();
-    let mut params: Vec<String> = Vec::new();
+/// Terms become FTS5 phrases; terms too short for the trigram tokenizer
+/// fall back to LIKE conditions over the index columns.
+fn parse_search_query(query: &str, tokenizer: SearchTokenizer) -> SearchPlan {
+    let mut match_terms: Vec<String> = Vec::new();
+    let mut plan = SearchPlan::default();
 
     for token in tokenize_search_query(query) {
         if token.is_empty() {
             continue;
         }
 
+        let (negated, body) = match token.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, token.as_str()),
+        };
+        if let Some(tag) = body.strip_prefix("tag:") {
+            if let Some(tag) = normalize_tag(tag) {
+                plan.conditions.push(tag_condition(negated));
+                plan.params.push(tag);
+            }
+            continue;
+        }
+
         if token.starts_with('"') && token.ends_with('"') && token.len() > 1 {
-            // Literal search - remove quotes and search as-is
+            // Literal search - remove quotes and search as a phrase
             let literal = &token[1..token.len()-1];
-            if !literal.is_empty() {
-                let pattern = format!("%{}%", literal);
-                and_conditions.push(like_across_fields(&pattern, false, &mut params));
+            if literal.is_empty() {
+                continue;
+            }
+            if is_indexable(literal, tokenizer) {
+                match_terms.push(fts_phrase(literal));
+            } else {
+                let condition = like_across_fields(&format!("%{}%", literal), false, &mut plan.params);
+                plan.conditions.push(condition);
             }
         } else if let Some(term) = token.strip_prefix('-') {
-            // NOT condition
-            if !term.is_empty() {
-                let pattern = format!("%{}%", term);
-                and_conditions.push(like_across_fields(&pattern, true, &mut params));
+            // NOT condition (FTS5 NOT needs a left operand, so exclude via subquery)
+            if term.is_empty() {
+                continue;
+            }
+            if is_indexable(term, tokenizer) {
+                plan.conditions.push(match_condition(true));
+                plan.params.push(fts_phrase(term));
+            } else {
+                let condition = like_across_fields(&format!("%{}%", term), true, &mut plan.params);
+                plan.conditions.push(condition);
             }
         } else if token.contains('|') {
             // OR condition
             let or_terms: Vec<&str> = token.split('|').filter(|t| !t.is_empty()).collect();
-            if !or_terms.is_empty() {
+            if or_terms.is_empty() {
+                continue;
+            }
+            if or_terms.iter().all(|term| is_indexable(term, tokenizer)) {
+                let phrases: Vec<String> = or_terms.iter().map(|term| fts_phrase(term)).collect();
+                match_terms.push(format!("({})", phrases.join(" OR ")));
+            } else {
                 let or_conditions: Vec<String> = or_terms
                     .iter()
-                    .map(|term| {
-                        let pattern = format!("%{}%", term);
-                        like_across_fields(&pattern, false, &mut params)
-                    })
+                    .map(|term| like_across_fields(&format!("%{}%", term), false, &mut plan.params))
                     .collect();
-                and_conditions.push(format!("({})", or_conditions.join(" OR ")));
+                plan.conditions.push(format!("({})", or_conditions.join(" OR ")));
             }
-        } else {
+        } else if is_indexable(&token, tokenizer) {
             // Regular AND term
-            let pattern = format!("%{}%", token);
-            and_conditions.push(like_across_fields(&pattern, false, &mut params));
+            match_terms.push(fts_phrase(&token));
+        } else {
+            let condition = like_across_fields(&format!("%{}%", token), false, &mut plan.params);
+            plan.conditions.push(condition);
         }
     }
 
-    if and_conditions.is_empty() {
-        return (String::new(), Vec::new());
+    // Ranking matches any term; with several, each must match some row
+    if match_terms.len() > 1 {
+        for term in &match_terms {
+            plan.conditions.push(match_condition(false));
+            plan.params.push(term.clone());
+        }
     }
-
-    let condition = format!("({})", and_conditions.join(" AND "));
-    (condition, params)
+    if !match_terms.is_empty() {
+        plan.match_expr = Some(match_terms.join(" OR "));
+    }
+    plan
 }
 
 // ============================================================================
@@ -545,9 +1401,29 @@ fn map_session_row(row: &rusqlite::Row) -> Result<Session, rusqlite::Error> {
         model: row.get(16)?,
         status: session_status_from_str(&status_str),
         provider: parse_provider(row, 18),
+        parent_session_id: row.get(19)?,
+        pinned: row.get(20)?,
+        notes: row.get(21)?,
+        archived: row.get(22)?,
     })
 }
 
+/// Read `tasks.depends_on`: a JSON array of IDs, or a single bare ID in rows
+/// written before tasks could have several dependencies.
+fn parse_task_dependencies(value: Option<String>) -> Vec<String> {
+    match value {
+        Some(v) if v.trim_start().starts_with('[') => serde_json::from_str(&v).unwrap_or_default(),
+        Some(v) if !v.is_empty() => vec![v],
+        _ => Vec::new(),
+    }
+}
+
+/// Load a single task by id
+fn select_task(conn: &Connection, id: &str) -> rusqlite::Result<Task> {
+    let sql = format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS);
+    conn.query_row(&sql, params![id], map_task_row)
+}
+
 fn map_task_row(row: &rusqlite::Row) -> Result<Task, rusqlite::Error> {
     let status_str: String = row.get(3)?;
 
@@ -558,7 +1434,11 @@ fn map_task_row(row: &rusqlite::Row) -> Result<Task, rusqlite::Error> {
         status: status_str.parse().unwrap_or(TaskStatus::Backlog),
         priority: row.get(4)?,
         execution_mode: row.get(5)?,
-        depends_on: row.get(6)?,
+        depends_on: parse_task_dependencies(row.get(6)?),
+        dependency_mode: row
+            .get::<_, Option<String>>(24)?
+            .and_then(|m| m.parse().ok())
+            .unwrap_or_default(),
         session_id: row.get(7)?,
         system_prompt: row.get(8)?,
         allowed_tools: row.get(9)?,
@@ -571,10 +1451,45 @@ fn map_task_row(row: &rusqlite::Row) -> Result<Task, rusqlite::Error> {
         result_output: row.get(16)?,
         result_tokens: row.get(17)?,
         result_cost_usd: row.get(18)?,
+        result_error: row.get(23)?,
         created_at: row.get(19)?,
         started_at: row.get(20)?,
         completed_at: row.get(21)?,
         provider: parse_provider(row, 22),
+        max_attempts: row.get(25)?,
+        retry_delay_secs: row.get(26)?,
+        attempt_count: row.get(27)?,
+        next_attempt_at: row.get(28)?,
+        run_at: row.get(29)?,
+        recurrence: row.get(30)?,
+        use_worktree: row.get(31)?,
+        worktree_path: row.get(32)?,
+        worktree_branch: row.get(33)?,
+        timeout_minutes: row.get(34)?,
+        verify_command: row.get(35)?,
+        fix_of: row.get(36)?,
+        output_session_id: row.get(37)?,
+    })
+}
+
+fn map_task_run_row(row: &rusqlite::Row) -> Result<TaskRun, rusqlite::Error> {
+    let status: String = row.get(3)?;
+
+    Ok(TaskRun {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        attempt: row.get(2)?,
+        status: status.parse().unwrap_or(TaskRunStatus::Failed),
+        exit_code: row.get(4)?,
+        error: row.get(5)?,
+        tokens: row.get(6)?,
+        cost_usd: row.get(7)?,
+        session_id: row.get(8)?,
+        started_at: row.get(9)?,
+        finished_at: row.get(10)?,
+        log_path: row.get(11)?,
+        pid: row.get(12)?,
+        pid_started_at: row.get(13)?,
     })
 }
 
@@ -632,11 +1547,84 @@ fn next_sort_order(conn: &Connection, table: &str) -> i32 {
     .unwrap_or(0) + 1
 }
 
-/// Update the FTS index for a session.
+/// How far a session's messages are in the search and tool indexes
+struct SessionIndexState {
+    id: i64,
+    search_message_id: i64,
+    tool_message_id: i64,
+}
+
+/// Load a session's index state, creating it if the session has none
+fn session_index_state(
+    conn: &Connection,
+    session_id: &str,
+) -> Result<SessionIndexState, rusqlite::Error> {
+    conn.execute(
+        "INSERT OR IGNORE INTO session_index_state (session_id) VALUES (?1)",
+        params![session_id],
+    )?;
+    conn.query_row(
+        "SELECT id, search_message_id, tool_message_id FROM session_index_state
+         WHERE session_id = ?1",
+        params![session_id],
+        |row| {
+            Ok(SessionIndexState {
+                id: row.get(0)?,
+                search_message_id: row.get(1)?,
+                tool_message_id: row.get(2)?,
+            })
+        },
+    )
+}
+
+/// Update the metadata row of a session in the FTS index.
 fn update_session_fts(conn: &Connection, session_id: &str) -> Result<(), rusqlite::Error> {
+    let state = session_index_state(conn, session_id)?;
+    conn.execute("DELETE FROM session_search WHERE rowid = ?1", params![-state.id])?;
+    conn.execute(
+        &format!(
+            "INSERT INTO session_search (rowid, session_id, first_prompt, label, tags, project_name)
+             {} WHERE s.session_id = ?1",
+            SEARCH_METADATA_SELECT
+        ),
+        params![session_id],
+    )?;
+    Ok(())
+}
+
+/// Add the messages stored since a session was last indexed to the FTS index
+fn index_session_messages(conn: &Connection, session_id: &str) -> Result<(), rusqlite::Error> {
+    let state = session_index_state(conn, session_id)?;
+    conn.execute(
+        &format!(
+            "INSERT INTO session_search (rowid, session_id, transcript)
+             {} AND session_id = ?1 AND id > ?2",
+            SEARCH_MESSAGE_SELECT
+        ),
+        params![session_id, state.search_message_id],
+    )?;
+    conn.execute(
+        "UPDATE session_index_state SET search_message_id = COALESCE(
+             (SELECT MAX(id) FROM session_messages WHERE session_id = ?1), 0)
+         WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    Ok(())
+}
+
+/// Drop a session's messages from the search and tool indexes, before its
+/// transcript is replaced
+fn clear_message_index(conn: &Connection, session_id: &str) -> Result<(), rusqlite::Error> {
     conn.execute(
-        "INSERT OR REPLACE INTO sessions_fts (rowid, first_prompt, all_prompts, label, tags)
-         SELECT rowid, first_prompt, first_prompt, label, tags FROM sessions WHERE session_id = ?1",
+        "DELETE FROM session_search
+         WHERE rowid IN (SELECT id FROM session_messages WHERE session_id = ?1)",
+        params![session_id],
+    )?;
+    conn.execute("DELETE FROM tool_invocations WHERE session_id = ?1", params![session_id])?;
+    conn.execute("DELETE FROM session_file_touches WHERE session_id = ?1", params![session_id])?;
+    conn.execute(
+        "UPDATE session_index_state SET search_message_id = 0, tool_message_id = 0
+         WHERE session_id = ?1",
         params![session_id],
     )?;
     Ok(())
@@ -648,7 +1636,6 @@ fn update_session_fts(conn: &Connection, session_id: &str) -> Result<(), rusqlit
 
 /// Get sessions from database
 pub fn get_sessions(
-    _app: &AppHandle,
     project: Option<&str>,
     limit: i64,
 ) -> Result<Vec<Session>, DatabaseError> {
@@ -658,10 +1645,13 @@ pub fn get_sessions(
     if let Some(proj) = project {
         wb.push("project_path = ?", proj.to_string());
     }
+    // Sub-agent sessions are listed under their parent (see `get_session_tree`)
+    wb.push_condition("parent_session_id IS NULL".to_string());
+    wb.push_condition("archived = 0".to_string());
     wb.push_param(limit);
 
     let sql = format!(
-        "SELECT {} FROM sessions {} ORDER BY last_human_message_at DESC LIMIT ?",
+        "SELECT {} FROM session_view {} ORDER BY pinned DESC, last_human_message_at DESC LIMIT ?",
         SESSION_COLUMNS,
         wb.to_where_clause()
     );
@@ -683,35 +1673,311 @@ pub fn get_session_detail(
     let conn = get_db()?;
 
     let sql = format!(
-        "SELECT {} FROM sessions WHERE session_id = ?1",
+        "SELECT {} FROM session_view WHERE session_id = ?1",
         SESSION_COLUMNS
     );
     let session = conn.query_row(&sql, params![session_id], map_session_row)?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, role, content, timestamp, tokens_in, tokens_out, model
-         FROM session_messages WHERE session_id = ?1 ORDER BY timestamp ASC"
-    )?;
+    let sql = format!(
+        "SELECT {} FROM session_messages WHERE session_id = ?1 ORDER BY id ASC",
+        MESSAGE_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql)?;
 
     let messages: Vec<SessionMessage> = stmt
-        .query_map(params![session_id], |row| {
-            Ok(SessionMessage {
-                id: row.get(0)?,
-                role: row.get(1)?,
-                content: row.get(2)?,
-                timestamp: row.get(3)?,
-                tokens_in: row.get(4)?,
-                tokens_out: row.get(5)?,
-                model: row.get(6)?,
-                images: vec![], // Images are loaded separately via get_session_images
-            })
-        })?
+        .query_map(params![session_id], map_message_row)?
         .filter_map(|r| r.ok())
         .collect();
 
     Ok(SessionDetail { session, messages })
 }
 
+/// Get a session's detail together with the tree of sub-agent sessions it spawned
+pub fn get_session_tree(app: &AppHandle, session_id: &str) -> Result<SessionTree, DatabaseError> {
+    let detail = get_session_detail(app, session_id)?;
+
+    let conn = get_db()?;
+    let mut visited = HashSet::from([session_id.to_string()]);
+    let agents = get_agent_nodes(&conn, session_id, &mut visited)?;
+
+    Ok(SessionTree {
+        tree_tokens: detail.session.total_tokens
+            + agents.iter().map(|a| a.tree_tokens).sum::<i64>(),
+        tree_cost_usd: detail.session.total_cost_usd
+            + agents.iter().map(|a| a.tree_cost_usd).sum::<f64>(),
+        detail,
+        agents,
+    })
+}
+
+/// Load the sub-agent sessions under `parent_id`, most expensive first.
+/// `visited` guards against cycles in corrupted parent links.
+fn get_agent_nodes(
+    conn: &Connection,
+    parent_id: &str,
+    visited: &mut HashSet<String>,
+) -> Result<Vec<AgentNode>, DatabaseError> {
+    let sql = format!(
+        "SELECT {} FROM session_view WHERE parent_session_id = ?1",
+        SESSION_COLUMNS
+    );
+    let children: Vec<Session> = conn
+        .prepare(&sql)?
+        .query_map(params![parent_id], map_session_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut nodes = Vec::new();
+    for session in children {
+        if !visited.insert(session.session_id.clone()) {
+            continue;
+        }
+        let children = get_agent_nodes(conn, &session.session_id, visited)?;
+        nodes.push(AgentNode {
+            tree_tokens: session.total_tokens + children.iter().map(|c| c.tree_tokens).sum::<i64>(),
+            tree_cost_usd: session.total_cost_usd
+                + children.iter().map(|c| c.tree_cost_usd).sum::<f64>(),
+            session,
+            children,
+        });
+    }
+
+    nodes.sort_by(|a, b| b.tree_cost_usd.total_cmp(&a.tree_cost_usd));
+    Ok(nodes)
+}
+
+/// Column list for session message queries (must match `map_message_row`)
+const MESSAGE_COLUMNS: &str =
+    "message_id, role, content, timestamp, tokens_in, tokens_out, model, tool_calls, tool_use_id, is_error";
+
+/// Map a row selected with `MESSAGE_COLUMNS` to a SessionMessage
+fn map_message_row(row: &rusqlite::Row) -> rusqlite::Result<SessionMessage> {
+    let tool_calls: Option<String> = row.get(7)?;
+    Ok(SessionMessage {
+        id: row.get(0)?,
+        role: row.get(1)?,
+        content: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+        timestamp: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+        tokens_in: row.get(4)?,
+        tokens_out: row.get(5)?,
+        model: row.get(6)?,
+        images: vec![], // Images are loaded separately via get_session_images
+        tool_calls: tool_calls
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        tool_use_id: row.get(8)?,
+        is_error: row.get::<_, Option<i32>>(9)?.unwrap_or(0) != 0,
+    })
+}
+
+/// Insert transcript messages for a session in order
+fn insert_session_messages(
+    tx: &rusqlite::Transaction,
+    session_id: &str,
+    messages: &[SessionMessage],
+) -> Result<(), DatabaseError> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO session_messages
+         (session_id, message_id, role, content, timestamp, tokens_in, tokens_out, model,
+          tool_calls, tool_use_id, is_error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+
+    for msg in messages {
+        stmt.execute(params![
+            session_id,
+            msg.id,
+            msg.role,
+            msg.content,
+            msg.timestamp,
+            msg.tokens_in,
+            msg.tokens_out,
+            msg.model,
+            tool_calls_json(msg),
+            msg.tool_use_id,
+            msg.is_error as i32,
+        ])?;
+    }
+    Ok(())
+}
+
+/// Serialize a message's tool calls for storage (NULL when there are none)
+fn tool_calls_json(msg: &SessionMessage) -> Option<String> {
+    if msg.tool_calls.is_empty() {
+        None
+    } else {
+        serde_json::to_string(&msg.tool_calls).ok()
+    }
+}
+
+/// Add a session's newly stored messages to the search and tool indexes
+fn index_new_messages(conn: &Connection, session_id: &str) -> Result<(), DatabaseError> {
+    index_session_messages(conn, session_id)?;
+    update_tool_index(conn, session_id)
+}
+
+/// Replace the stored transcript of a session with freshly parsed messages
+pub fn replace_session_messages(
+    session_id: &str,
+    messages: &[SessionMessage],
+) -> Result<(), DatabaseError> {
+    let mut conn = get_db()?;
+    let tx = conn.transaction()?;
+
+    clear_message_index(&tx, session_id)?;
+    tx.execute(
+        "DELETE FROM session_messages WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    insert_session_messages(&tx, session_id, messages)?;
+    index_new_messages(&tx, session_id)?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Append newly parsed messages to a session's stored transcript
+pub fn append_session_messages(
+    session_id: &str,
+    messages: &[SessionMessage],
+) -> Result<(), DatabaseError> {
+    let Some(first) = messages.first() else {
+        return Ok(());
+    };
+
+    let mut conn = get_db()?;
+    let tx = conn.transaction()?;
+
+    // A streamed response can straddle two reads: fold its continuation into the stored row
+    let sql = format!(
+        "SELECT {}, id FROM session_messages WHERE session_id = ?1 ORDER BY id DESC LIMIT 1",
+        MESSAGE_COLUMNS
+    );
+    let last = tx
+        .query_row(&sql, params![session_id], |row| {
+            Ok((map_message_row(row)?, row.get::<_, i64>(10)?))
+        })
+        .ok();
+
+    let mut rest = messages;
+    if let Some((mut stored, row_id)) = last {
+        if stored.continues_with(first) {
+            stored.absorb(first.clone());
+            tx.execute(
+                "UPDATE session_messages SET content = ?1, tool_calls = ?2 WHERE id = ?3",
+                params![stored.content, tool_calls_json(&stored), row_id],
+            )?;
+            // Index the grown message again with the new ones
+            tx.execute("DELETE FROM session_search WHERE rowid = ?1", params![row_id])?;
+            tx.execute(
+                "UPDATE session_index_state
+                 SET search_message_id = MIN(search_message_id, ?1),
+                     tool_message_id = MIN(tool_message_id, ?1)
+                 WHERE session_id = ?2",
+                params![row_id - 1, session_id],
+            )?;
+            rest = &messages[1..];
+        }
+    }
+    insert_session_messages(&tx, session_id, rest)?;
+    index_new_messages(&tx, session_id)?;
+
+    tx.commit()?;
+    Ok(())
+}
+
+// ============================================================================
+// Session file cursors
+// ============================================================================
+
+/// Load the parse cursor saved for a session file
+pub fn get_session_cursor(file_path: &str) -> Result<Option<SessionCursor>, DatabaseError> {
+    let conn = get_db()?;
+
+    let state: Option<String> = conn
+        .query_row(
+            "SELECT state FROM session_file_cursors WHERE file_path = ?1",
+            params![file_path],
+            |row| row.get(0),
+        )
+        .ok();
+
+    // An unreadable snapshot (e.g. written by an older version) just forces a full parse
+    Ok(state.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// Save the parse cursor for a session file with the keys first seen since the
+/// last save. A cursor from a full parse replaces the keys recorded before it.
+pub fn save_session_cursor(
+    file_path: &str,
+    session_id: &str,
+    cursor: &SessionCursor,
+    full: bool,
+    new_keys: &[u64],
+) -> Result<(), DatabaseError> {
+    let mut conn = get_db()?;
+    let state = serde_json::to_string(cursor)?;
+    let tx = conn.transaction()?;
+
+    // An upsert rather than REPLACE, which would delete the file's keys with the row
+    tx.execute(
+        "INSERT INTO session_file_cursors (file_path, session_id, byte_offset, state, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(file_path) DO UPDATE SET
+             session_id = excluded.session_id, byte_offset = excluded.byte_offset,
+             state = excluded.state, updated_at = excluded.updated_at",
+        params![
+            file_path,
+            session_id,
+            cursor.offset as i64,
+            state,
+            chrono::Utc::now().timestamp_millis()
+        ],
+    )?;
+    if full {
+        tx.execute("DELETE FROM session_file_keys WHERE file_path = ?1", params![file_path])?;
+    }
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR IGNORE INTO session_file_keys (file_path, digest) VALUES (?1, ?2)",
+        )?;
+        for digest in new_keys {
+            // Stored as the same 64 bits, read as a signed integer
+            insert.execute(params![file_path, *digest as i64])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The keys recorded for one session file
+pub struct SessionFileKeys(HashSet<u64>);
+
+impl KeyHistory for SessionFileKeys {
+    fn contains(&self, digest: u64) -> bool {
+        self.0.contains(&digest)
+    }
+}
+
+/// Load the keys recorded for a session file, once per parse
+pub fn get_session_file_keys(file_path: &str) -> Result<SessionFileKeys, DatabaseError> {
+    let conn = get_db()?;
+    let mut stmt = conn.prepare("SELECT digest FROM session_file_keys WHERE file_path = ?1")?;
+    let keys = stmt
+        .query_map(params![file_path], |row| row.get::<_, i64>(0))?
+        .filter_map(|r| r.ok())
+        .map(|digest| digest as u64)
+        .collect();
+    Ok(SessionFileKeys(keys))
+}
+
+/// Drop all saved cursors so the next scan re-parses every file from the start
+pub fn clear_session_cursors() -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+    conn.execute("DELETE FROM session_file_cursors", [])?;
+    Ok(())
+}
+
 /// Get active sessions - scans file system to find truly active sessions.
 /// This is the source of truth since database status can be stale.
 pub fn get_active_sessions(_app: &AppHandle) -> Result<Vec<Session>, DatabaseError> {
@@ -719,17 +1985,30 @@ pub fn get_active_sessions(_app: &AppHandle) -> Result<Vec<Session>, DatabaseErr
     let projects_dir = crate::platform::get_claude_dir().join("projects");
 
     // Scan file system to find recently modified session files
-    let active_session_ids = find_active_session_ids(&projects_dir);
+    let mut active_session_ids = find_active_session_ids(&projects_dir);
+
+    // Sessions waiting on the user go quiet, so they are kept until they turn stale
+    let stale_cutoff = chrono::Utc::now().timestamp_millis()
+        - crate::session::NEEDS_INPUT_STALE.as_millis() as i64;
+    let _ = conn.execute(
+        "UPDATE sessions SET status = 'completed' WHERE status = 'needs_input' AND last_active_at < ?1",
+        params![stale_cutoff],
+    );
+    let waiting_ids: Vec<String> = conn
+        .prepare("SELECT session_id FROM sessions WHERE status = 'needs_input'")?
+        .query_map([], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
 
-    if active_session_ids.is_empty() {
+    if active_session_ids.is_empty() && waiting_ids.is_empty() {
         let _ = conn.execute("UPDATE sessions SET status = 'completed' WHERE status = 'active'", []);
         return Ok(Vec::new());
     }
 
-    // Update these sessions to active status
+    // Update these sessions to active status (a pending prompt keeps needs_input)
     for session_id in &active_session_ids {
         let _ = conn.execute(
-            "UPDATE sessions SET status = 'active' WHERE session_id = ?",
+            "UPDATE sessions SET status = 'active' WHERE session_id = ? AND status != 'needs_input'",
             params![session_id]
         );
     }
@@ -743,9 +2022,15 @@ pub fn get_active_sessions(_app: &AppHandle) -> Result<Vec<Session>, DatabaseErr
     let params: Vec<&dyn rusqlite::ToSql> = active_session_ids.iter().map(|s| s as &dyn rusqlite::ToSql).collect();
     let _ = conn.execute(&query, params.as_slice());
 
+    for session_id in waiting_ids {
+        if !active_session_ids.contains(&session_id) {
+            active_session_ids.push(session_id);
+        }
+    }
+
     // Fetch the active sessions
     let sql = format!(
-        "SELECT {} FROM sessions WHERE session_id = ?",
+        "SELECT {} FROM session_view WHERE session_id = ? AND parent_session_id IS NULL",
         SESSION_COLUMNS
     );
     let mut result: Vec<Session> = Vec::new();
@@ -797,26 +2082,34 @@ fn find_active_session_ids(projects_dir: &std::path::Path) -> Vec<String> {
     ids
 }
 
-/// Insert or update a session
+/// Insert or update a session, refreshing its row in the search index
 pub fn upsert_session(session: &Session) -> Result<(), DatabaseError> {
-    let conn = get_db()?;
+    let mut conn = get_db()?;
+    let tx = conn.transaction()?;
 
-    let tags_json = serde_json::to_string(&session.tags).unwrap_or_default();
     let provider_str = session.provider.to_string().to_lowercase();
 
-    conn.execute(
+    // Updated in place: replacing the row would cascade to its messages.
+    // Labels, tags and other user metadata live in `session_metadata` and are left untouched.
+    let updates: Vec<String> = SESSION_TABLE_COLUMNS
+        .split(',')
+        .map(str::trim)
+        .filter(|column| *column != "session_id")
+        .map(|column| format!("{0} = excluded.{0}", column))
+        .collect();
+    tx.execute(
         &format!(
-            "INSERT OR REPLACE INTO sessions ({})
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
-            SESSION_COLUMNS
+            "INSERT INTO sessions ({})
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+             ON CONFLICT(session_id) DO UPDATE SET {}",
+            SESSION_TABLE_COLUMNS,
+            updates.join(", ")
         ),
         params![
             session.session_id,
             session.project_path,
             session.project_name,
             session.first_prompt,
-            session.label,
-            tags_json,
             session.started_at,
             session.last_active_at,
             session.last_human_message_at,
@@ -830,13 +2123,235 @@ pub fn upsert_session(session: &Session) -> Result<(), DatabaseError> {
             session.model,
             session_status_to_str(&session.status),
             provider_str,
+            session.parent_session_id,
         ],
     )?;
 
-    update_session_fts(&conn, &session.session_id)?;
+    update_session_fts(&tx, &session.session_id)?;
+
+    tx.commit()?;
     Ok(())
 }
 
+/// Longest prompt stored with a file touch
+const TOUCH_PROMPT_CHARS: usize = 500;
+
+/// Add the tool calls of messages stored since a session was last indexed
+/// to its `tool_invocations` and `session_file_touches` rows. Results that
+/// arrive later complete the calls already recorded.
+fn update_tool_index(conn: &Connection, session_id: &str) -> Result<(), DatabaseError> {
+    let Some((project_path, provider)) = conn
+        .query_row(
+            "SELECT project_path, provider FROM sessions WHERE session_id = ?1",
+            params![session_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+        )
+        .optional()?
+    else {
+        return Ok(());
+    };
+    let indexed = session_index_state(conn, session_id)?.tool_message_id;
+
+    let mut stmt = conn.prepare(
+        "SELECT role, timestamp, tool_calls, tool_use_id, is_error,
+                CASE WHEN role = 'user' THEN content END
+         FROM session_messages
+         WHERE session_id = ?1 AND id > ?2
+           AND (role = 'user' OR tool_calls IS NOT NULL OR tool_use_id IS NOT NULL)
+         ORDER BY id ASC",
+    )?;
+    type Row = (String, Option<i64>, Option<String>, Option<String>, Option<i32>, Option<String>);
+    let rows: Vec<Row> = stmt
+        .query_map(params![session_id, indexed], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Result time and error flag of each answered call
+    let results: HashMap<&str, (Option<i64>, bool)> = rows
+        .iter()
+        .filter(|(role, ..)| role == "tool")
+        .filter_map(|(_, timestamp, _, tool_use_id, is_error, _)| {
+            Some((tool_use_id.as_deref()?, (*timestamp, is_error.unwrap_or(0) != 0)))
+        })
+        .collect();
+
+    // Calls recorded earlier that are answered now
+    for (tool_use_id, (finished_at, is_error)) in &results {
+        conn.execute(
+            "UPDATE tool_invocations
+             SET is_error = ?1,
+                 duration_ms = CASE WHEN started_at > 0 AND ?2 >= started_at
+                                    THEN ?2 - started_at END
+             WHERE session_id = ?3 AND tool_use_id = ?4",
+            params![*is_error as i32, finished_at, session_id, tool_use_id],
+        )?;
+        // A failed edit changed nothing
+        if *is_error {
+            conn.execute(
+                "DELETE FROM session_file_touches WHERE session_id = ?1 AND tool_use_id = ?2",
+                params![session_id, tool_use_id],
+            )?;
+        }
+    }
+
+    let project_dir = std::path::Path::new(&project_path);
+    let mut insert_invocation = conn.prepare(
+        "INSERT INTO tool_invocations
+         (session_id, project_path, provider, tool_use_id, tool_name, input_summary, command,
+          started_at, duration_ms, is_error)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut insert_touch = conn.prepare(
+        "INSERT INTO session_file_touches
+         (session_id, project_path, provider, file_path, tool_name, touched_at, prompt,
+          tool_use_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?;
+    let mut recorded = conn.prepare(
+        "SELECT 1 FROM tool_invocations WHERE session_id = ?1 AND tool_use_id = ?2",
+    )?;
+
+    // The prompt in effect where the new messages start
+    let mut prompt: Option<String> = conn
+        .query_row(
+            "SELECT content FROM session_messages
+             WHERE session_id = ?1 AND id <= ?2 AND role = 'user'
+             ORDER BY id DESC LIMIT 1",
+            params![session_id, indexed],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()?
+        .flatten()
+        .map(|c| c.trim().chars().take(TOUCH_PROMPT_CHARS).collect())
+        .filter(|c: &String| !c.is_empty());
+    for (role, timestamp, tool_calls, _, _, content) in &rows {
+        if role == "user" {
+            prompt = content
+                .as_deref()
+                .map(str::trim)
+                .filter(|c| !c.is_empty())
+                .map(|c| c.chars().take(TOUCH_PROMPT_CHARS).collect());
+            continue;
+        }
+        let Some(tool_calls) = tool_calls.as_deref().filter(|_| role == "assistant") else {
+            continue;
+        };
+        let tool_calls: Vec<crate::session::ToolCall> =
+            serde_json::from_str(tool_calls).unwrap_or_default();
+        let started_at = timestamp.filter(|ts| *ts > 0);
+
+        for call in tool_calls {
+            // A message that grew across reads is indexed again
+            if let Some(id) = call.id.as_deref() {
+                if recorded.exists(params![session_id, id])? {
+                    continue;
+                }
+            }
+            let result = call.id.as_deref().and_then(|id| results.get(id));
+            let is_error = result.is_some_and(|(_, is_error)| *is_error);
+            let duration_ms = match (started_at, result.and_then(|(ts, _)| *ts)) {
+                (Some(start), Some(end)) if end >= start => Some(end - start),
+                _ => None,
+            };
+            insert_invocation.execute(params![
+                session_id,
+                project_path,
+                provider,
+                call.id,
+                call.name,
+                call.input_summary(),
+                call.shell_command(),
+                started_at,
+                duration_ms,
+                is_error as i32,
+            ])?;
+
+            // A failed edit changed nothing
+            if is_error {
+                continue;
+            }
+            for file in call.modified_files() {
+                let file_path = project_dir
+                    .join(file.trim_start_matches("./"))
+                    .to_string_lossy()
+                    .to_string();
+                insert_touch.execute(params![
+                    session_id,
+                    project_path,
+                    provider,
+                    file_path,
+                    call.name,
+                    started_at,
+                    prompt,
+                    call.id,
+                ])?;
+            }
+        }
+    }
+
+    conn.execute(
+        "UPDATE session_index_state SET tool_message_id = COALESCE(
+             (SELECT MAX(id) FROM session_messages WHERE session_id = ?1), 0)
+         WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    Ok(())
+}
+
+/// Look up a session's stored status
+pub fn get_session_status(session_id: &str) -> Result<Option<SessionStatus>, DatabaseError> {
+    let conn = get_db()?;
+
+    let status: Option<String> = conn
+        .query_row(
+            "SELECT status FROM sessions WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(status.as_deref().map(session_status_from_str))
+}
+
+/// Overwrite a session's status (e.g. from a hook event) until its file is parsed again
+pub fn set_session_status(session_id: &str, status: &SessionStatus) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    conn.execute(
+        "UPDATE sessions SET status = ?1 WHERE session_id = ?2",
+        params![session_status_to_str(status), session_id],
+    )?;
+    Ok(())
+}
+
+/// Find the session a hook event names, if it has been indexed
+pub fn find_hook_session(session_id: &str) -> Result<Option<Session>, DatabaseError> {
+    let conn = get_db()?;
+
+    let sql = format!("SELECT {} FROM session_view WHERE session_id = ?1", SESSION_COLUMNS);
+    Ok(conn.query_row(&sql, params![session_id], map_session_row).optional()?)
+}
+
+/// Most recently active top-level session of a provider in a project
+pub fn get_latest_project_session_id(
+    project_path: &str,
+    provider: crate::providers::ProviderId,
+) -> Result<Option<String>, DatabaseError> {
+    let conn = get_db()?;
+
+    Ok(conn
+        .query_row(
+            "SELECT session_id FROM sessions
+             WHERE project_path = ?1 AND provider = ?2 AND parent_session_id IS NULL
+             ORDER BY last_active_at DESC LIMIT 1",
+            params![project_path, provider.to_string().to_lowercase()],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
 /// Update session label
 pub fn update_session_label(
     _app: &AppHandle,
@@ -845,15 +2360,62 @@ pub fn update_session_label(
 ) -> Result<(), DatabaseError> {
     let conn = get_db()?;
 
+    ensure_session_metadata(&conn, session_id)?;
     conn.execute(
-        "UPDATE sessions SET label = ?1 WHERE session_id = ?2",
-        params![label, session_id],
+        "UPDATE session_metadata SET label = ?1, updated_at = ?2 WHERE session_id = ?3",
+        params![label, chrono::Utc::now().timestamp_millis(), session_id],
     )?;
 
     update_session_fts(&conn, session_id)?;
     Ok(())
 }
 
+/// Create an empty `session_metadata` row for a session if it has none
+fn ensure_session_metadata(conn: &Connection, session_id: &str) -> Result<(), DatabaseError> {
+    conn.execute(
+        "INSERT OR IGNORE INTO session_metadata (session_id, updated_at) VALUES (?1, ?2)",
+        params![session_id, chrono::Utc::now().timestamp_millis()],
+    )?;
+    Ok(())
+}
+
+/// Pin or unpin a session; pinned sessions are listed first
+pub fn set_session_pinned(session_id: &str, pinned: bool) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    ensure_session_metadata(&conn, session_id)?;
+    conn.execute(
+        "UPDATE session_metadata SET pinned = ?1, updated_at = ?2 WHERE session_id = ?3",
+        params![pinned, chrono::Utc::now().timestamp_millis(), session_id],
+    )?;
+    Ok(())
+}
+
+/// Set or clear a session's free-form notes
+pub fn set_session_notes(session_id: &str, notes: Option<&str>) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    let notes = notes.map(str::trim).filter(|n| !n.is_empty());
+    ensure_session_metadata(&conn, session_id)?;
+    conn.execute(
+        "UPDATE session_metadata SET notes = ?1, updated_at = ?2 WHERE session_id = ?3",
+        params![notes, chrono::Utc::now().timestamp_millis(), session_id],
+    )?;
+    Ok(())
+}
+
+/// Archive or restore a session; archived sessions are hidden from lists and search
+pub fn set_session_archived(session_id: &str, archived: bool) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    ensure_session_metadata(&conn, session_id)?;
+    conn.execute(
+        "UPDATE session_metadata SET archived = ?1, updated_at = ?2 WHERE session_id = ?3",
+        params![archived, chrono::Utc::now().timestamp_millis(), session_id],
+    )?;
+    Ok(())
+}
+
 /// Delete a session and its messages
 pub fn delete_session(
     _app: &AppHandle,
@@ -862,13 +2424,33 @@ pub fn delete_session(
     let conn = get_db()?;
 
     conn.execute(
-        "DELETE FROM sessions_fts WHERE rowid = (SELECT rowid FROM sessions WHERE session_id = ?1)",
+        "DELETE FROM session_search WHERE session_id = ?1",
         params![session_id],
     )?;
     conn.execute(
         "DELETE FROM session_messages WHERE session_id = ?1",
         params![session_id],
     )?;
+    conn.execute(
+        "DELETE FROM session_file_cursors WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    conn.execute(
+        "DELETE FROM tool_invocations WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    conn.execute(
+        "DELETE FROM session_file_touches WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    conn.execute(
+        "DELETE FROM session_metadata WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    conn.execute(
+        "DELETE FROM session_index_state WHERE session_id = ?1",
+        params![session_id],
+    )?;
     conn.execute(
         "DELETE FROM sessions WHERE session_id = ?1",
         params![session_id],
@@ -877,6 +2459,162 @@ pub fn delete_session(
     Ok(())
 }
 
+// ============================================================================
+// Session tags
+// ============================================================================
+
+/// Trim a tag and drop a leading `#`; None if nothing is left.
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').trim();
+    (!tag.is_empty()).then(|| tag.to_string())
+}
+
+/// Add tags to a list, skipping case-insensitive duplicates.
+fn merge_tags(tags: &mut Vec<String>, new_tags: &[String]) {
+    for tag in new_tags.iter().filter_map(|t| normalize_tag(t)) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            tags.push(tag);
+        }
+    }
+}
+
+fn read_session_tags(conn: &Connection, session_id: &str) -> Result<Vec<String>, DatabaseError> {
+    let tags: Option<String> = conn
+        .query_row(
+            "SELECT tags FROM session_metadata WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(tags
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default())
+}
+
+fn write_session_tags(
+    conn: &Connection,
+    session_id: &str,
+    tags: &[String],
+) -> Result<(), DatabaseError> {
+    ensure_session_metadata(conn, session_id)?;
+    conn.execute(
+        "UPDATE session_metadata SET tags = ?1, updated_at = ?2 WHERE session_id = ?3",
+        params![serde_json::to_string(tags)?, chrono::Utc::now().timestamp_millis(), session_id],
+    )?;
+    update_session_fts(conn, session_id)?;
+    Ok(())
+}
+
+/// Add tags to a session. Returns the session's tags.
+pub fn add_session_tags(
+    session_id: &str,
+    tags: &[String],
+) -> Result<Vec<String>, DatabaseError> {
+    let conn = get_db()?;
+
+    let mut current = read_session_tags(&conn, session_id)?;
+    merge_tags(&mut current, tags);
+    write_session_tags(&conn, session_id, &current)?;
+    Ok(current)
+}
+
+/// Remove tags (case-insensitively) from a session. Returns the session's tags.
+pub fn remove_session_tags(
+    session_id: &str,
+    tags: &[String],
+) -> Result<Vec<String>, DatabaseError> {
+    let conn = get_db()?;
+
+    let removed: Vec<String> = tags.iter().filter_map(|t| normalize_tag(t)).collect();
+    let mut current = read_session_tags(&conn, session_id)?;
+    current.retain(|t| !removed.iter().any(|r| r.eq_ignore_ascii_case(t)));
+    write_session_tags(&conn, session_id, &current)?;
+    Ok(current)
+}
+
+/// Replace a session's tags. Returns the normalized tags.
+pub fn set_session_tags(
+    session_id: &str,
+    tags: &[String],
+) -> Result<Vec<String>, DatabaseError> {
+    let conn = get_db()?;
+
+    let mut normalized = Vec::new();
+    merge_tags(&mut normalized, tags);
+    write_session_tags(&conn, session_id, &normalized)?;
+    Ok(normalized)
+}
+
+/// A tag and the number of sessions carrying it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// All tags in use, most used first
+pub fn get_tag_counts() -> Result<Vec<TagCount>, DatabaseError> {
+    let conn = get_db()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT json_each.value, COUNT(*) FROM session_metadata m, json_each(m.tags)
+         WHERE json_valid(m.tags)
+         GROUP BY json_each.value COLLATE NOCASE
+         ORDER BY COUNT(*) DESC, json_each.value COLLATE NOCASE",
+    )?;
+    let counts = stmt
+        .query_map([], |row| Ok(TagCount { tag: row.get(0)?, count: row.get(1)? }))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(counts)
+}
+
+/// Apply `edit` to the tags of every session carrying `tag`. Returns the number of sessions changed.
+fn edit_tag_everywhere(
+    conn: &Connection,
+    tag: &str,
+    edit: impl Fn(&mut Vec<String>),
+) -> Result<usize, DatabaseError> {
+    let sql = format!("SELECT s.session_id FROM session_metadata s WHERE {}", tag_condition(false));
+    let session_ids: Vec<String> = conn
+        .prepare(&sql)?
+        .query_map(params![tag], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    for session_id in &session_ids {
+        let mut tags = read_session_tags(conn, session_id)?;
+        edit(&mut tags);
+        write_session_tags(conn, session_id, &tags)?;
+    }
+    Ok(session_ids.len())
+}
+
+/// Rename a tag on every session (merging into `to` where both are present)
+pub fn rename_tag(from: &str, to: &str) -> Result<usize, DatabaseError> {
+    let (Some(from), Some(to)) = (normalize_tag(from), normalize_tag(to)) else {
+        return Ok(0);
+    };
+    let conn = get_db()?;
+
+    edit_tag_everywhere(&conn, &from, |tags| {
+        tags.retain(|t| !t.eq_ignore_ascii_case(&from));
+        merge_tags(tags, std::slice::from_ref(&to));
+    })
+}
+
+/// Remove a tag from every session
+pub fn delete_tag(tag: &str) -> Result<usize, DatabaseError> {
+    let Some(tag) = normalize_tag(tag) else {
+        return Ok(0);
+    };
+    let conn = get_db()?;
+
+    edit_tag_everywhere(&conn, &tag, |tags| tags.retain(|t| !t.eq_ignore_ascii_case(&tag)))
+}
+
 /// Get sessions for a specific date (for daily reports)
 pub fn get_sessions_by_date(_app: &AppHandle, date: &str) -> Result<Vec<Session>, DatabaseError> {
     let conn = get_db()?;
@@ -885,7 +2623,7 @@ pub fn get_sessions_by_date(_app: &AppHandle, date: &str) -> Result<Vec<Session>
     let end_ms = date_to_end_ms(date).unwrap_or(i64::MAX);
 
     let sql = format!(
-        "SELECT {} FROM sessions
+        "SELECT {} FROM session_view
          WHERE started_at >= ?1 AND started_at <= ?2
          ORDER BY last_human_message_at DESC",
         SESSION_COLUMNS
@@ -904,123 +2642,363 @@ pub fn get_sessions_by_date(_app: &AppHandle, date: &str) -> Result<Vec<Session>
 // Session search
 // ============================================================================
 
-/// Search sessions with advanced syntax support.
-/// Syntax: `term1 term2` (AND), `term1|term2` (OR), `-term` (NOT)
-pub fn search_sessions(
-    _app: &AppHandle,
-    query: &str,
-    project: Option<&str>,
-    limit: i64,
-) -> Result<Vec<Session>, DatabaseError> {
-    let conn = get_db()?;
+/// Marks the start/end of a highlighted term in FTS5 snippets
+const HIGHLIGHT_OPEN: char = '\u{2}';
+const HIGHLIGHT_CLOSE: char = '\u{3}';
 
-    let trimmed = query.trim();
-    if trimmed.is_empty() {
-        return Ok(Vec::new());
-    }
+/// Strip highlight markers from a snippet, returning the plain text and
+/// `[start, end)` offsets of each highlighted run in UTF-16 code units.
+fn extract_highlights(marked: &str) -> (String, Vec<(usize, usize)>) {
+    let mut text = String::with_capacity(marked.len());
+    let mut highlights = Vec::new();
+    let mut position = 0;
+    let mut start = None;
 
-    let (search_condition, search_params) = parse_search_query(trimmed);
-    if search_condition.is_empty() {
-        return Ok(Vec::new());
+    for ch in marked.chars() {
+        match ch {
+            HIGHLIGHT_OPEN => start = Some(position),
+            HIGHLIGHT_CLOSE => {
+                if let Some(begin) = start.take() {
+                    highlights.push((begin, position));
+                }
+            }
+            _ => {
+                text.push(ch);
+                position += ch.len_utf16();
+            }
+        }
     }
 
-    let mut params: Vec<Box<dyn rusqlite::ToSql>> = search_params
-        .into_iter()
-        .map(|p| Box::new(p) as Box<dyn rusqlite::ToSql>)
-        .collect();
+    (text, highlights)
+}
+
+/// Map a row of `SESSION_COLUMNS` followed by rank and marked snippet.
+fn map_search_hit_row(row: &rusqlite::Row) -> Result<SessionSearchHit, rusqlite::Error> {
+    let session = map_session_row(row)?;
+    let rank: Option<f64> = row.get(SESSION_COLUMN_COUNT)?;
+    let marked: Option<String> = row.get(SESSION_COLUMN_COUNT + 1)?;
+    let (snippet, highlights) = match marked {
+        Some(marked) => {
+            let (text, highlights) = extract_highlights(&marked);
+            (Some(text), highlights)
+        }
+        None => (None, Vec::new()),
+    };
+
+    Ok(SessionSearchHit { session, rank, snippet, highlights })
+}
 
-    let where_clause = if let Some(proj) = project {
-        params.push(Box::new(proj.to_string()));
-        format!("{} AND s.project_path = ?", search_condition)
+/// Run a session query built from `wb`. With a MATCH expression the results
+/// are ranked by bm25 and carry snippets, both from each session's best
+/// matching index row; otherwise they are ordered by recency.
+fn query_session_hits(
+    conn: &Connection,
+    mut wb: WhereBuilder,
+    match_expr: Option<String>,
+    limit: i64,
+) -> Result<Vec<SessionSearchHit>, DatabaseError> {
+    let columns = SESSION_COLUMNS
+        .split(',')
+        .map(|c| format!("s.{}", c.trim()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // FTS5 functions cannot run in an aggregate: the LIMIT keeps SQLite from
+    // folding the ranked rows into the grouping query
+    let (from, extra_columns, order_by) = if match_expr.is_some() {
+        (
+            format!(
+                "session_view s JOIN (
+                     SELECT session_id, MIN(rank) AS rank, marked FROM (
+                         SELECT session_id, bm25(session_search, {}) AS rank,
+                                snippet(session_search, -1, char(2), char(3), '…', 16) AS marked
+                         FROM session_search WHERE session_search MATCH ? LIMIT -1
+                     ) GROUP BY session_id
+                 ) hit ON hit.session_id = s.session_id",
+                SEARCH_RANK_WEIGHTS
+            ),
+            "hit.rank, hit.marked",
+            "hit.rank ASC",
+        )
     } else {
-        search_condition
+        ("session_view s".to_string(), "NULL, NULL", "s.last_human_message_at DESC")
     };
 
-    params.push(Box::new(limit));
+    wb.push_condition("s.parent_session_id IS NULL".to_string());
+
+    let where_clause = wb.to_where_clause();
+    wb.push_param(limit);
 
     let sql = format!(
-        "SELECT {} FROM sessions s WHERE {} ORDER BY last_human_message_at DESC LIMIT ?",
-        SESSION_COLUMNS, where_clause
+        "SELECT {}, {} FROM {} {} ORDER BY {} LIMIT ?",
+        columns, extra_columns, from, where_clause, order_by
     );
 
+    // The MATCH in the join comes before the WHERE parameters
+    let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+    if let Some(expr) = &match_expr {
+        params.push(expr);
+    }
+    params.extend(wb.param_refs());
+
     let mut stmt = conn.prepare(&sql)?;
-    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-    let sessions: Vec<Session> = stmt
-        .query_map(rusqlite::params_from_iter(param_refs), map_session_row)?
+    let hits = stmt
+        .query_map(params.as_slice(), map_search_hit_row)?
         .filter_map(|r| r.ok())
         .collect();
 
-    Ok(sessions)
+    Ok(hits)
+}
+
+/// Translate a raw query into a search plan, or None when it has no usable terms.
+fn plan_search(query: Option<&str>, tokenizer: SearchTokenizer) -> Option<SearchPlan> {
+    let trimmed = query?.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    let plan = parse_search_query(trimmed, tokenizer);
+    (!plan.is_empty()).then_some(plan)
+}
+
+/// Search sessions with advanced syntax support.
+/// Syntax: `term1 term2` (AND), `term1|term2` (OR), `-term` (NOT)
+pub fn search_sessions(
+    query: &str,
+    project: Option<&str>,
+    limit: i64,
+    tokenizer: SearchTokenizer,
+) -> Result<Vec<SessionSearchHit>, DatabaseError> {
+    let conn = get_db()?;
+
+    let Some(plan) = plan_search(Some(query), tokenizer) else {
+        return Ok(Vec::new());
+    };
+
+    let mut wb = WhereBuilder::new();
+    let match_expr = plan.apply(&mut wb);
+    if let Some(proj) = project {
+        wb.push("s.project_path = ?", proj.to_string());
+    }
+    wb.push_condition("s.archived = 0".to_string());
+
+    query_session_hits(&conn, wb, match_expr, limit)
 }
 
 /// Search sessions with advanced filters
 pub fn search_sessions_filtered(
-    _app: &AppHandle,
     query: Option<&str>,
     project: Option<&str>,
     status: Option<&str>,
     model: Option<&str>,
     date_from: Option<&str>,
     date_to: Option<&str>,
+    tags: Option<&[String]>,
     limit: i64,
-) -> Result<Vec<Session>, DatabaseError> {
+    tokenizer: SearchTokenizer,
+) -> Result<Vec<SessionSearchHit>, DatabaseError> {
     let conn = get_db()?;
 
     let mut wb = WhereBuilder::new();
 
     // Text search with advanced syntax
-    if let Some(q) = query {
-        let trimmed = q.trim();
-        if !trimmed.is_empty() {
-            let (search_condition, search_params) = parse_search_query(trimmed);
-            if !search_condition.is_empty() {
-                wb.push_condition(search_condition);
-                for p in search_params {
-                    wb.push_param(p);
-                }
-            }
-        }
-    }
+    let match_expr = plan_search(query, tokenizer).and_then(|plan| plan.apply(&mut wb));
 
     if let Some(p) = project {
         wb.push("s.project_path = ?", p.to_string());
     }
 
-    if let Some(s) = status {
-        wb.push("s.status = ?", s.to_string());
+    // Archived sessions only show up when asked for
+    match status {
+        Some("archived") => wb.push_condition("s.archived = 1".to_string()),
+        Some(s) => {
+            wb.push("s.status = ?", s.to_string());
+            wb.push_condition("s.archived = 0".to_string());
+        }
+        None => wb.push_condition("s.archived = 0".to_string()),
     }
 
     if let Some(m) = model {
         wb.push("s.model LIKE ?", format!("%{}%", m));
     }
 
-    if let Some(from) = date_from {
-        if let Some(ts) = date_to_start_ms(from) {
-            wb.push("s.last_active_at >= ?", ts);
-        }
-    }
+    if let Some(from) = date_from {
+        if let Some(ts) = date_to_start_ms(from) {
+            wb.push("s.last_active_at >= ?", ts);
+        }
+    }
+
+    if let Some(to) = date_to {
+        if let Some(ts) = date_to_end_ms(to) {
+            wb.push("s.last_active_at <= ?", ts);
+        }
+    }
+
+    // Every listed tag must be present
+    for tag in tags.unwrap_or_default().iter().filter_map(|t| normalize_tag(t)) {
+        wb.push(&tag_condition(false), tag);
+    }
+
+    query_session_hits(&conn, wb, match_expr, limit)
+}
+
+// ============================================================================
+// Tool analytics
+// ============================================================================
+
+/// Filter tool invocations by project and provider
+fn tool_filter(project: Option<&str>, provider: Option<&str>) -> WhereBuilder {
+    let mut wb = WhereBuilder::new();
+    if let Some(project) = project {
+        wb.push("project_path = ?", project.to_string());
+    }
+    if let Some(provider) = provider {
+        wb.push("provider = ?", provider.to_lowercase());
+    }
+    wb
+}
+
+/// Per-tool call counts, failure rates and durations
+pub fn get_tool_stats(
+    project: Option<&str>,
+    provider: Option<&str>,
+    sort: ToolStatsSort,
+    limit: i64,
+) -> Result<Vec<ToolStat>, DatabaseError> {
+    let conn = get_db()?;
+
+    let mut wb = tool_filter(project, provider);
+    let where_clause = wb.to_where_clause();
+    wb.push_param(limit);
+
+    let order_by = match sort {
+        ToolStatsSort::Calls => "call_count DESC",
+        ToolStatsSort::FailureRate => "failure_rate DESC, call_count DESC",
+        ToolStatsSort::Duration => "avg_duration_ms DESC",
+    };
+    let sql = format!(
+        "SELECT tool_name, COUNT(*) AS call_count, SUM(is_error) AS error_count,
+                CAST(SUM(is_error) AS REAL) / COUNT(*) AS failure_rate,
+                AVG(duration_ms) AS avg_duration_ms, MAX(duration_ms)
+         FROM tool_invocations {} GROUP BY tool_name ORDER BY {} LIMIT ?",
+        where_clause, order_by
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let stats = stmt
+        .query_map(rusqlite::params_from_iter(wb.param_refs()), |row| {
+            Ok(ToolStat {
+                tool_name: row.get(0)?,
+                call_count: row.get(1)?,
+                error_count: row.get(2)?,
+                failure_rate: row.get(3)?,
+                avg_duration_ms: row.get(4)?,
+                max_duration_ms: row.get(5)?,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(stats)
+}
+
+/// Shell commands run by agents, most recent first
+pub fn get_shell_commands(
+    project: Option<&str>,
+    provider: Option<&str>,
+    limit: i64,
+) -> Result<Vec<ToolInvocation>, DatabaseError> {
+    let conn = get_db()?;
+
+    let mut wb = tool_filter(project, provider);
+    wb.push_condition("command IS NOT NULL".to_string());
+    let where_clause = wb.to_where_clause();
+    wb.push_param(limit);
+
+    let sql = format!(
+        "SELECT session_id, project_path, provider, tool_name, input_summary, command,
+                started_at, duration_ms, is_error
+         FROM tool_invocations {} ORDER BY started_at DESC LIMIT ?",
+        where_clause
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let commands = stmt
+        .query_map(rusqlite::params_from_iter(wb.param_refs()), |row| {
+            Ok(ToolInvocation {
+                session_id: row.get(0)?,
+                project_path: row.get(1)?,
+                provider: parse_provider(row, 2),
+                tool_name: row.get(3)?,
+                input_summary: row.get(4)?,
+                command: row.get(5)?,
+                started_at: row.get(6)?,
+                duration_ms: row.get(7)?,
+                is_error: row.get::<_, Option<i32>>(8)?.unwrap_or(0) != 0,
+            })
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(commands)
+}
+
+/// Tool calls that wrote a file, most recent first. `path` may be absolute or
+/// relative to `project` (anywhere, without a project) and may be a glob
+/// (`*`, `?`, `[...]`).
+pub fn get_file_touches(
+    path: &str,
+    project: Option<&str>,
+    limit: i64,
+) -> Result<Vec<FileTouch>, DatabaseError> {
+    let conn = get_db()?;
+
+    let relative = path.trim_start_matches("./");
+    let is_glob = path.contains(['*', '?', '[']);
+    let (pattern, is_glob) = match project {
+        _ if std::path::Path::new(path).is_absolute() => (path.to_string(), is_glob),
+        Some(project) => (
+            std::path::Path::new(project).join(relative).to_string_lossy().to_string(),
+            is_glob,
+        ),
+        None => (format!("*/{}", relative), true),
+    };
 
-    if let Some(to) = date_to {
-        if let Some(ts) = date_to_end_ms(to) {
-            wb.push("s.last_active_at <= ?", ts);
-        }
+    let mut wb = WhereBuilder::new();
+    let condition = if is_glob { "t.file_path GLOB ?" } else { "t.file_path = ?" };
+    wb.push(condition, pattern);
+    if let Some(project) = project {
+        wb.push("t.project_path = ?", project.to_string());
     }
-
     let where_clause = wb.to_where_clause();
     wb.push_param(limit);
 
     let sql = format!(
-        "SELECT {} FROM sessions s {} ORDER BY last_human_message_at DESC LIMIT ?",
-        SESSION_COLUMNS, where_clause
+        "SELECT t.session_id, s.parent_session_id, t.project_path, t.provider, t.file_path,
+                t.tool_name, t.touched_at, t.prompt, s.first_prompt
+         FROM session_file_touches t LEFT JOIN sessions s ON s.session_id = t.session_id
+         {} ORDER BY t.touched_at DESC LIMIT ?",
+        where_clause
     );
 
     let mut stmt = conn.prepare(&sql)?;
-    let sessions: Vec<Session> = stmt
-        .query_map(rusqlite::params_from_iter(wb.param_refs()), map_session_row)?
+    let touches = stmt
+        .query_map(rusqlite::params_from_iter(wb.param_refs()), |row| {
+            Ok(FileTouch {
+                session_id: row.get(0)?,
+                parent_session_id: row.get(1)?,
+                project_path: row.get(2)?,
+                provider: parse_provider(row, 3),
+                file_path: row.get(4)?,
+                tool_name: row.get(5)?,
+                touched_at: row.get(6)?,
+                prompt: row.get(7)?,
+                session_first_prompt: row.get(8)?,
+            })
+        })?
         .filter_map(|r| r.ok())
         .collect();
 
-    Ok(sessions)
+    Ok(touches)
 }
 
 // ============================================================================
@@ -1119,6 +3097,7 @@ pub fn get_usage_stats(
                 COUNT(session_id)
          FROM sessions {}
          GROUP BY project_path
+         HAVING project_path != ''
          ORDER BY SUM(total_cost_usd) DESC LIMIT 20",
         where_clause
     );
@@ -1162,10 +3141,18 @@ pub fn get_usage_stats(
 
 /// Get list of unique projects from sessions
 pub fn get_projects(_app: &AppHandle) -> Result<Vec<String>, DatabaseError> {
+    get_project_paths()
+}
+
+/// Distinct project paths of all sessions. Sessions whose project could not be
+/// resolved have an empty path and are left out.
+pub fn get_project_paths() -> Result<Vec<String>, DatabaseError> {
     let conn = get_db()?;
 
     let mut stmt = conn.prepare(
-        "SELECT DISTINCT project_path FROM sessions WHERE project_path IS NOT NULL ORDER BY project_path"
+        "SELECT DISTINCT project_path FROM sessions
+         WHERE project_path IS NOT NULL AND project_path != ''
+         ORDER BY project_path",
     )?;
 
     let projects: Vec<String> = stmt
@@ -1202,7 +3189,12 @@ pub fn get_tasks(
         "ORDER BY sort_order ASC"
     };
 
-    let sql = format!("SELECT * FROM tasks {} {}", wb.to_where_clause(), order_by);
+    let sql = format!(
+        "SELECT {} FROM tasks {} {}",
+        TASK_COLUMNS,
+        wb.to_where_clause(),
+        order_by
+    );
     let mut stmt = conn.prepare(&sql)?;
     let tasks = stmt
         .query_map(rusqlite::params_from_iter(wb.param_refs()), map_task_row)?
@@ -1219,6 +3211,8 @@ pub fn create_task(
     project: Option<&str>,
     priority: Option<&str>,
     notes: Option<&str>,
+    depends_on: &[String],
+    dependency_mode: DependencyMode,
 ) -> Result<Task, DatabaseError> {
     let conn = get_db()?;
 
@@ -1226,11 +3220,23 @@ pub fn create_task(
     let now = chrono::Utc::now().to_rfc3339();
     let priority = priority.unwrap_or("medium");
     let sort_order = next_sort_order(&conn, "tasks");
+    let depends_on = validate_task_dependencies(&conn, &id, depends_on)?;
 
     conn.execute(
-        "INSERT INTO tasks (id, prompt, project_path, status, priority, execution_mode, sort_order, created_at, notes)
-         VALUES (?1, ?2, ?3, 'backlog', ?4, 'new', ?5, ?6, ?7)",
-        params![id, prompt, project, priority, sort_order, now, notes],
+        "INSERT INTO tasks (id, prompt, project_path, status, priority, execution_mode, sort_order, created_at, notes,
+                            depends_on, dependency_mode)
+         VALUES (?1, ?2, ?3, 'backlog', ?4, 'new', ?5, ?6, ?7, ?8, ?9)",
+        params![
+            id,
+            prompt,
+            project,
+            priority,
+            sort_order,
+            now,
+            notes,
+            serde_json::to_string(&depends_on)?,
+            dependency_mode.to_string(),
+        ],
     )?;
 
     Ok(Task {
@@ -1239,8 +3245,9 @@ pub fn create_task(
         project_path: project.map(|s| s.to_string()),
         status: TaskStatus::Backlog,
         priority: priority.to_string(),
-        execution_mode: "new".to_string(),
-        depends_on: None,
+        execution_mode: ExecutionMode::New,
+        depends_on,
+        dependency_mode,
         session_id: None,
         system_prompt: None,
         allowed_tools: None,
@@ -1253,10 +3260,24 @@ pub fn create_task(
         result_output: None,
         result_tokens: None,
         result_cost_usd: None,
+        result_error: None,
         created_at: now,
         started_at: None,
         completed_at: None,
         provider: crate::providers::ProviderId::Claude,
+        max_attempts: None,
+        retry_delay_secs: None,
+        attempt_count: 0,
+        next_attempt_at: None,
+        run_at: None,
+        recurrence: None,
+        use_worktree: None,
+        worktree_path: None,
+        worktree_branch: None,
+        timeout_minutes: None,
+        verify_command: None,
+        fix_of: None,
+        output_session_id: None,
     })
 }
 
@@ -1280,15 +3301,20 @@ pub fn update_task(
                     params![s.to_string(), now, id],
                 )?;
             }
-            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Skipped => {
+            TaskStatus::Completed
+            | TaskStatus::Failed
+            | TaskStatus::Skipped
+            | TaskStatus::Cancelled => {
                 conn.execute(
                     "UPDATE tasks SET status = ?1, completed_at = ?2 WHERE id = ?3",
                     params![s.to_string(), now, id],
                 )?;
             }
-            _ => {
+            // Queueing by hand starts the retry count afresh
+            TaskStatus::Queued | TaskStatus::Backlog => {
                 conn.execute(
-                    "UPDATE tasks SET status = ?1 WHERE id = ?2",
+                    "UPDATE tasks SET status = ?1, attempt_count = 0, next_attempt_at = NULL
+                     WHERE id = ?2",
                     params![s.to_string(), id],
                 )?;
             }
@@ -1307,18 +3333,452 @@ pub fn update_task(
         conn.execute("UPDATE tasks SET sort_order = ?1 WHERE id = ?2", params![o, id])?;
     }
 
-    let task = conn.query_row(
-        "SELECT * FROM tasks WHERE id = ?1",
-        params![id],
-        map_task_row,
-    )?;
+    let task = select_task(&conn, id)?;
 
     Ok(task)
 }
 
+/// Deduplicate a task's dependencies and check that they exist and form no cycle
+fn validate_task_dependencies(
+    conn: &Connection,
+    task_id: &str,
+    depends_on: &[String],
+) -> Result<Vec<String>, DatabaseError> {
+    let mut deps: Vec<String> = Vec::new();
+    for dep in depends_on {
+        if !deps.contains(dep) {
+            deps.push(dep.clone());
+        }
+    }
+    if deps.is_empty() {
+        return Ok(deps);
+    }
+
+    let tasks: Vec<Task> = conn
+        .prepare(&format!("SELECT {} FROM tasks", TASK_COLUMNS))?
+        .query_map([], map_task_row)?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    if let Some(missing) = deps.iter().find(|d| *d != task_id && !tasks.iter().any(|t| &t.id == *d)) {
+        return Err(DatabaseError::NotFound(format!("Task {}", missing)));
+    }
+    if let Some(cycle) = crate::task_graph::find_cycle(&tasks, task_id, &deps) {
+        return Err(DatabaseError::InvalidDependencies(format!(
+            "cycle {}",
+            cycle.join(" -> ")
+        )));
+    }
+    Ok(deps)
+}
+
+/// Replace the tasks a task waits for
+pub fn set_task_dependencies(
+    _app: &AppHandle,
+    id: &str,
+    depends_on: &[String],
+    mode: DependencyMode,
+) -> Result<Task, DatabaseError> {
+    let conn = get_db()?;
+
+    let depends_on = validate_task_dependencies(&conn, id, depends_on)?;
+    conn.execute(
+        "UPDATE tasks SET depends_on = ?1, dependency_mode = ?2 WHERE id = ?3",
+        params![serde_json::to_string(&depends_on)?, mode.to_string(), id],
+    )?;
+
+    Ok(select_task(&conn, id)?)
+}
+
+/// Dependency graph of a project's tasks (or all tasks)
+pub fn get_task_graph(
+    app: &AppHandle,
+    project: Option<&str>,
+) -> Result<crate::task_graph::TaskGraph, DatabaseError> {
+    // Dependencies may cross projects, so readiness is judged against every task
+    let tasks = get_tasks(app, None, None)?;
+    Ok(crate::task_graph::build_graph(&tasks, |t| {
+        project.is_none() || t.project_path.as_deref() == project
+    }))
+}
+
+/// Get a single task by ID
+pub fn get_task(id: &str) -> Result<Task, DatabaseError> {
+    let conn = get_db()?;
+    Ok(select_task(&conn, id)?)
+}
+
+/// Record the outcome of a task run. The session it produced is kept apart
+/// from `session_id`, which stays the session the task resumes or forks.
+pub fn set_task_result(
+    id: &str,
+    exit_code: i32,
+    output: &str,
+    tokens: i64,
+    cost_usd: f64,
+    error: Option<&str>,
+    session_id: Option<&str>,
+) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    conn.execute(
+        "UPDATE tasks SET result_exit_code = ?1, result_output = ?2, result_tokens = ?3,
+                          result_cost_usd = ?4, result_error = ?5,
+                          output_session_id = COALESCE(?6, output_session_id)
+         WHERE id = ?7",
+        params![exit_code, output, tokens, cost_usd, error, session_id, id],
+    )?;
+    Ok(())
+}
+
+/// Mark a task failed, recording why without touching the output of its last run
+pub fn fail_task(id: &str, error: &str) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+    conn.execute(
+        "UPDATE tasks SET status = ?1, completed_at = ?2, result_error = ?3 WHERE id = ?4",
+        params![TaskStatus::Failed.to_string(), chrono::Utc::now().to_rfc3339(), error, id],
+    )?;
+    Ok(())
+}
+
+/// Normalize a task's schedule: the run time in UTC, and an unset run time of
+/// a recurring task filled in with its next occurrence.
+fn task_schedule(
+    run_at: Option<&str>,
+    recurrence: Option<&str>,
+) -> Result<(Option<String>, Option<String>), DatabaseError> {
+    let recurrence = recurrence.map(str::trim).filter(|r| !r.is_empty());
+    let run_at = match (run_at, recurrence) {
+        (Some(at), _) => Some(
+            chrono::DateTime::parse_from_rfc3339(at)
+                .map_err(|e| DatabaseError::InvalidSchedule(format!("run time '{}': {}", at, e)))?
+                .with_timezone(&chrono::Utc)
+                .to_rfc3339(),
+        ),
+        (None, Some(recurrence)) => Some(
+            crate::scheduler::next_occurrence(recurrence, chrono::Utc::now())
+                .map_err(DatabaseError::InvalidSchedule)?,
+        ),
+        (None, None) => None,
+    };
+    if let Some(recurrence) = recurrence {
+        crate::scheduler::CronSchedule::parse(recurrence).map_err(DatabaseError::InvalidSchedule)?;
+    }
+    Ok((run_at, recurrence.map(str::to_string)))
+}
+
+/// Change a task's settings, keeping those `settings` leaves out. Changing
+/// the recurrence alone schedules the task at its next occurrence.
+pub fn update_task_settings(
+    id: &str,
+    settings: &TaskSettings,
+) -> Result<Task, DatabaseError> {
+    let conn = get_db()?;
+    let mut task = select_task(&conn, id)
+        .optional()?
+        .ok_or_else(|| DatabaseError::NotFound(format!("Task {}", id)))?;
+
+    if let Some(mode) = settings.execution_mode {
+        task.execution_mode = mode;
+    }
+    if let Some(session_id) = &settings.session_id {
+        task.session_id = session_id.clone();
+    }
+    if let Some(budget) = settings.max_budget_usd {
+        task.max_budget_usd = budget.filter(|b| *b > 0.0);
+    }
+    if let Some(attempts) = settings.max_attempts {
+        task.max_attempts = attempts.filter(|n| *n > 0);
+    }
+    if let Some(delay) = settings.retry_delay_secs {
+        task.retry_delay_secs = delay.filter(|d| *d >= 0);
+    }
+    if let Some(timeout) = settings.timeout_minutes {
+        task.timeout_minutes = timeout.map(|m| m.max(0));
+    }
+    if let Some(command) = &settings.verify_command {
+        task.verify_command = command.as_deref().map(|c| c.trim().to_string());
+    }
+    if let Some(use_worktree) = settings.use_worktree {
+        task.use_worktree = use_worktree;
+    }
+    if settings.run_at.is_some() || settings.recurrence.is_some() {
+        let recurrence = settings.recurrence.clone().unwrap_or(task.recurrence);
+        let run_at = match &settings.run_at {
+            Some(run_at) => run_at.clone(),
+            None if settings.recurrence.is_some() => None,
+            None => task.run_at,
+        };
+        (task.run_at, task.recurrence) = task_schedule(run_at.as_deref(), recurrence.as_deref())?;
+    }
+
+    conn.execute(
+        "UPDATE tasks SET execution_mode = ?1, session_id = ?2, max_budget_usd = ?3,
+                          max_attempts = ?4, retry_delay_secs = ?5, timeout_minutes = ?6,
+                          verify_command = ?7, use_worktree = ?8, run_at = ?9, recurrence = ?10
+         WHERE id = ?11",
+        params![
+            task.execution_mode,
+            task.session_id,
+            task.max_budget_usd,
+            task.max_attempts,
+            task.retry_delay_secs,
+            task.timeout_minutes,
+            task.verify_command,
+            task.use_worktree,
+            task.run_at,
+            task.recurrence,
+            id
+        ],
+    )?;
+
+    Ok(select_task(&conn, id)?)
+}
+
+/// Queue a task whose scheduled time has come, setting its next run time
+pub fn enqueue_scheduled_task(
+    app: &AppHandle,
+    id: &str,
+    next_run_at: Option<&str>,
+) -> Result<Task, DatabaseError> {
+    set_task_run_at(id, next_run_at)?;
+    update_task(app, id, Some(TaskStatus::Queued), None, None, None)
+}
+
+/// Set or clear when a scheduled task next runs
+pub fn set_task_run_at(id: &str, run_at: Option<&str>) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+    conn.execute("UPDATE tasks SET run_at = ?1 WHERE id = ?2", params![run_at, id])?;
+    Ok(())
+}
+
+/// Queue a task that resumes `session_id` to fix the checks `failed_id` failed.
+/// It inherits the failed task's project, provider, limits and verification.
+/// Unfinished tasks waiting on the failed task wait on the fix instead, so
+/// they still run once its checks pass.
+pub fn create_fix_task(
+    failed_id: &str,
+    prompt: &str,
+    session_id: &str,
+) -> Result<Task, DatabaseError> {
+    let conn = get_db()?;
+    let tx = conn.unchecked_transaction()?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().to_rfc3339();
+    let sort_order = next_sort_order(&conn, "tasks");
+    let inserted = conn.execute(
+        "INSERT INTO tasks (id, prompt, project_path, status, priority, execution_mode, depends_on,
+                            session_id, system_prompt, allowed_tools, max_budget_usd, max_turns,
+                            sort_order, created_at, provider, max_attempts, retry_delay_secs,
+                            timeout_minutes, verify_command, fix_of)
+         SELECT ?1, ?2, project_path, 'queued', priority, 'resume', '[]',
+                ?3, system_prompt, allowed_tools, max_budget_usd, max_turns,
+                ?4, ?5, provider, max_attempts, retry_delay_secs,
+                timeout_minutes, verify_command, id
+         FROM tasks WHERE id = ?6",
+        params![id, prompt, session_id, sort_order, now, failed_id],
+    )?;
+    if inserted == 0 {
+        return Err(DatabaseError::NotFound(format!("Task {}", failed_id)));
+    }
+
+    let dependents: Vec<(String, String)> = conn
+        .prepare(
+            "SELECT id, depends_on FROM tasks
+             WHERE depends_on LIKE ?1 AND status IN ('backlog', 'queued')",
+        )?
+        .query_map(params![format!("%\"{}\"%", failed_id)], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<_, _>>()?;
+    for (dependent, depends_on) in dependents {
+        let depends_on: Vec<String> = serde_json::from_str::<Vec<String>>(&depends_on)?
+            .into_iter()
+            .map(|dep| if dep == failed_id { id.clone() } else { dep })
+            .collect();
+        conn.execute(
+            "UPDATE tasks SET depends_on = ?1 WHERE id = ?2",
+            params![serde_json::to_string(&depends_on)?, dependent],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(select_task(&conn, &id)?)
+}
+
+/// Record the worktree and branch a task runs on
+pub fn set_task_worktree(
+    id: &str,
+    path: Option<&str>,
+    branch: Option<&str>,
+) -> Result<Task, DatabaseError> {
+    let conn = get_db()?;
+
+    conn.execute(
+        "UPDATE tasks SET worktree_path = ?1, worktree_branch = ?2 WHERE id = ?3",
+        params![path, branch, id],
+    )?;
+
+    Ok(select_task(&conn, id)?)
+}
+
+/// Record the start of a new attempt at a task
+pub fn start_task_run(task_id: &str) -> Result<TaskRun, DatabaseError> {
+    let conn = get_db()?;
+    let now = chrono::Utc::now().to_rfc3339();
+
+    conn.execute(
+        "UPDATE tasks SET attempt_count = attempt_count + 1, next_attempt_at = NULL,
+                          run_at = CASE WHEN recurrence IS NULL THEN NULL ELSE run_at END
+         WHERE id = ?1",
+        params![task_id],
+    )?;
+    let attempt: i32 = conn.query_row(
+        "SELECT attempt_count FROM tasks WHERE id = ?1",
+        params![task_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO task_runs (task_id, attempt, status, started_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![task_id, attempt, TaskRunStatus::Running.to_string(), now],
+    )?;
+    let id = conn.last_insert_rowid();
+    let log_path = crate::task_log::log_path(task_id, id)
+        .to_string_lossy()
+        .to_string();
+    conn.execute(
+        "UPDATE task_runs SET log_path = ?1 WHERE id = ?2",
+        params![log_path, id],
+    )?;
+
+    Ok(TaskRun {
+        id,
+        task_id: task_id.to_string(),
+        attempt,
+        status: TaskRunStatus::Running,
+        exit_code: None,
+        error: None,
+        tokens: None,
+        cost_usd: None,
+        session_id: None,
+        started_at: now,
+        finished_at: None,
+        log_path: Some(log_path),
+        pid: None,
+        pid_started_at: None,
+    })
+}
+
+/// Record how an attempt ended
+pub fn finish_task_run(
+    run_id: i64,
+    status: TaskRunStatus,
+    exit_code: Option<i32>,
+    error: Option<&str>,
+    tokens: Option<i64>,
+    cost_usd: Option<f64>,
+    session_id: Option<&str>,
+) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    conn.execute(
+        "UPDATE task_runs SET status = ?1, exit_code = ?2, error = ?3, tokens = ?4, cost_usd = ?5,
+                              session_id = ?6, finished_at = ?7
+         WHERE id = ?8",
+        params![
+            status.to_string(),
+            exit_code,
+            error,
+            tokens,
+            cost_usd,
+            session_id,
+            chrono::Utc::now().to_rfc3339(),
+            run_id
+        ],
+    )?;
+    Ok(())
+}
+
+/// Record the process a run started, so it can be found again after a restart
+pub fn set_task_run_pid(
+    run_id: i64,
+    pid: u32,
+    started_at: Option<&str>,
+) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+    conn.execute(
+        "UPDATE task_runs SET pid = ?1, pid_started_at = ?2 WHERE id = ?3",
+        params![pid, started_at, run_id],
+    )?;
+    Ok(())
+}
+
+/// Attempts at a task, newest first, a page at a time
+pub fn get_task_runs(
+    _app: &AppHandle,
+    task_id: &str,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<TaskRun>, DatabaseError> {
+    let conn = get_db()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM task_runs WHERE task_id = ?1 ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+        TASK_RUN_COLUMNS
+    ))?;
+    let runs = stmt
+        .query_map(
+            params![task_id, limit.map_or(-1, i64::from), offset.unwrap_or(0)],
+            map_task_run_row,
+        )?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(runs)
+}
+
+/// Get a single run by ID
+pub fn get_task_run(run_id: i64) -> Result<TaskRun, DatabaseError> {
+    let conn = get_db()?;
+    Ok(conn.query_row(
+        &format!("SELECT {} FROM task_runs WHERE id = ?1", TASK_RUN_COLUMNS),
+        params![run_id],
+        map_task_run_row,
+    )?)
+}
+
+/// A task's most recent run
+pub fn get_latest_task_run(task_id: &str) -> Result<Option<TaskRun>, DatabaseError> {
+    let conn = get_db()?;
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM task_runs WHERE task_id = ?1 ORDER BY id DESC LIMIT 1",
+                TASK_RUN_COLUMNS
+            ),
+            params![task_id],
+            map_task_run_row,
+        )
+        .optional()?)
+}
+
+/// Put a task back in the queue, to run again no earlier than `next_attempt_at`
+pub fn schedule_task_retry(id: &str, next_attempt_at: &str) -> Result<(), DatabaseError> {
+    let conn = get_db()?;
+
+    conn.execute(
+        "UPDATE tasks SET status = ?1, next_attempt_at = ?2 WHERE id = ?3",
+        params![TaskStatus::Queued.to_string(), next_attempt_at, id],
+    )?;
+    Ok(())
+}
+
 /// Delete a task
 pub fn delete_task(_app: &AppHandle, id: &str) -> Result<(), DatabaseError> {
     let conn = get_db()?;
+    conn.execute("DELETE FROM task_runs WHERE task_id = ?1", params![id])?;
     conn.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
     Ok(())
 }
@@ -1489,11 +3949,20 @@ pub fn get_session_images(
         |row| row.get(0),
     )?;
 
-    // Find JSONL file
-    let projects_dir = crate::platform::get_claude_dir().join("projects");
-    let session_file = projects_dir
-        .join(urlencoding::encode(&project_path).into_owned())
-        .join(format!("{}.jsonl", session_id));
+    // Find JSONL file: the parse cursor records it, else derive it from the project path
+    let cursor_file: Option<String> = conn
+        .query_row(
+            "SELECT file_path FROM session_file_cursors WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .ok();
+    let session_file = cursor_file.map(PathBuf::from).unwrap_or_else(|| {
+        crate::platform::get_claude_dir()
+            .join("projects")
+            .join(crate::platform::encode_project_path(&project_path))
+            .join(format!("{}.jsonl", session_id))
+    });
 
     if !session_file.exists() {
         return Ok(vec![]);
@@ -1541,3 +4010,619 @@ fn extract_images_from_json(content: &serde_json::Value) -> Vec<crate::session::
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str) -> Session {
+        serde_json::from_value(serde_json::json!({
+            "session_id": id,
+            "project_path": "/work/app",
+            "project_name": "app",
+            "first_prompt": "Fix the build",
+            "label": null,
+            "tags": [],
+            "started_at": 1,
+            "last_active_at": 2,
+            "last_human_message_at": 1,
+            "message_count": 2,
+            "total_tokens": 10,
+            "total_cost_usd": 0.5,
+            "input_tokens": 6,
+            "output_tokens": 4,
+            "cache_read_tokens": 0,
+            "cache_write_tokens": 0,
+            "model": null,
+            "status": "completed",
+        }))
+        .unwrap()
+    }
+
+    fn message_count(conn: &Connection, session_id: &str) -> i64 {
+        conn.query_row(
+            "SELECT COUNT(*) FROM session_messages WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_session_update_keeps_transcript() {
+        let _db = reset_test_database();
+        let message = |role: &str, content: &str| SessionMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let messages = vec![message("user", "Fix the build"), message("assistant", "Done.")];
+        upsert_session(&session("s1")).unwrap();
+        replace_session_messages("s1", &messages).unwrap();
+
+        let mut updated = session("s1");
+        updated.message_count = 3;
+        upsert_session(&updated).unwrap();
+        let conn = get_db().unwrap();
+        assert_eq!(message_count(&conn, "s1"), 2);
+
+        // Deleting the session cascades to its transcript
+        conn.execute("DELETE FROM sessions WHERE session_id = 's1'", []).unwrap();
+        assert_eq!(message_count(&conn, "s1"), 0);
+    }
+
+    #[test]
+    fn test_session_file_keys() {
+        let _db = reset_test_database();
+        let cursor = SessionCursor::default();
+        let keys = || get_session_file_keys("/logs/s1.jsonl").unwrap();
+
+        save_session_cursor("/logs/s1.jsonl", "s1", &cursor, true, &[1, u64::MAX]).unwrap();
+        save_session_cursor("/logs/s1.jsonl", "s1", &cursor, false, &[2]).unwrap();
+        let recorded = keys();
+        assert!(recorded.contains(1) && recorded.contains(2) && recorded.contains(u64::MAX));
+        assert!(!get_session_file_keys("/logs/s2.jsonl").unwrap().contains(1));
+
+        // A full parse starts the history over
+        save_session_cursor("/logs/s1.jsonl", "s1", &cursor, true, &[3]).unwrap();
+        let recorded = keys();
+        assert!(!recorded.contains(1) && recorded.contains(3));
+
+        // And the keys go with their cursor
+        clear_session_cursors().unwrap();
+        assert!(!keys().contains(3));
+    }
+
+    #[test]
+    fn test_parse_search_query_terms() {
+        let plan = parse_search_query("foo", SearchTokenizer::Unicode61);
+        assert_eq!(plan.match_expr.as_deref(), Some("\"foo\""));
+        assert!(plan.conditions.is_empty());
+
+        // Each term must match some row of the session; ranking uses any of them
+        let plan = parse_search_query("foo bar", SearchTokenizer::Unicode61);
+        assert_eq!(plan.match_expr.as_deref(), Some("\"foo\" OR \"bar\""));
+        assert_eq!(plan.conditions, vec![match_condition(false), match_condition(false)]);
+        assert_eq!(plan.params, vec!["\"foo\"", "\"bar\""]);
+
+        let plan = parse_search_query("foo|bar \"a|b\"", SearchTokenizer::Unicode61);
+        assert_eq!(plan.match_expr.as_deref(), Some("(\"foo\" OR \"bar\") OR \"a|b\""));
+    }
+
+    #[test]
+    fn test_parse_search_query_exclusions_and_tags() {
+        let plan = parse_search_query("-foo tag:#Work -tag:old", SearchTokenizer::Unicode61);
+        assert_eq!(plan.match_expr, None);
+        assert_eq!(
+            plan.conditions,
+            vec![match_condition(true), tag_condition(false), tag_condition(true)]
+        );
+        assert_eq!(plan.params, vec!["\"foo\"", "Work", "old"]);
+
+        assert!(parse_search_query("- tag: \"\"", SearchTokenizer::Unicode61).is_empty());
+    }
+
+    #[test]
+    fn test_parse_search_query_short_trigram_terms() {
+        let plan = parse_search_query("ab", SearchTokenizer::Trigram);
+        assert_eq!(plan.match_expr, None);
+        assert_eq!(plan.conditions, vec![like_across_fields("%ab%", false, &mut Vec::new())]);
+        assert!(plan.params.iter().all(|p| p == "%ab%"));
+
+        let plan = parse_search_query("ab", SearchTokenizer::Unicode61);
+        assert_eq!(plan.match_expr.as_deref(), Some("\"ab\""));
+    }
+
+    #[test]
+    fn test_extract_highlights() {
+        let (o, c) = (HIGHLIGHT_OPEN, HIGHLIGHT_CLOSE);
+        let marked = format!("a {o}b{c} \u{1F600} {o}cd{c}");
+        let (text, highlights) = extract_highlights(&marked);
+        assert_eq!(text, "a b \u{1F600} cd");
+        // The emoji takes two UTF-16 code units
+        assert_eq!(highlights, vec![(2, 3), (7, 9)]);
+
+        let (text, highlights) = extract_highlights("plain");
+        assert_eq!(text, "plain");
+        assert!(highlights.is_empty());
+    }
+
+    #[test]
+    fn test_agent_tree() {
+        let _db = reset_test_database();
+        let agent = |id: &str, parent: &str, cost: f64| {
+            let mut session = session(id);
+            session.parent_session_id = Some(parent.to_string());
+            session.total_cost_usd = cost;
+            session
+        };
+        upsert_session(&session("s1")).unwrap();
+        upsert_session(&agent("agent-a", "s1", 1.0)).unwrap();
+        upsert_session(&agent("agent-b", "s1", 2.0)).unwrap();
+        upsert_session(&agent("agent-c", "agent-a", 4.0)).unwrap();
+
+        let conn = get_db().unwrap();
+        let mut visited = HashSet::from(["s1".to_string()]);
+        let nodes = get_agent_nodes(&conn, "s1", &mut visited).unwrap();
+
+        // Most expensive subtree first, with nested agents rolled up
+        let ids: Vec<&str> = nodes.iter().map(|n| n.session.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["agent-a", "agent-b"]);
+        assert_eq!(nodes[0].tree_cost_usd, 5.0);
+        assert_eq!(nodes[0].tree_tokens, 20);
+        assert_eq!(nodes[0].children[0].session.session_id, "agent-c");
+
+        // Sub-agents are left out of session lists
+        let hits = query_session_hits(&conn, WhereBuilder::new(), None, 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session.session_id, "s1");
+
+        // A corrupted parent link back up the tree does not loop
+        let relink = "UPDATE sessions SET parent_session_id = 'agent-c' WHERE session_id = 's1'";
+        conn.execute(relink, []).unwrap();
+        let mut visited = HashSet::from(["s1".to_string()]);
+        assert_eq!(get_agent_nodes(&conn, "s1", &mut visited).unwrap().len(), 2);
+    }
+
+    fn assistant_calls(timestamp: i64, calls: serde_json::Value) -> SessionMessage {
+        SessionMessage {
+            role: "assistant".to_string(),
+            timestamp,
+            tool_calls: serde_json::from_value(calls).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn tool_output(tool_use_id: &str, timestamp: i64, is_error: bool) -> SessionMessage {
+        SessionMessage {
+            role: "tool".to_string(),
+            timestamp,
+            tool_use_id: Some(tool_use_id.to_string()),
+            is_error,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tool_index() {
+        let _db = reset_test_database();
+        upsert_session(&session("s1")).unwrap();
+        let calls = serde_json::json!([
+            { "id": "t1", "name": "Bash", "input": { "command": "cargo test" } },
+            { "id": "t2", "name": "Read", "input": { "file_path": "src/lib.rs" } },
+        ]);
+        let messages = [assistant_calls(1_000, calls), tool_output("t1", 1_500, false)];
+        replace_session_messages("s1", &messages).unwrap();
+
+        // A result stored by a later read completes the call recorded earlier
+        append_session_messages("s1", &[tool_output("t2", 3_000, true)]).unwrap();
+
+        let stats = get_tool_stats(None, None, ToolStatsSort::Calls, 10).unwrap();
+        let stat = |name: &str| stats.iter().find(|s| s.tool_name == name).unwrap();
+        assert_eq!((stat("Bash").call_count, stat("Bash").error_count), (1, 0));
+        assert_eq!(stat("Bash").max_duration_ms, Some(500));
+        assert_eq!((stat("Read").failure_rate, stat("Read").max_duration_ms), (1.0, Some(2_000)));
+
+        let by_failures = get_tool_stats(None, None, ToolStatsSort::FailureRate, 10).unwrap();
+        assert_eq!(by_failures[0].tool_name, "Read");
+        assert!(get_tool_stats(Some("/other"), None, ToolStatsSort::Calls, 10).unwrap().is_empty());
+
+        let commands = get_shell_commands(Some("/work/app"), Some("Claude"), 10).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].command.as_deref(), Some("cargo test"));
+        assert_eq!((commands[0].started_at, commands[0].duration_ms), (Some(1_000), Some(500)));
+
+        // Replacing the transcript rebuilds the index
+        replace_session_messages("s1", &[tool_output("t9", 1, false)]).unwrap();
+        assert!(get_tool_stats(None, None, ToolStatsSort::Calls, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backfill_tool_index() {
+        let _db = reset_test_database();
+        upsert_session(&session("s1")).unwrap();
+        let calls = serde_json::json!([{ "id": "t1", "name": "Bash", "input": {} }]);
+        let messages = [assistant_calls(1_000, calls), tool_output("t1", 1_500, false)];
+        replace_session_messages("s1", &messages).unwrap();
+
+        // As left by a version that stored transcripts without indexing tool calls
+        let conn = get_db().unwrap();
+        conn.execute_batch(
+            "DELETE FROM tool_invocations; UPDATE session_index_state SET tool_message_id = 0;",
+        )
+        .unwrap();
+        backfill_tool_index(&conn);
+        backfill_tool_index(&conn);
+        drop(conn);
+
+        let stats = get_tool_stats(None, None, ToolStatsSort::Calls, 10).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].call_count, stats[0].max_duration_ms), (1, Some(500)));
+    }
+
+    #[test]
+    fn test_file_touches() {
+        let _db = reset_test_database();
+        upsert_session(&session("s1")).unwrap();
+        let user = |content: &str| SessionMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let patch = "*** Begin Patch\n*** Add File: docs/guide.md\n+Hi\n*** End Patch";
+        let messages = [
+            user("Fix the parser"),
+            assistant_calls(1_000, serde_json::json!([
+                { "id": "t1", "name": "Edit", "input": { "file_path": "src/parser.rs" } },
+                { "id": "t2", "name": "Write", "input": { "file_path": "/work/app/README.md" } },
+            ])),
+            user("Now the docs"),
+            assistant_calls(2_000, serde_json::json!([
+                { "id": "t3", "name": "apply_patch", "input": patch },
+            ])),
+        ];
+        replace_session_messages("s1", &messages).unwrap();
+
+        let touches = get_file_touches("src/parser.rs", Some("/work/app"), 10).unwrap();
+        assert_eq!(touches.len(), 1);
+        assert_eq!(touches[0].file_path, "/work/app/src/parser.rs");
+        assert_eq!(touches[0].prompt.as_deref(), Some("Fix the parser"));
+        assert_eq!(touches[0].session_first_prompt.as_deref(), Some("Fix the build"));
+
+        // Without a project a relative path matches in any directory
+        let touches = get_file_touches("guide.md", None, 10).unwrap();
+        assert_eq!(touches.len(), 1);
+        assert_eq!(touches[0].prompt.as_deref(), Some("Now the docs"));
+        assert_eq!(get_file_touches("/work/app/*.md", None, 10).unwrap().len(), 2);
+        assert!(get_file_touches("src/parser.rs", Some("/work/other"), 10).unwrap().is_empty());
+
+        // A write that failed changed nothing
+        append_session_messages("s1", &[tool_output("t2", 1_200, true)]).unwrap();
+        assert!(get_file_touches("/work/app/README.md", None, 10).unwrap().is_empty());
+        assert_eq!(get_file_touches("*.md", None, 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_session_tags() {
+        let _db = reset_test_database();
+        for id in ["s1", "s2", "s3"] {
+            upsert_session(&session(id)).unwrap();
+        }
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        let stored = set_session_tags("s1", &tags(&["#Work", "work", " urgent "])).unwrap();
+        assert_eq!(stored, tags(&["Work", "urgent"]));
+        let stored = add_session_tags("s2", &tags(&["work", "home"])).unwrap();
+        assert_eq!(stored, tags(&["work", "home"]));
+        assert_eq!(remove_session_tags("s2", &tags(&["#HOME"])).unwrap(), tags(&["work"]));
+
+        let counts = get_tag_counts().unwrap();
+        assert_eq!((counts[0].tag.to_lowercase(), counts[0].count), ("work".to_string(), 2));
+        assert_eq!(counts.len(), 2);
+
+        // `tag:` qualifiers filter through the stored JSON arrays
+        let search = |query: &str| {
+            let mut wb = WhereBuilder::new();
+            let plan = parse_search_query(query, SearchTokenizer::Unicode61);
+            let match_expr = plan.apply(&mut wb);
+            let conn = get_db().unwrap();
+            let mut ids: Vec<String> = query_session_hits(&conn, wb, match_expr, 10)
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.session.session_id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(search("tag:WORK"), tags(&["s1", "s2"]));
+        assert_eq!(search("tag:work -tag:urgent"), tags(&["s2"]));
+        assert_eq!(search("-tag:work"), tags(&["s3"]));
+        assert_eq!(search("build tag:urgent"), tags(&["s1"]));
+
+        assert_eq!(rename_tag("work", "#Office").unwrap(), 2);
+        assert_eq!(search("tag:office"), tags(&["s1", "s2"]));
+        assert_eq!(delete_tag("urgent").unwrap(), 1);
+        assert!(search("tag:urgent").is_empty());
+    }
+
+    #[test]
+    fn test_fix_claude_project_paths() {
+        let conn = test_connection();
+        let projects = tempfile::tempdir().unwrap();
+        let encoded = projects.path().join("-work-app");
+        std::fs::create_dir(&encoded).unwrap();
+        std::fs::write(encoded.join("s1.jsonl"), "{\"cwd\":\"/work/app\"}\n").unwrap();
+
+        let insert = "INSERT INTO sessions
+                      (session_id, project_path, project_name, started_at, last_active_at,
+                       message_count, total_tokens, total_cost_usd, status, provider)
+                      VALUES (?1, ?2, ?3, 1, 2, 1, 0, 0, 'completed', 'claude')";
+        let stored = encoded.to_string_lossy().to_string();
+        conn.execute(insert, params!["s1", stored, "-work-app"]).unwrap();
+        // No transcript to read the directory from: the row is left alone
+        conn.execute(insert, params!["s2", stored, "-work-app"]).unwrap();
+        conn.execute(
+            "INSERT INTO session_file_cursors (file_path, session_id, byte_offset, state, updated_at)
+             VALUES ('/logs/s1.jsonl', 's1', 0, '{}', 0)",
+            [],
+        )
+        .unwrap();
+
+        fix_claude_project_paths(&conn, projects.path());
+        // Fixed rows no longer match, so running again changes nothing
+        fix_claude_project_paths(&conn, projects.path());
+
+        let project = |id: &str| {
+            conn.query_row(
+                "SELECT project_path, project_name FROM sessions WHERE session_id = ?1",
+                params![id],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(project("s1"), ("/work/app".to_string(), "app".to_string()));
+        assert_eq!(project("s2"), (stored, "-work-app".to_string()));
+
+        let indexed: String = conn
+            .query_row(
+                "SELECT project_name FROM session_search WHERE rowid < 0 AND session_id = 's1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(indexed, "app");
+        let cursors: i64 = conn
+            .query_row("SELECT COUNT(*) FROM session_file_cursors", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cursors, 0);
+    }
+
+    #[test]
+    fn test_metadata_migration() {
+        let conn = test_connection();
+        let insert = "INSERT INTO sessions
+                      (session_id, project_path, project_name, started_at, last_active_at,
+                       message_count, total_tokens, total_cost_usd, status, label, tags)
+                      VALUES (?1, '/work/app', 'app', 1, 2, 1, 0, 0, 'completed', ?2, ?3)";
+        conn.execute(insert, params!["s1", "Release", "[\"work\"]"]).unwrap();
+        conn.execute(insert, params!["s2", None::<String>, "[]"]).unwrap();
+        conn.execute(insert, params!["s3", "Old label", None::<String>]).unwrap();
+        conn.execute(
+            "INSERT INTO session_metadata (session_id, label, updated_at) VALUES ('s3', 'Mine', 0)",
+            [],
+        )
+        .unwrap();
+
+        run_migrations(&conn);
+
+        // Labels and tags move to `session_metadata` without overwriting what is there
+        let metadata = |id: &str| {
+            conn.query_row(
+                "SELECT label, tags FROM session_metadata WHERE session_id = ?1",
+                params![id],
+                |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()
+            .unwrap()
+        };
+        assert_eq!(metadata("s1"), Some((Some("Release".into()), Some("[\"work\"]".into()))));
+        assert_eq!(metadata("s2"), None);
+        assert_eq!(metadata("s3"), Some((Some("Mine".into()), None)));
+
+        let leftover: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sessions WHERE label IS NOT NULL OR tags IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(leftover, 0);
+
+        let sql = format!("SELECT {} FROM session_view WHERE session_id = 's1'", SESSION_COLUMNS);
+        let session = conn.query_row(&sql, [], map_session_row).unwrap();
+        assert_eq!(session.label.as_deref(), Some("Release"));
+        assert_eq!(session.tags, vec!["work"]);
+    }
+
+    #[test]
+    fn test_metadata_survives_reparse() {
+        let _db = reset_test_database();
+        upsert_session(&session("s1")).unwrap();
+        set_session_pinned("s1", true).unwrap();
+        set_session_notes("s1", Some("  check the flaky test ")).unwrap();
+        set_session_tags("s1", &["work".to_string()]).unwrap();
+
+        let mut reparsed = session("s1");
+        reparsed.message_count = 5;
+        upsert_session(&reparsed).unwrap();
+
+        let conn = get_db().unwrap();
+        let sql = format!("SELECT {} FROM session_view WHERE session_id = 's1'", SESSION_COLUMNS);
+        let stored = conn.query_row(&sql, [], map_session_row).unwrap();
+        assert_eq!(stored.message_count, 5);
+        assert!(stored.pinned);
+        assert_eq!(stored.notes.as_deref(), Some("check the flaky test"));
+        assert_eq!(stored.tags, vec!["work"]);
+        drop(conn);
+
+        // Archived sessions drop out of lists and search until asked for
+        let listed = |sessions: Vec<Session>| sessions.iter().any(|s| s.session_id == "s1");
+        let found = |query: &str| {
+            let hits = search_sessions(query, None, 10, SearchTokenizer::Unicode61).unwrap();
+            hits.iter().any(|hit| hit.session.session_id == "s1")
+        };
+        assert!(listed(get_sessions(None, 10).unwrap()));
+        assert!(found("build"));
+        set_session_archived("s1", true).unwrap();
+        assert!(!listed(get_sessions(None, 10).unwrap()));
+        assert!(!found("build"));
+    }
+
+    #[test]
+    fn test_latest_project_session() {
+        let _db = reset_test_database();
+        let at = |id: &str, last_active_at: i64| {
+            let mut session = session(id);
+            session.last_active_at = last_active_at;
+            session
+        };
+        upsert_session(&at("old", 10)).unwrap();
+        upsert_session(&at("latest", 20)).unwrap();
+        let mut agent = at("agent-1", 30);
+        agent.parent_session_id = Some("latest".to_string());
+        upsert_session(&agent).unwrap();
+        let mut codex = at("codex-1", 40);
+        codex.provider = crate::providers::ProviderId::Codex;
+        upsert_session(&codex).unwrap();
+
+        // Sub-agents and other providers' sessions are not continued
+        let claude = crate::providers::ProviderId::Claude;
+        let latest = get_latest_project_session_id("/work/app", claude).unwrap();
+        assert_eq!(latest.as_deref(), Some("latest"));
+        assert_eq!(get_latest_project_session_id("/work/other", claude).unwrap(), None);
+
+        assert_eq!("Fork".parse(), Ok(ExecutionMode::Fork));
+        assert_eq!(ExecutionMode::Continue.to_string(), "continue");
+        assert!("rewind".parse::<ExecutionMode>().is_err());
+    }
+
+    #[test]
+    fn test_task_run_logs() {
+        let _db = reset_test_database();
+        get_db()
+            .unwrap()
+            .execute(
+                "INSERT INTO tasks (id, prompt, sort_order, created_at) VALUES ('t1', 'Fix', 0, '')",
+                [],
+            )
+            .unwrap();
+
+        let first = start_task_run("t1").unwrap();
+        // Queuing the task again starts its attempts over
+        get_db()
+            .unwrap()
+            .execute("UPDATE tasks SET attempt_count = 0 WHERE id = 't1'", [])
+            .unwrap();
+        let second = start_task_run("t1").unwrap();
+
+        assert_eq!((first.attempt, second.attempt), (1, 1));
+        assert_ne!(first.log_path, second.log_path);
+        assert_eq!(get_task_run(second.id).unwrap().log_path, second.log_path);
+    }
+
+    #[test]
+    fn test_update_task_settings() {
+        let _db = reset_test_database();
+        let mut task = test_task("t1");
+        task.max_budget_usd = Some(5.0);
+        task.verify_command = Some("cargo test".to_string());
+        insert_test_task(&task);
+        let update = |settings: serde_json::Value| {
+            let settings: TaskSettings = serde_json::from_value(settings).unwrap();
+            update_task_settings("t1", &settings)
+        };
+
+        // Omitted settings keep their value, null clears one and bad values are clamped
+        let updated = update(serde_json::json!({
+            "max_budget_usd": null,
+            "max_attempts": 0,
+            "timeout_minutes": -5,
+            "use_worktree": true,
+        }))
+        .unwrap();
+        assert_eq!(updated.max_budget_usd, None);
+        assert_eq!(updated.max_attempts, None);
+        assert_eq!(updated.timeout_minutes, Some(0));
+        assert_eq!(updated.use_worktree, Some(true));
+        assert_eq!(updated.verify_command.as_deref(), Some("cargo test"));
+
+        // A new recurrence alone moves the run time to its next occurrence
+        let scheduled = update(serde_json::json!({"run_at": "2030-01-01T09:00:00+02:00"})).unwrap();
+        assert_eq!(scheduled.run_at.as_deref(), Some("2030-01-01T07:00:00+00:00"));
+        let recurring = update(serde_json::json!({"recurrence": "@hourly"})).unwrap();
+        assert_eq!(recurring.recurrence.as_deref(), Some("@hourly"));
+        assert!(recurring.run_at.unwrap() < scheduled.run_at.unwrap());
+        assert!(update(serde_json::json!({"recurrence": "every day"})).is_err());
+        assert!(update_task_settings("missing", &TaskSettings::default()).is_err());
+    }
+
+    #[test]
+    fn test_create_fix_task() {
+        let _db = reset_test_database();
+        let mut failed = test_task("failed");
+        failed.provider = crate::providers::ProviderId::Codex;
+        failed.priority = "high".to_string();
+        failed.max_budget_usd = Some(2.5);
+        failed.max_attempts = Some(3);
+        failed.timeout_minutes = Some(20);
+        failed.verify_command = Some("cargo test".to_string());
+        failed.depends_on = vec!["setup".to_string()];
+        let mut waiting = test_task("waiting");
+        waiting.depends_on = vec!["setup".to_string(), "failed".to_string()];
+        let mut done = test_task("done");
+        done.status = TaskStatus::Completed;
+        done.depends_on = vec!["failed".to_string()];
+        for task in [&failed, &waiting, &done] {
+            insert_test_task(task);
+        }
+
+        let fix = create_fix_task("failed", "Fix the checks", "s1").unwrap();
+        assert_eq!(fix.fix_of.as_deref(), Some("failed"));
+        assert_eq!((fix.status, fix.execution_mode), (TaskStatus::Queued, ExecutionMode::Resume));
+        assert_eq!(fix.session_id.as_deref(), Some("s1"));
+        assert!(fix.depends_on.is_empty());
+        assert_eq!(fix.project_path, failed.project_path);
+        assert_eq!(fix.provider, failed.provider);
+        assert_eq!(fix.priority, "high");
+        assert_eq!(fix.max_budget_usd, Some(2.5));
+        assert_eq!(fix.max_attempts, Some(3));
+        assert_eq!(fix.timeout_minutes, Some(20));
+        assert_eq!(fix.verify_command.as_deref(), Some("cargo test"));
+
+        // Unfinished dependents wait on the fix instead of the failed task
+        let conn = get_db().unwrap();
+        assert_eq!(select_task(&conn, "waiting").unwrap().depends_on, ["setup", fix.id.as_str()]);
+        assert_eq!(select_task(&conn, "done").unwrap().depends_on, ["failed"]);
+        drop(conn);
+        assert!(create_fix_task("missing", "Fix", "s1").is_err());
+    }
+
+    #[test]
+    fn test_task_run_clears_one_off_schedule() {
+        let _db = reset_test_database();
+        let mut once = test_task("once");
+        once.run_at = Some("2025-06-01T02:00:00Z".to_string());
+        let mut nightly = test_task("nightly");
+        nightly.run_at = once.run_at.clone();
+        nightly.recurrence = Some("0 2 * * *".to_string());
+        insert_test_task(&once);
+        insert_test_task(&nightly);
+
+        // A one-off task queued by hand does not run again at its scheduled time
+        start_task_run("once").unwrap();
+        start_task_run("nightly").unwrap();
+        let conn = get_db().unwrap();
+        assert_eq!(select_task(&conn, "once").unwrap().run_at, None);
+        assert_eq!(select_task(&conn, "nightly").unwrap().run_at, nightly.run_at);
+    }
+}
@@ -1,9 +1,11 @@
 // Hook Event Processor
 //
 // Watches ~/.alice/hooks-events.jsonl for new lines written by provider hook
-// scripts (Claude Code SessionStart/Stop/PreToolUse, etc.) and dispatches
+// scripts (Claude Code SessionStart/Stop/PreToolUse/Notification, etc.) and dispatches
 // native notifications and frontend events accordingly.
 
+use crate::session::SessionStatus;
+use crate::tray::{set_tray_state, TrayState};
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use tauri::{AppHandle, Emitter};
@@ -80,6 +82,7 @@ fn process_hook_event(app: &AppHandle, line: &str) {
         "stop" | "session_end" => {
             let _ = crate::notification::notify_task_completed(app, project, "Session ended", 0.0, 0);
         }
+        "notification" => mark_needs_input(app, &event),
         "pre_tool_use" => {
             let tool = event.tool.as_deref().unwrap_or("unknown tool");
             let body = format!("Wants to use: {}", tool);
@@ -91,3 +94,50 @@ fn process_hook_event(app: &AppHandle, line: &str) {
     // Emit to frontend for real-time activity feed
     let _ = app.emit("hook-event", &event);
 }
+
+/// Claude's Notification hook fires when it is waiting on the user (a
+/// permission prompt or an idle prompt): flip the session to NeedsInput.
+fn mark_needs_input(app: &AppHandle, event: &HookEvent) {
+    let non_empty = |s: &&str| !s.trim().is_empty();
+    let session_id = event.session_id.as_deref().filter(non_empty);
+    let project = event.project.as_deref().filter(non_empty);
+
+    // Another session in the same project may be the one waiting, so only
+    // the session the event names is updated.
+    let session = match session_id.map(crate::database::find_hook_session) {
+        Some(Ok(session)) => session,
+        Some(Err(e)) => {
+            tracing::warn!("Failed to look up session for notification hook: {}", e);
+            None
+        }
+        None => None,
+    };
+
+    set_tray_state(app, TrayState::Warning);
+
+    let Some(session) = session else {
+        let project_name = project
+            .map(crate::session::extract_project_name)
+            .unwrap_or_else(|| "Unknown project".to_string());
+        crate::notification::alert_needs_input(app, &project_name);
+        return;
+    };
+
+    if session.status == SessionStatus::NeedsInput {
+        return;
+    }
+    if let Err(e) = crate::database::set_session_status(&session.session_id, &SessionStatus::NeedsInput) {
+        tracing::warn!("Failed to update session {} status: {}", session.session_id, e);
+    }
+
+    crate::notification::alert_needs_input(app, &session.project_name);
+
+    let _ = app.emit(
+        "session-updated",
+        crate::watcher::SessionUpdateEvent {
+            session_id: session.session_id,
+            project_path: session.project_path,
+            status: SessionStatus::NeedsInput.as_str().to_string(),
+        },
+    );
+}
@@ -9,10 +9,15 @@ mod platform;
 mod providers;
 mod queue;
 mod report;
+mod scheduler;
 mod session;
+mod task_graph;
+mod task_log;
+mod terminal_run;
 mod tray;
 mod usage;
 mod watcher;
+mod worktree;
 
 use tauri::{
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
@@ -91,11 +96,13 @@ fn toggle_quick_window(window: &WebviewWindow, tray_position: tauri::PhysicalPos
 
 /// Initialize core services: database, queue, auto-action, and background workers.
 fn initialize_services(handle: &AppHandle) {
-    if let Err(e) = database::init_database(handle) {
+    let tokenizer = config::load_config().search_tokenizer;
+    if let Err(e) = database::init_database(handle, tokenizer) {
         tracing::error!("Failed to initialize database: {}", e);
     }
 
     queue::init_queue(handle);
+    scheduler::start_scheduler(handle.clone());
     auto_action::init_auto_action(handle);
     hook_processor::start_hook_processor(handle.clone());
 
@@ -181,12 +188,22 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::get_sessions,
             commands::get_session_detail,
+            commands::get_session_tree,
             commands::get_session_images,
             commands::get_active_sessions,
             commands::get_usage_stats,
+            commands::get_tool_stats,
+            commands::get_shell_commands,
+            commands::get_file_sessions,
             commands::get_tasks,
             commands::create_task,
             commands::update_task,
+            commands::update_task_settings,
+            commands::set_task_dependencies,
+            commands::get_task_graph,
+            commands::get_task_runs,
+            commands::tail_task_log,
+            commands::remove_task_worktree,
             commands::delete_task,
             commands::search_sessions,
             commands::search_sessions_filtered,
@@ -203,6 +220,7 @@ pub fn run() {
             commands::debug_codex_db_sessions,
             commands::start_queue,
             commands::stop_queue,
+            commands::cancel_task,
             commands::get_queue_status,
             commands::generate_daily_report,
             commands::get_daily_report,
@@ -215,6 +233,15 @@ pub fn run() {
             commands::get_system_info,
             commands::reorder_tasks,
             commands::update_session_label,
+            commands::set_session_pinned,
+            commands::set_session_notes,
+            commands::set_session_archived,
+            commands::add_session_tags,
+            commands::remove_session_tags,
+            commands::set_session_tags,
+            commands::get_tags,
+            commands::rename_tag,
+            commands::delete_tag,
             commands::scan_claude_directory,
             commands::install_hooks,
             commands::check_hooks_installed,
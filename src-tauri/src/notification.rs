@@ -45,6 +45,37 @@ pub fn notify_needs_input(app: &AppHandle, project_name: &str) -> Result<(), Str
     send_notification(app, &format!("⚠ {project_name}"), "Waiting for user input")
 }
 
+/// Send the needs-input alert if `notifications.on_needs_input` is enabled.
+pub fn alert_needs_input(app: &AppHandle, project_name: &str) {
+    if crate::config::load_config().notifications.on_needs_input {
+        if let Err(e) = notify_needs_input(app, project_name) {
+            tracing::warn!("Failed to send needs-input notification: {}", e);
+        }
+    }
+}
+
+pub fn notify_budget_exceeded(
+    app: &AppHandle,
+    project_name: &str,
+    spent: f64,
+    budget: f64,
+) -> Result<(), String> {
+    send_notification(
+        app,
+        &format!("✗ {project_name}"),
+        &format!("Budget exceeded: ${spent:.2} of ${budget:.2}, task stopped"),
+    )
+}
+
+/// Send the budget-exceeded alert if `notifications.on_budget_exceeded` is enabled.
+pub fn alert_budget_exceeded(app: &AppHandle, project_name: &str, spent: f64, budget: f64) {
+    if crate::config::load_config().notifications.on_budget_exceeded {
+        if let Err(e) = notify_budget_exceeded(app, project_name, spent, budget) {
+            tracing::warn!("Failed to send budget-exceeded notification: {}", e);
+        }
+    }
+}
+
 pub fn notify_queue_started(
     app: &AppHandle,
     project_name: &str,
@@ -1,6 +1,7 @@
 // Platform abstraction layer for cross-platform compatibility (macOS + Windows)
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 /// Get the user's home directory, panicking if unavailable.
 fn home_dir() -> PathBuf {
@@ -114,13 +115,88 @@ curl -s -X POST "http://127.0.0.1:$ALICE_PORT/notify" \
     )
 }
 
+/// Encode a project path the way Claude Code names its per-project directories:
+/// every character other than an ASCII letter or digit becomes `-`.
+pub fn encode_project_path(path: &str) -> String {
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Deepest directory level `probe_encoded_path` descends to
+const MAX_PROBE_DEPTH: usize = 32;
+
+/// Decoded project paths by encoded directory name, so the filesystem is probed once each
+static DECODED_PATHS: once_cell::sync::Lazy<std::sync::Mutex<HashMap<String, String>>> =
+    once_cell::sync::Lazy::new(Default::default);
+
+/// Find an existing directory below `dir` whose path encodes to `remaining`.
+fn probe_encoded_path(dir: &Path, remaining: &str, depth: usize) -> Option<PathBuf> {
+    if depth > MAX_PROBE_DEPTH {
+        return None;
+    }
+    let entries = std::fs::read_dir(dir).ok()?;
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        let encoded = encode_project_path(&name.to_string_lossy());
+
+        if remaining == encoded {
+            return Some(path);
+        }
+        if let Some(rest) = remaining
+            .strip_prefix(encoded.as_str())
+            .and_then(|rest| rest.strip_prefix('-'))
+        {
+            if let Some(found) = probe_encoded_path(&path, rest, depth + 1) {
+                return Some(found);
+            }
+        }
+    }
+
+    None
+}
+
 /// Decode an encoded project path from Claude Code's directory structure.
 /// Claude Code encodes paths by replacing path separators with `-`.
 ///
 /// Unix:    `-Users-alice-projects-myapp` -> `/Users/alice/projects/myapp`
 /// Windows: `-C-Users-alice-projects-myapp` -> `C:/Users/alice/projects/myapp`
-#[allow(dead_code)]
+///
+/// The encoding is lossy (`-`, `.` and `_` also become `-`), so the filesystem is
+/// probed first for an existing directory with the same encoding; results are
+/// cached per encoded name. Sessions record their real `cwd`, which should be
+/// preferred over this decoder when available.
 pub fn decode_project_path(encoded: &str) -> String {
+    if let Some(path) = DECODED_PATHS.lock().ok().and_then(|c| c.get(encoded).cloned()) {
+        return path;
+    }
+    let path = decode_project_path_uncached(encoded);
+    if let Ok(mut cache) = DECODED_PATHS.lock() {
+        cache.insert(encoded.to_string(), path.clone());
+    }
+    path
+}
+
+fn decode_project_path_uncached(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let probed = if let Some(rest) = encoded.strip_prefix('-') {
+        probe_encoded_path(Path::new("/"), rest, 1)
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && &bytes[1..3] == b"--" {
+        // Windows: `C:\` encodes to `C--`
+        let root = format!("{}:{}", bytes[0] as char, std::path::MAIN_SEPARATOR);
+        probe_encoded_path(Path::new(&root), &encoded[3..], 1)
+    } else {
+        None
+    };
+    if let Some(path) = probed {
+        return path.to_string_lossy().to_string();
+    }
+
     let decoded = urlencoding::decode(encoded).unwrap_or_else(|_| encoded.into());
     let bytes = decoded.as_bytes();
 
@@ -141,12 +217,9 @@ pub fn decode_project_path(encoded: &str) -> String {
         );
     }
 
-    // Unix: -Users-... or -home-...
-    for prefix in &["Users", "home"] {
-        let encoded_prefix = format!("-{}-", prefix);
-        if let Some(stripped) = decoded.strip_prefix(encoded_prefix.as_str()) {
-            return format!("/{}/{}", prefix, stripped.replace('-', "/"));
-        }
+    // Unix: -Users-..., -home-... or any other absolute path
+    if decoded.starts_with('-') {
+        return decoded.replace('-', "/");
     }
 
     // Already an absolute path (Unix `/...` or Windows `C:\...`)
@@ -218,6 +291,112 @@ pub fn path_file_name(path: &str) -> &str {
         .unwrap_or(path)
 }
 
+/// Start a command in a new process group, so that it and everything it
+/// spawns can be killed together with `kill_process_tree`.
+pub fn isolate_process_group(cmd: &mut tokio::process::Command) {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    #[cfg(windows)]
+    cmd.creation_flags(0x0000_0200); // CREATE_NEW_PROCESS_GROUP
+}
+
+/// Signal every process in the group led by `pgid`: SIGTERM, or SIGKILL when
+/// `force` is set. Windows has no signals, so the tree is always ended there.
+pub fn kill_process_group(pgid: u32, force: bool) -> Result<(), String> {
+    let status = if cfg!(target_os = "windows") {
+        run_quietly("taskkill", &["/T", "/F", "/PID", &pgid.to_string()])
+    } else {
+        let signal = if force { "-KILL" } else { "-TERM" };
+        // A negative PID signals the whole process group
+        run_quietly("kill", &[signal, "--", &format!("-{}", pgid)])
+    };
+    match status {
+        Ok(s) if s.success() => Ok(()),
+        Ok(s) => Err(format!("Failed to kill process group {}: {}", pgid, s)),
+        Err(e) => Err(format!("Failed to kill process group {}: {}", pgid, e)),
+    }
+}
+
+/// Terminate a process started with `isolate_process_group` and all of its descendants.
+/// A process that does not lead its own group is terminated with the group it
+/// is in, unless that is Alice's own, and then alone.
+pub fn kill_process_tree(pid: u32) -> Result<(), String> {
+    let group = kill_process_group(pid, false);
+    if group.is_ok() || cfg!(target_os = "windows") {
+        return group;
+    }
+    match process_group_of(pid) {
+        Some(pgid) if Some(pgid) != process_group_of(std::process::id()) => {
+            kill_process_group(pgid, false)
+        }
+        _ => match run_quietly("kill", &["-TERM", &pid.to_string()]) {
+            Ok(s) if s.success() => Ok(()),
+            Ok(s) => Err(format!("Failed to kill process {}: {}", pid, s)),
+            Err(e) => Err(format!("Failed to kill process {}: {}", pid, e)),
+        },
+    }
+}
+
+/// The process group a process belongs to (Unix only)
+fn process_group_of(pid: u32) -> Option<u32> {
+    let output = std::process::Command::new("ps")
+        .args(["-o", "pgid=", "-p", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+fn run_quietly(cmd: &str, args: &[&str]) -> std::io::Result<std::process::ExitStatus> {
+    std::process::Command::new(cmd)
+        .args(args)
+        .stderr(std::process::Stdio::null())
+        .status()
+}
+
+/// Whether a process is still running; None where that cannot be checked.
+pub fn is_process_alive(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "windows") {
+        let filter = format!("PID eq {}", pid);
+        let output = std::process::Command::new("tasklist")
+            .args(["/FI", &filter, "/NH", "/FO", "CSV"])
+            .output()
+            .ok()?;
+        let listed = format!("\"{}\"", pid);
+        return Some(String::from_utf8_lossy(&output.stdout).contains(&listed));
+    }
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok()
+        .map(|s| s.success())
+}
+
+/// When a process started, as the system reports it, to tell it from a later
+/// process given the same PID. None when the process is gone or unknown.
+pub fn process_start_time(pid: u32) -> Option<String> {
+    let output = if cfg!(target_os = "windows") {
+        let script = format!(
+            "(Get-Process -Id {}).StartTime.ToUniversalTime().ToString('o')",
+            pid
+        );
+        std::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &script])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?
+    } else {
+        std::process::Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()?
+    };
+    let started = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !started.is_empty()).then_some(started)
+}
+
 /// Escape a command string for embedding in AppleScript double-quoted strings.
 fn escape_for_applescript(command: &str) -> String {
     command.replace('\\', "\\\\").replace('"', "\\\"")
@@ -441,3 +620,72 @@ pub fn get_available_terminals() -> Vec<(&'static str, &'static str)> {
     terminals.push(("custom", "Custom..."));
     terminals
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_project_path() {
+        assert_eq!(encode_project_path("/Users/me/my_app.v2"), "-Users-me-my-app-v2");
+        assert_eq!(encode_project_path("C:\\Users\\me"), "C--Users-me");
+    }
+
+    #[test]
+    fn test_decode_project_path_probes_real_dirs() {
+        let root = std::env::temp_dir()
+            .canonicalize()
+            .unwrap()
+            .join(format!("alice-decode-{}", std::process::id()));
+        let project = root.join("my-app").join("web.client");
+        std::fs::create_dir_all(&project).unwrap();
+
+        let encoded = encode_project_path(&project.to_string_lossy());
+        let decoded = decode_project_path(&encoded);
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(decoded, project.to_string_lossy());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_decode_project_path_fallback() {
+        assert_eq!(decode_project_path("-alice-missing-dir"), "/alice/missing/dir");
+        assert_eq!(decode_project_path("/already/absolute"), "/already/absolute");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_process_group_reaches_children() {
+        use std::os::unix::process::CommandExt;
+        // The child ignores SIGTERM, so only the forced kill stops it
+        let mut leader = std::process::Command::new("sh")
+            .args(["-c", "trap '' TERM; sleep 30 & echo $!; wait"])
+            .stdout(std::process::Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        std::io::BufRead::read_line(
+            &mut std::io::BufReader::new(leader.stdout.take().unwrap()),
+            &mut line,
+        )
+        .unwrap();
+        let child: u32 = line.trim().parse().unwrap();
+
+        kill_process_group(leader.id(), true).unwrap();
+        leader.wait().unwrap();
+        for _ in 0..50 {
+            // The orphaned child may linger as a zombie until it is reaped
+            let state = std::process::Command::new("ps")
+                .args(["-o", "stat=", "-p", &child.to_string()])
+                .output()
+                .unwrap();
+            let state = String::from_utf8_lossy(&state.stdout);
+            if state.trim().is_empty() || state.trim().starts_with('Z') {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("process {} outlived its group", child);
+    }
+}
@@ -1,7 +1,12 @@
 // Claude Code provider implementation
 
 use super::{session_id_from_path, Provider, ProviderError, ProviderId, ProviderUsage};
-use crate::session::{extract_session_metadata, parse_session_file, Session, SessionStatus};
+use crate::session::{
+    extract_session_messages, file_idle_time, read_file_head, read_logged_session_id,
+    read_session_lines_from, KeyHistory, SessionCursor, SessionDetail, SessionStatus,
+    SessionUpdate, AGENT_FILE_PREFIX, NEEDS_INPUT_STALE,
+};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 /// Claude Code provider
@@ -50,58 +55,144 @@ impl Provider for ClaudeProvider {
             .collect()
     }
 
-    fn parse_session(&self, path: &Path) -> Result<Session, ProviderError> {
-        let lines = parse_session_file(path)
-            .map_err(|e| ProviderError::SessionParse(e.to_string()))?;
+    fn parse_session(&self, path: &Path) -> Result<SessionDetail, ProviderError> {
+        let update = self.parse_session_incremental(path, None, &())?;
+        Ok(SessionDetail {
+            session: update.session,
+            messages: update.messages,
+        })
+    }
+
+    fn parse_session_incremental(
+        &self,
+        path: &Path,
+        cursor: Option<SessionCursor>,
+        history: &dyn KeyHistory,
+    ) -> Result<SessionUpdate, ProviderError> {
+        let parse_err = |e: std::io::Error| ProviderError::SessionParse(e.to_string());
+
+        // A truncated or rotated file invalidates the cursor and its history: start over
+        let (mut cursor, full, history) = match cursor.filter(|c| c.matches_file(path)) {
+            Some(cursor) => (cursor, false, history),
+            None => (SessionCursor::default(), true, &() as &dyn KeyHistory),
+        };
 
-        if lines.is_empty() {
+        let (lines, offset) = read_session_lines_from(path, cursor.offset).map_err(parse_err)?;
+
+        if full && lines.is_empty() {
             return Err(ProviderError::SessionParse("Empty session file".to_string()));
         }
 
         let session_id = session_id_from_path(path)?;
 
-        let project_path = path
+        // Cursors saved before sub-agent links were recorded lack the parent's ID
+        let resumed_agent = !full && session_id.starts_with(AGENT_FILE_PREFIX);
+        if resumed_agent && cursor.state.parent_session_id(&session_id).is_none() {
+            cursor.state.backfill_logged_session_id(read_logged_session_id(path));
+        }
+
+        // Decoded only while no line recording `cwd` has been seen (the name is a lossy encoding)
+        let encoded_project_dir = path
             .parent()
-            .and_then(|p| p.to_str())
-            .ok_or_else(|| ProviderError::SessionParse("Invalid project path".to_string()))?
-            .to_string();
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+            .ok_or_else(|| ProviderError::SessionParse("Invalid project path".to_string()))?;
 
-        let mut session = extract_session_metadata(&session_id, &project_path, &lines);
-        session.provider = ProviderId::Claude;
+        let lines: Vec<_> = lines
+            .into_iter()
+            .filter(|line| cursor.state.accept(line, history))
+            .collect();
 
-        if self.is_session_active(path) && session.status == SessionStatus::Completed {
-            session.status = SessionStatus::Active;
+        // A sub-agent file is entirely sidechain turns and is its own session;
+        // a main file may also log sub-agent turns inline.
+        let (lines, agent_lines) = if session_id.starts_with(AGENT_FILE_PREFIX) {
+            for line in &lines {
+                cursor.state.push_line(line, history);
+            }
+            (lines, BTreeMap::new())
+        } else {
+            cursor.state.push_main_lines(&session_id, lines, history)
+        };
+        cursor.offset = offset;
+        if full {
+            cursor.head = read_file_head(path).map_err(parse_err)?;
         }
 
-        Ok(session)
-    }
+        let mut session = cursor
+            .state
+            .to_session(&session_id, || crate::platform::decode_project_path(encoded_project_dir));
+        session.provider = ProviderId::Claude;
+        session.parent_session_id = cursor.state.parent_session_id(&session_id);
 
-    fn get_usage(&self) -> Result<Option<ProviderUsage>, ProviderError> {
-        let Some(creds) = crate::usage::read_claude_credentials() else {
-            return Ok(Some(ProviderUsage::error(ProviderId::Claude, "No credentials found")));
+        let idle = file_idle_time(path).unwrap_or(NEEDS_INPUT_STALE);
+        session.status = match session.status {
+            SessionStatus::Completed if cursor.state.awaiting_reply(idle) => {
+                SessionStatus::NeedsInput
+            }
+            SessionStatus::Completed if self.is_session_active(path) => SessionStatus::Active,
+            SessionStatus::Active if cursor.state.awaiting_permission(idle) => {
+                SessionStatus::NeedsInput
+            }
+            SessionStatus::NeedsInput if idle >= NEEDS_INPUT_STALE => SessionStatus::Completed,
+            status => status,
         };
 
-        let Some(access_token) = creds.access_token else {
-            return Ok(Some(ProviderUsage::error(ProviderId::Claude, "No access token")));
-        };
+        let agents = agent_lines
+            .into_iter()
+            .filter_map(|(agent_session_id, lines)| {
+                let state = cursor.state.sidechain(&agent_session_id)?;
+                let mut agent =
+                    state.to_session(&agent_session_id, || session.project_path.clone());
+                agent.provider = ProviderId::Claude;
+                agent.parent_session_id = Some(session_id.clone());
+                Some(SessionDetail {
+                    session: agent,
+                    messages: extract_session_messages(&lines),
+                })
+            })
+            .collect();
+
+        Ok(SessionUpdate {
+            session,
+            messages: extract_session_messages(&lines),
+            full,
+            new_keys: cursor.state.take_new_keys(),
+            cursor: Some(cursor),
+            agents,
+        })
+    }
 
+    fn get_usage(&self) -> Result<Option<ProviderUsage>, ProviderError> {
         let runtime = tokio::runtime::Runtime::new()
             .map_err(|e| ProviderError::UsageFetch(format!("Failed to create runtime: {}", e)))?;
 
-        let oauth_usage = match runtime.block_on(crate::usage::fetch_oauth_usage(&access_token)) {
-            Ok(usage) => usage,
-            Err(e) => return Ok(Some(ProviderUsage::error(ProviderId::Claude, e))),
-        };
+        Ok(Some(runtime.block_on(get_claude_usage())))
+    }
+}
+
+/// Public async function to get Claude usage (avoids nested runtime issues)
+pub async fn get_claude_usage() -> ProviderUsage {
+    let Some(creds) = crate::usage::read_claude_credentials() else {
+        return ProviderUsage::error(ProviderId::Claude, "No credentials found");
+    };
+
+    let Some(access_token) = creds.access_token else {
+        return ProviderUsage::error(ProviderId::Claude, "No access token");
+    };
 
-        Ok(Some(ProviderUsage {
-            id: ProviderId::Claude,
-            session_percent: oauth_usage.five_hour.utilization,
-            session_reset_at: Some(oauth_usage.five_hour.resets_at),
-            weekly_percent: Some(oauth_usage.seven_day.utilization),
-            weekly_reset_at: Some(oauth_usage.seven_day.resets_at),
-            last_updated: chrono::Utc::now().timestamp_millis(),
-            error: None,
-        }))
+    let oauth_usage = match crate::usage::fetch_oauth_usage(&access_token).await {
+        Ok(usage) => usage,
+        Err(e) => return ProviderUsage::error(ProviderId::Claude, e),
+    };
+
+    ProviderUsage {
+        id: ProviderId::Claude,
+        session_percent: oauth_usage.five_hour.utilization,
+        session_reset_at: Some(oauth_usage.five_hour.resets_at),
+        weekly_percent: Some(oauth_usage.seven_day.utilization),
+        weekly_reset_at: Some(oauth_usage.seven_day.resets_at),
+        last_updated: chrono::Utc::now().timestamp_millis(),
+        error: None,
     }
 }
 
@@ -120,4 +211,161 @@ mod tests {
         let provider = ClaudeProvider::new();
         assert_eq!(provider.get_cli_command(), "claude");
     }
+
+    fn user_line(uuid: &str, text: &str) -> String {
+        serde_json::json!({
+            "type": "user",
+            "uuid": uuid,
+            "cwd": "/work/app",
+            "message": { "role": "user", "content": text },
+        })
+        .to_string()
+    }
+
+    fn assistant_line(uuid: &str, message_id: &str, text: &str) -> String {
+        serde_json::json!({
+            "type": "assistant",
+            "uuid": uuid,
+            "requestId": format!("req-{}", message_id),
+            "message": {
+                "id": message_id,
+                "role": "assistant",
+                "model": "claude-sonnet-4",
+                "content": [{ "type": "text", "text": text }],
+                "usage": {
+                    "input_tokens": 100,
+                    "output_tokens": 10,
+                    "cache_read_input_tokens": 0,
+                    "cache_creation_input_tokens": 0,
+                },
+            },
+        })
+        .to_string()
+    }
+
+    fn session_file(dir: &tempfile::TempDir) -> PathBuf {
+        let project_dir = dir.path().join("projects").join("-work-app");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        project_dir.join("s1.jsonl")
+    }
+
+    fn append(path: &Path, text: &str) {
+        use std::io::Write;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path).unwrap();
+        file.write_all(text.as_bytes()).unwrap();
+    }
+
+    /// Keys recorded by earlier passes, as the database keeps them
+    #[derive(Default)]
+    struct Recorded(std::collections::HashSet<u64>);
+
+    impl KeyHistory for Recorded {
+        fn contains(&self, digest: u64) -> bool {
+            self.0.contains(&digest)
+        }
+    }
+
+    #[test]
+    fn test_incremental_parse_matches_full_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = session_file(&dir);
+        let provider = ClaudeProvider::with_data_dir(dir.path().to_path_buf());
+
+        let start = [user_line("u1", "Hi"), assistant_line("a1", "m1", "Hello")];
+        append(&path, &format!("{}\n", start.join("\n")));
+        let mut recorded = Recorded::default();
+        let first = provider.parse_session_incremental(&path, None, &recorded).unwrap();
+        recorded.0.extend(&first.new_keys);
+
+        // History re-logged long after the original: its usage must not count again
+        let mut more: Vec<String> = (0..600)
+            .map(|i| assistant_line(&format!("f{}", i), &format!("f{}", i), "more"))
+            .collect();
+        more.push(assistant_line("a1-copy", "m1", "Hello"));
+        more.push(assistant_line("a2", "m2", "Bye"));
+        append(&path, &format!("{}\n", more.join("\n")));
+
+        let incremental =
+            provider.parse_session_incremental(&path, first.cursor, &recorded).unwrap();
+        let full = provider.parse_session_incremental(&path, None, &()).unwrap();
+        assert!(!incremental.full);
+        assert!(full.full);
+
+        // The keys are recorded apart from the cursor, which stays small
+        let state = serde_json::to_string(&incremental.cursor).unwrap();
+        assert!(state.len() < 1024, "cursor state is {} bytes", state.len());
+
+        let (a, b) = (&incremental.session, &full.session);
+        assert_eq!(b.input_tokens, 100 * 602);
+        assert_eq!(b.output_tokens, 10 * 602);
+        assert_eq!((a.input_tokens, a.output_tokens), (b.input_tokens, b.output_tokens));
+        assert_eq!((a.total_tokens, a.message_count), (b.total_tokens, b.message_count));
+        assert_eq!(a.total_cost_usd, b.total_cost_usd);
+    }
+
+    #[test]
+    fn test_agent_cursor_from_before_parent_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_dir = dir.path().join("projects").join("-work-app");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        let path = project_dir.join(format!("{}1.jsonl", AGENT_FILE_PREFIX));
+        let provider = ClaudeProvider::with_data_dir(dir.path().to_path_buf());
+
+        let mut line: serde_json::Value = serde_json::from_str(&user_line("u1", "Hi")).unwrap();
+        line["sessionId"] = "s1".into();
+        line["isSidechain"] = true.into();
+        append(&path, &format!("{}\n", line));
+        let update = provider.parse_session_incremental(&path, None, &()).unwrap();
+        assert_eq!(update.session.parent_session_id.as_deref(), Some("s1"));
+
+        // A cursor saved before the parent was recorded picks it up from the file
+        let mut saved = serde_json::to_value(update.cursor.unwrap()).unwrap();
+        saved["state"].as_object_mut().unwrap().remove("logged_session_id").unwrap();
+        let cursor: SessionCursor = serde_json::from_value(saved).unwrap();
+        let update = provider.parse_session_incremental(&path, Some(cursor), &()).unwrap();
+        assert!(!update.full);
+        assert_eq!(update.session.parent_session_id.as_deref(), Some("s1"));
+    }
+
+    #[test]
+    fn test_cursor_resumes_and_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = session_file(&dir);
+        let provider = ClaudeProvider::with_data_dir(dir.path().to_path_buf());
+
+        // A trailing line still being written is left for the next read
+        let first = format!("{}\n", user_line("u1", "Hi"));
+        let second = assistant_line("a1", "m1", "Hello");
+        let (head, tail) = second.split_at(second.len() / 2);
+        append(&path, &format!("{}{}", first, head));
+
+        let update = provider.parse_session_incremental(&path, None, &()).unwrap();
+        let cursor = update.cursor.unwrap();
+        assert_eq!(cursor.offset, first.len() as u64);
+        assert_eq!(update.messages.len(), 1);
+
+        append(&path, &format!("{}\n", tail));
+        assert!(cursor.matches_file(&path));
+        let update = provider.parse_session_incremental(&path, Some(cursor), &()).unwrap();
+        assert!(!update.full);
+        assert_eq!(update.messages.len(), 1);
+        assert_eq!(update.messages[0].content, "Hello");
+        let cursor = update.cursor.unwrap();
+
+        // A truncated file is parsed again from the start
+        std::fs::write(&path, format!("{}\n", user_line("u1", "Hi"))).unwrap();
+        assert!(!cursor.matches_file(&path));
+        let update = provider.parse_session_incremental(&path, Some(cursor.clone()), &()).unwrap();
+        assert!(update.full);
+        assert_eq!(update.messages.len(), 1);
+        assert_eq!(update.session.message_count, 1);
+
+        // So is a rotated one that has grown past the old offset
+        let rotated = [user_line("u9", "Other"), user_line("u10", "Another"), second];
+        std::fs::write(&path, format!("{}\n", rotated.join("\n"))).unwrap();
+        assert!(!cursor.matches_file(&path));
+        let update = provider.parse_session_incremental(&path, Some(cursor), &()).unwrap();
+        assert!(update.full);
+        assert_eq!(update.messages.len(), 3);
+    }
 }
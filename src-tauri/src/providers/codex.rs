@@ -1,7 +1,7 @@
 // OpenAI Codex CLI provider implementation
 
 use super::{session_id_from_path, Provider, ProviderError, ProviderId, ProviderUsage};
-use crate::session::{Session, SessionStatus};
+use crate::session::{Session, SessionDetail, SessionMessage, SessionStatus, ToolCall};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -58,7 +58,7 @@ impl Provider for CodexProvider {
         dirs
     }
 
-    fn parse_session(&self, path: &Path) -> Result<Session, ProviderError> {
+    fn parse_session(&self, path: &Path) -> Result<SessionDetail, ProviderError> {
         let lines = parse_codex_session_file(path)
             .map_err(|e| ProviderError::SessionParse(e.to_string()))?;
 
@@ -67,7 +67,14 @@ impl Provider for CodexProvider {
         }
 
         let session_id = session_id_from_path(path)?;
-        Ok(build_codex_session(&session_id, path, &lines))
+        let messages = build_codex_messages(&lines);
+        let mut session = build_codex_session(&session_id, path, &lines);
+        session.first_prompt = messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.clone());
+
+        Ok(SessionDetail { session, messages })
     }
 
     fn get_usage(&self) -> Result<Option<ProviderUsage>, ProviderError> {
@@ -181,6 +188,14 @@ struct Codex2026TokenUsage {
     pub total_tokens: i64,
 }
 
+/// Transcript entry carried by a Codex response item
+#[derive(Debug, Clone)]
+enum CodexTranscriptItem {
+    Message { role: String, text: String },
+    ToolCall { call_id: Option<String>, name: String, input: serde_json::Value },
+    ToolOutput { call_id: Option<String>, output: String, is_error: bool },
+}
+
 /// Unified parsed line that works with both formats
 #[derive(Debug, Clone)]
 struct ParsedCodexLine {
@@ -189,6 +204,9 @@ struct ParsedCodexLine {
     pub token_count: Option<CodexTokenCount>,
     /// 2026 format token usage (different structure)
     pub token_usage_2026: Option<Codex2026TokenUsage>,
+    /// 2026 format usage of the most recent turn only
+    pub last_token_usage: Option<Codex2026TokenUsage>,
+    pub transcript: Option<CodexTranscriptItem>,
 }
 
 /// Parse ISO 8601 timestamp to milliseconds since epoch
@@ -198,6 +216,114 @@ fn parse_iso_timestamp(ts: &str) -> Option<i64> {
         .map(|dt| dt.timestamp_millis())
 }
 
+/// Join the text parts of a response item `content` array.
+fn codex_content_text(content: Option<&serde_json::Value>) -> String {
+    let Some(serde_json::Value::Array(parts)) = content else {
+        return content.and_then(|c| c.as_str()).unwrap_or_default().to_string();
+    };
+
+    parts
+        .iter()
+        .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Tool output may be a plain string or a JSON envelope carrying an exit code.
+fn codex_tool_output(output: Option<&serde_json::Value>) -> (String, bool) {
+    let raw = match output {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(serde_json::Value::Object(obj)) => obj
+            .get("content")
+            .or_else(|| obj.get("output"))
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        _ => String::new(),
+    };
+
+    let Ok(envelope) = serde_json::from_str::<serde_json::Value>(&raw) else {
+        return (raw, false);
+    };
+    let exit_code = envelope
+        .get("metadata")
+        .and_then(|m| m.get("exit_code"))
+        .and_then(|c| c.as_i64());
+    let text = envelope
+        .get("output")
+        .and_then(|o| o.as_str())
+        .map(String::from)
+        .unwrap_or(raw);
+    (text, exit_code.is_some_and(|code| code != 0))
+}
+
+/// Parse a response item (2026 `response_item` payload or a 2025 top-level line).
+fn parse_codex_response_item(item: &serde_json::Value) -> Option<CodexTranscriptItem> {
+    let str_field = |key: &str| item.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    match item.get("type").and_then(|t| t.as_str())? {
+        "message" => Some(CodexTranscriptItem::Message {
+            role: str_field("role")?,
+            text: codex_content_text(item.get("content")),
+        }),
+        "function_call" | "custom_tool_call" => {
+            // Function call arguments are a JSON-encoded string
+            let input = match item.get("arguments").or_else(|| item.get("input")) {
+                Some(serde_json::Value::String(s)) => serde_json::from_str(s)
+                    .unwrap_or_else(|_| serde_json::Value::String(s.clone())),
+                Some(other) => other.clone(),
+                None => serde_json::Value::Null,
+            };
+            Some(CodexTranscriptItem::ToolCall {
+                call_id: str_field("call_id"),
+                name: str_field("name").unwrap_or_else(|| "unknown".to_string()),
+                input,
+            })
+        }
+        "local_shell_call" => Some(CodexTranscriptItem::ToolCall {
+            call_id: str_field("call_id"),
+            name: "shell".to_string(),
+            input: item.get("action").cloned().unwrap_or(serde_json::Value::Null),
+        }),
+        "function_call_output" | "custom_tool_call_output" | "local_shell_call_output" => {
+            let (output, is_error) = codex_tool_output(item.get("output"));
+            Some(CodexTranscriptItem::ToolOutput {
+                call_id: str_field("call_id"),
+                output,
+                is_error,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// File headers of an `apply_patch` envelope
+const PATCH_FILE_HEADERS: [&str; 4] = [
+    "*** Add File: ",
+    "*** Update File: ",
+    "*** Delete File: ",
+    "*** Move to: ",
+];
+
+/// Paths touched by an `apply_patch` envelope, including move destinations.
+/// Paths are as written in the patch (usually relative to the working directory).
+pub fn patch_file_paths(patch: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in patch.lines() {
+        let line = line.trim();
+        let Some(path) = PATCH_FILE_HEADERS.iter().find_map(|h| line.strip_prefix(h)) else {
+            continue;
+        };
+        let path = path.trim();
+        if !path.is_empty() && !paths.iter().any(|p| p == path) {
+            paths.push(path.to_string());
+        }
+    }
+    paths
+}
+
 /// Known line types for the 2026 format
 const CODEX_2026_LINE_TYPES: &[&str] = &[
     "session_meta", "turn_context", "event_msg", "item", "response_item",
@@ -225,14 +351,22 @@ fn parse_codex_line(line_str: &str) -> Option<ParsedCodexLine> {
                 None
             };
 
-            // Extract cumulative token usage from event_msg with type=token_count
-            let token_usage_2026 = if line_type == Some("event_msg")
+            // Extract cumulative and per-turn token usage from event_msg with type=token_count
+            let token_info = if line_type == Some("event_msg")
                 && payload.get("type").and_then(|t| t.as_str()) == Some("token_count")
             {
-                payload
-                    .get("info")
-                    .and_then(|info| info.get("total_token_usage"))
+                payload.get("info")
+            } else {
+                None
+            };
+            let usage_field = |key: &str| -> Option<Codex2026TokenUsage> {
+                token_info
+                    .and_then(|info| info.get(key))
                     .and_then(|usage| serde_json::from_value(usage.clone()).ok())
+            };
+
+            let transcript = if line_type == Some("response_item") {
+                parse_codex_response_item(payload)
             } else {
                 None
             };
@@ -241,18 +375,24 @@ fn parse_codex_line(line_str: &str) -> Option<ParsedCodexLine> {
                 timestamp_ms,
                 model,
                 token_count: None,
-                token_usage_2026,
+                token_usage_2026: usage_field("total_token_usage"),
+                last_token_usage: usage_field("last_token_usage"),
+                transcript,
             });
         }
     }
 
-    // Try 2025 format
-    let line2025: CodexJsonlLine = serde_json::from_str(line_str).ok()?;
+    // Try 2025 format (response items are written as top-level lines)
+    let value: serde_json::Value = serde_json::from_str(line_str).ok()?;
+    let transcript = parse_codex_response_item(&value);
+    let line2025: CodexJsonlLine = serde_json::from_value(value).ok()?;
     Some(ParsedCodexLine {
         timestamp_ms: line2025.timestamp,
         model: line2025.turn_context.and_then(|tc| tc.model),
         token_count: line2025.token_count,
         token_usage_2026: None,
+        last_token_usage: None,
+        transcript,
     })
 }
 
@@ -308,14 +448,14 @@ fn timestamps_from_file_metadata(path: &Path) -> (i64, i64) {
 /// GPT-4o: input $2.5, output $10, cached $1.25
 /// GPT-5.2-codex: input $2, output $8, cached $0.5
 /// Note: cached_input_tokens is a SUBSET of input_tokens, reasoning_output_tokens is a SUBSET of output_tokens
-struct CodexModelPricing {
+pub(crate) struct CodexModelPricing {
     input: f64,
     output: f64,
     cached: f64,
 }
 
 impl CodexModelPricing {
-    fn for_model(model: Option<&str>) -> Self {
+    pub(crate) fn for_model(model: Option<&str>) -> Self {
         match model {
             // GPT-5.2-codex pricing
             Some(m) if m.contains("5.2") || m.contains("gpt-5") => Self {
@@ -335,7 +475,7 @@ impl CodexModelPricing {
     /// Calculate cost given token counts.
     /// `input_tokens` includes cached; `output_tokens` includes reasoning.
     /// `cached_input_tokens` is the subset of `input_tokens` that were cached (cheaper rate).
-    fn calculate_cost(&self, input_tokens: i64, output_tokens: i64, cached_input_tokens: i64) -> f64 {
+    pub(crate) fn calculate_cost(&self, input_tokens: i64, output_tokens: i64, cached_input_tokens: i64) -> f64 {
         let non_cached_input = input_tokens - cached_input_tokens;
 
         (non_cached_input as f64 / 1_000_000.0) * self.input
@@ -439,7 +579,101 @@ fn build_codex_session(session_id: &str, path: &Path, lines: &[ParsedCodexLine])
         model,
         status,
         provider: ProviderId::Codex,
+        parent_session_id: None,
+        pinned: false,
+        notes: None,
+        archived: false,
+    }
+}
+
+/// Openings of the user turns Codex injects before the prompt: the
+/// environment description and AGENTS.md or configured instructions
+const CODEX_CONTEXT_PREFIXES: &[&str] = &[
+    "<environment_context>",
+    "<user_instructions>",
+    "# AGENTS.md instructions for ",
+];
+
+/// Whether a user turn is context Codex injected rather than a prompt
+fn is_injected_context(text: &str) -> bool {
+    let text = text.trim_start();
+    CODEX_CONTEXT_PREFIXES.iter().any(|prefix| text.starts_with(prefix))
+}
+
+/// Normalize parsed Codex lines into the user/assistant/tool transcript.
+fn build_codex_messages(lines: &[ParsedCodexLine]) -> Vec<SessionMessage> {
+    let mut messages: Vec<SessionMessage> = Vec::new();
+    let mut model: Option<String> = None;
+    let mut timestamp = 0i64;
+
+    for line in lines {
+        if let Some(ts) = line.timestamp_ms {
+            timestamp = ts;
+        }
+        if line.model.is_some() {
+            model = line.model.clone();
+        }
+
+        // Per-turn usage belongs to the latest assistant message without counts
+        if let Some(ref usage) = line.last_token_usage {
+            if let Some(msg) = messages
+                .iter_mut()
+                .rev()
+                .find(|m| m.role == "assistant")
+                .filter(|m| m.tokens_in.is_none())
+            {
+                msg.tokens_in = Some(usage.input_tokens);
+                msg.tokens_out = Some(usage.output_tokens);
+            }
+        }
+
+        let Some(ref item) = line.transcript else { continue };
+        match item {
+            CodexTranscriptItem::Message { role, text } => {
+                // Skip injected context (environment, AGENTS.md instructions) and empty turns
+                let is_context = role == "user" && is_injected_context(text);
+                if text.is_empty() || is_context || (role != "user" && role != "assistant") {
+                    continue;
+                }
+                messages.push(SessionMessage {
+                    role: role.clone(),
+                    content: text.clone(),
+                    timestamp,
+                    model: (role == "assistant").then(|| model.clone()).flatten(),
+                    ..Default::default()
+                });
+            }
+            CodexTranscriptItem::ToolCall { call_id, name, input } => {
+                let call = ToolCall {
+                    id: call_id.clone(),
+                    name: name.clone(),
+                    input: input.clone(),
+                };
+                match messages.last_mut() {
+                    Some(last) if last.role == "assistant" => last.tool_calls.push(call),
+                    _ => messages.push(SessionMessage {
+                        role: "assistant".to_string(),
+                        timestamp,
+                        model: model.clone(),
+                        tool_calls: vec![call],
+                        ..Default::default()
+                    }),
+                }
+            }
+            CodexTranscriptItem::ToolOutput { call_id, output, is_error } => {
+                messages.push(SessionMessage {
+                    role: "tool".to_string(),
+                    content: crate::session::truncate_chars(output, crate::session::MAX_TOOL_OUTPUT_CHARS),
+                    timestamp,
+                    tool_use_id: call_id.clone(),
+                    is_error: *is_error,
+                    ..Default::default()
+                });
+            }
+        }
     }
+
+    messages
 }
 
 /// Extract (used_percent, reset_at) from a rate-limit window JSON object.
@@ -542,4 +776,102 @@ mod tests {
         let provider = CodexProvider::new();
         assert_eq!(provider.get_cli_command(), "codex");
     }
+
+    #[test]
+    fn test_patch_file_paths() {
+        let patch = "*** Begin Patch\n*** Update File: src/lib.rs\n*** Move to: src/main.rs\n@@\n-a\n+b\n*** Add File: README.md\n+hi\n*** Update File: src/lib.rs\n*** End Patch";
+        assert_eq!(patch_file_paths(patch), vec!["src/lib.rs", "src/main.rs", "README.md"]);
+    }
+
+    fn response_item(payload: serde_json::Value) -> ParsedCodexLine {
+        let line = serde_json::json!({ "type": "response_item", "payload": payload });
+        parse_codex_line(&line.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_transcript_skips_injected_context() {
+        let user = |text: &str| {
+            response_item(serde_json::json!({
+                "type": "message",
+                "role": "user",
+                "content": [{ "type": "input_text", "text": text }],
+            }))
+        };
+        let lines = vec![
+            user("<environment_context>\n  <cwd>/work/app</cwd>\n</environment_context>"),
+            user("<user_instructions>\nBe brief\n</user_instructions>"),
+            user("# AGENTS.md instructions for /work/app\n\n<INSTRUCTIONS>\nUse tabs\n</INSTRUCTIONS>"),
+            user("<div>Why is this not centered?</div>"),
+        ];
+
+        let messages = build_codex_messages(&lines);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "<div>Why is this not centered?</div>");
+    }
+
+    #[test]
+    fn test_transcript_tool_calls_and_outputs() {
+        let envelope = serde_json::json!({ "output": "no such file", "metadata": { "exit_code": 2 } });
+        let lines = vec![
+            response_item(serde_json::json!({
+                "type": "message",
+                "role": "user",
+                "content": [{ "type": "input_text", "text": "List files" }],
+            })),
+            response_item(serde_json::json!({
+                "type": "function_call",
+                "name": "shell",
+                "call_id": "c1",
+                "arguments": "{\"command\":[\"ls\"]}",
+            })),
+            response_item(serde_json::json!({
+                "type": "function_call_output",
+                "call_id": "c1",
+                "output": envelope.to_string(),
+            })),
+            response_item(serde_json::json!({
+                "type": "custom_tool_call",
+                "name": "apply_patch",
+                "call_id": "c2",
+                "input": "*** Begin Patch",
+            })),
+            response_item(serde_json::json!({
+                "type": "custom_tool_call_output",
+                "call_id": "c2",
+                "output": "Done!",
+            })),
+            response_item(serde_json::json!({
+                "type": "local_shell_call",
+                "call_id": "c3",
+                "action": { "type": "exec", "command": ["pwd"] },
+            })),
+            response_item(serde_json::json!({
+                "type": "local_shell_call_output",
+                "call_id": "c3",
+                "output": { "output": "/tmp" },
+            })),
+        ];
+
+        let messages = build_codex_messages(&lines);
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "assistant", "tool", "assistant", "tool"]);
+
+        // Function call arguments are decoded; custom tool input is kept as text
+        let call = &messages[1].tool_calls[0];
+        assert_eq!((call.id.as_deref(), call.name.as_str()), (Some("c1"), "shell"));
+        assert_eq!(call.input, serde_json::json!({ "command": ["ls"] }));
+        assert_eq!(messages[3].tool_calls[0].input, serde_json::json!("*** Begin Patch"));
+        let shell = &messages[5].tool_calls[0];
+        assert_eq!(shell.name, "shell");
+        assert_eq!(shell.input["command"], serde_json::json!(["pwd"]));
+
+        // A non-zero exit code in the output envelope marks the result as an error
+        assert_eq!(messages[2].tool_use_id.as_deref(), Some("c1"));
+        assert_eq!(messages[2].content, "no such file");
+        assert!(messages[2].is_error);
+        assert_eq!(messages[4].content, "Done!");
+        assert!(!messages[4].is_error);
+        assert_eq!(messages[6].tool_use_id.as_deref(), Some("c3"));
+        assert_eq!(messages[6].content, "/tmp");
+    }
 }
@@ -1,19 +1,20 @@
 // Google Gemini CLI provider implementation
 //
-// Partial implementation: supports usage tracking (OAuth quota API) and task execution.
-// Session file parsing is not implemented (format unknown).
+// Sessions live under ~/.gemini/tmp/<project-hash>/: recorded chats in
+// chats/session-*.json and conversations saved with `/chat save` in checkpoint-*.json.
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 
-use super::{Provider, ProviderError, ProviderId, ProviderUsage};
-use crate::session::Session;
+use super::{session_id_from_path, Provider, ProviderError, ProviderId, ProviderUsage};
+use crate::session::{Session, SessionDetail, SessionMessage, SessionStatus, ToolCall};
 
 /// Gemini provider
 pub struct GeminiProvider {
-    #[allow(dead_code)]
     data_dir: PathBuf,
 }
 
@@ -46,13 +47,78 @@ impl Provider for GeminiProvider {
     }
 
     fn get_session_dirs(&self) -> Vec<PathBuf> {
-        Vec::new()
+        // Watch the whole tmp directory so newly created project dirs are picked up
+        let tmp_dir = self.data_dir.join("tmp");
+        if tmp_dir.is_dir() {
+            vec![tmp_dir]
+        } else {
+            vec![]
+        }
     }
 
-    fn parse_session(&self, _path: &Path) -> Result<Session, ProviderError> {
-        Err(ProviderError::SessionParse(
-            "Gemini session parsing not implemented (format unknown)".to_string(),
-        ))
+    fn is_session_file(&self, path: &Path) -> bool {
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            return false;
+        }
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let in_chats = path
+            .parent()
+            .and_then(|p| p.file_name())
+            .is_some_and(|name| name == "chats");
+
+        (in_chats && stem.starts_with("session-")) || (!in_chats && stem.starts_with("checkpoint-"))
+    }
+
+    fn resolve_project_path(&self, path: &Path, known_roots: &[String]) -> Option<String> {
+        match_project_hash(gemini_project_dir(path)?, known_roots)
+    }
+
+    fn parse_session(&self, path: &Path) -> Result<SessionDetail, ProviderError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ProviderError::SessionParse(e.to_string()))?;
+        let json: Value = serde_json::from_str(&content)
+            .map_err(|e| ProviderError::SessionParse(e.to_string()))?;
+
+        let project_dir = gemini_project_dir(path)
+            .ok_or_else(|| ProviderError::SessionParse("Invalid project path".to_string()))?;
+        let project_path = recorded_project_root(project_dir);
+
+        let parsed = match json {
+            // Checkpoints are a bare array of API `Content` entries
+            Value::Array(contents) => {
+                let stem = session_id_from_path(path)?;
+                let hash = project_dir
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or_default();
+                let short_hash = hash.get(..8).unwrap_or(hash);
+                parse_checkpoint(&format!("{}-{}", short_hash, stem), &contents)
+            }
+            Value::Object(_) => {
+                let chat: GeminiChatFile = serde_json::from_value(json)
+                    .map_err(|e| ProviderError::SessionParse(e.to_string()))?;
+                let session_id = match chat.session_id.clone() {
+                    Some(id) => id,
+                    None => session_id_from_path(path)?,
+                };
+                parse_chat(session_id, chat)
+            }
+            _ => {
+                return Err(ProviderError::SessionParse(
+                    "Unrecognized Gemini session file".to_string(),
+                ))
+            }
+        };
+
+        if parsed.messages.is_empty() {
+            return Err(ProviderError::SessionParse("Empty session file".to_string()));
+        }
+
+        let session = build_gemini_session(path, &project_path, parsed.session_id, &parsed.messages, parsed.totals);
+        Ok(SessionDetail {
+            session,
+            messages: parsed.messages,
+        })
     }
 
     fn get_usage(&self) -> Result<Option<ProviderUsage>, ProviderError> {
@@ -66,6 +132,412 @@ impl Provider for GeminiProvider {
     }
 }
 
+// -- Session file parsing --
+
+/// Recorded chat file (chats/session-*.json)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiChatFile {
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    messages: Vec<GeminiChatMessage>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiChatMessage {
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    timestamp: Option<String>,
+    /// "user", "gemini", "info", "error" or "warning"
+    #[serde(rename = "type")]
+    message_type: String,
+    #[serde(default)]
+    content: Value,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    tokens: Option<GeminiTokens>,
+    #[serde(default)]
+    tool_calls: Vec<GeminiToolCall>,
+}
+
+/// Per-response token counts. `cached` is a subset of `input`;
+/// `thoughts` are billed as output but reported separately.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct GeminiTokens {
+    #[serde(default)]
+    input: i64,
+    #[serde(default)]
+    output: i64,
+    #[serde(default)]
+    cached: i64,
+    #[serde(default)]
+    thoughts: i64,
+    #[serde(default)]
+    tool: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiToolCall {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: Value,
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    result_display: Option<Value>,
+    #[serde(default)]
+    status: Option<String>,
+}
+
+/// Token totals across a session
+#[derive(Debug, Clone, Default)]
+struct GeminiTotals {
+    input: i64,
+    output: i64,
+    cached: i64,
+    model: Option<String>,
+}
+
+/// Output of parsing one chat or checkpoint file
+struct ParsedGeminiSession {
+    session_id: String,
+    messages: Vec<SessionMessage>,
+    totals: GeminiTotals,
+}
+
+/// The `tmp/<project-hash>` directory a session file belongs to.
+fn gemini_project_dir(path: &Path) -> Option<&Path> {
+    let parent = path.parent()?;
+    if parent.file_name().is_some_and(|name| name == "chats") {
+        parent.parent()
+    } else {
+        Some(parent)
+    }
+}
+
+/// Project directories are named by the SHA-256 of the project root, which cannot be
+/// reversed. Newer CLI versions record the root in `.project_root`; otherwise the
+/// project is left empty here rather than named after the hash directory, for the
+/// caller to match against known roots (see `resolve_project_path`).
+fn recorded_project_root(project_dir: &Path) -> String {
+    std::fs::read_to_string(project_dir.join(".project_root"))
+        .map(|root| root.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// The root among `known_roots` whose hash names the project directory
+fn match_project_hash(project_dir: &Path, known_roots: &[String]) -> Option<String> {
+    let hash = project_dir.file_name()?.to_str()?;
+    known_roots.iter().find(|root| project_hash(root) == hash).cloned()
+}
+
+/// Name of the tmp directory Gemini CLI keeps for a project root
+fn project_hash(project_root: &str) -> String {
+    format!("{:x}", Sha256::digest(project_root.as_bytes()))
+}
+
+/// Parse an ISO 8601 timestamp to epoch milliseconds.
+fn parse_iso_millis(ts: Option<&str>) -> Option<i64> {
+    ts.and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|dt| dt.timestamp_millis())
+}
+
+/// Text of a message `content`, which is either a string or a list of parts.
+fn gemini_text(content: &Value) -> String {
+    match content {
+        Value::String(s) => s.trim().to_string(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        _ => String::new(),
+    }
+}
+
+/// Text of a tool result: prefer the display string, then function response output.
+fn gemini_tool_output(call: &GeminiToolCall) -> String {
+    if let Some(Value::String(display)) = &call.result_display {
+        return display.clone();
+    }
+
+    let responses = match &call.result {
+        Value::Array(parts) => parts.clone(),
+        Value::Null => vec![],
+        other => vec![other.clone()],
+    };
+    responses
+        .iter()
+        .filter_map(|part| part.get("functionResponse").and_then(|r| r.get("response")))
+        .map(|response| match response.get("output").or_else(|| response.get("error")) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => response.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Normalize a recorded chat file into transcript messages and token totals.
+fn parse_chat(session_id: String, chat: GeminiChatFile) -> ParsedGeminiSession {
+    let mut messages = Vec::new();
+    let mut totals = GeminiTotals::default();
+
+    for msg in chat.messages {
+        let timestamp = parse_iso_millis(msg.timestamp.as_deref()).unwrap_or(0);
+        match msg.message_type.as_str() {
+            "user" => {
+                let text = gemini_text(&msg.content);
+                if !text.is_empty() {
+                    messages.push(SessionMessage {
+                        id: msg.id,
+                        role: "user".to_string(),
+                        content: text,
+                        timestamp,
+                        ..Default::default()
+                    });
+                }
+            }
+            "gemini" => {
+                let tokens = msg.tokens.unwrap_or_default();
+                totals.input += tokens.input + tokens.tool;
+                totals.output += tokens.output + tokens.thoughts;
+                totals.cached += tokens.cached;
+                if totals.model.is_none() {
+                    totals.model = msg.model.clone();
+                }
+
+                let tool_calls = msg
+                    .tool_calls
+                    .iter()
+                    .map(|call| ToolCall {
+                        id: call.id.clone(),
+                        name: call.name.clone(),
+                        input: call.args.clone(),
+                    })
+                    .collect();
+                messages.push(SessionMessage {
+                    id: msg.id,
+                    role: "assistant".to_string(),
+                    content: gemini_text(&msg.content),
+                    timestamp,
+                    tokens_in: Some(tokens.input + tokens.tool),
+                    tokens_out: Some(tokens.output + tokens.thoughts),
+                    model: msg.model,
+                    tool_calls,
+                    ..Default::default()
+                });
+
+                // Tool results are recorded inline with the call
+                for call in &msg.tool_calls {
+                    messages.push(SessionMessage {
+                        role: "tool".to_string(),
+                        content: crate::session::truncate_chars(
+                            &gemini_tool_output(call),
+                            crate::session::MAX_TOOL_OUTPUT_CHARS,
+                        ),
+                        timestamp,
+                        tool_use_id: call.id.clone(),
+                        is_error: call.status.as_deref() == Some("error"),
+                        ..Default::default()
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ParsedGeminiSession {
+        session_id,
+        messages,
+        totals,
+    }
+}
+
+/// Normalize a saved checkpoint (API `Content` list) into transcript messages.
+/// Checkpoints carry no timestamps or token counts.
+fn parse_checkpoint(session_id: &str, contents: &[Value]) -> ParsedGeminiSession {
+    let mut messages: Vec<SessionMessage> = Vec::new();
+
+    for content in contents {
+        let role = content.get("role").and_then(|r| r.as_str()).unwrap_or_default();
+        let Some(parts) = content.get("parts").and_then(|p| p.as_array()) else {
+            continue;
+        };
+
+        let text = gemini_text(&Value::Array(parts.clone()));
+        let tool_calls: Vec<ToolCall> = parts
+            .iter()
+            .filter_map(|part| part.get("functionCall"))
+            .map(|call| ToolCall {
+                id: call.get("id").and_then(|v| v.as_str()).map(String::from),
+                name: call
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown")
+                    .to_string(),
+                input: call.get("args").cloned().unwrap_or(Value::Null),
+            })
+            .collect();
+
+        for response in parts.iter().filter_map(|part| part.get("functionResponse")) {
+            let output = response.get("response").map(|r| match r.get("output") {
+                Some(Value::String(s)) => s.clone(),
+                _ => r.to_string(),
+            });
+            messages.push(SessionMessage {
+                role: "tool".to_string(),
+                content: crate::session::truncate_chars(
+                    &output.unwrap_or_default(),
+                    crate::session::MAX_TOOL_OUTPUT_CHARS,
+                ),
+                tool_use_id: response.get("id").and_then(|v| v.as_str()).map(String::from),
+                is_error: response.get("response").is_some_and(|r| r.get("error").is_some()),
+                ..Default::default()
+            });
+        }
+
+        if text.is_empty() && tool_calls.is_empty() {
+            continue;
+        }
+        messages.push(SessionMessage {
+            role: if role == "model" { "assistant" } else { "user" }.to_string(),
+            content: text,
+            tool_calls,
+            ..Default::default()
+        });
+    }
+
+    ParsedGeminiSession {
+        session_id: session_id.to_string(),
+        messages,
+        totals: GeminiTotals::default(),
+    }
+}
+
+/// Gemini API pricing (per 1M tokens, prompts up to 200k tokens)
+/// Pro: input $1.25, output $10, cached $0.31
+/// Flash: input $0.30, output $2.50, cached $0.075
+/// Flash-Lite: input $0.10, output $0.40, cached $0.025
+struct GeminiModelPricing {
+    input: f64,
+    output: f64,
+    cached: f64,
+}
+
+impl GeminiModelPricing {
+    fn for_model(model: Option<&str>) -> Self {
+        match model {
+            Some(m) if m.contains("flash-lite") => Self {
+                input: 0.10,
+                output: 0.40,
+                cached: 0.025,
+            },
+            Some(m) if m.contains("flash") => Self {
+                input: 0.30,
+                output: 2.50,
+                cached: 0.075,
+            },
+            // Default to Pro pricing
+            _ => Self {
+                input: 1.25,
+                output: 10.0,
+                cached: 0.31,
+            },
+        }
+    }
+
+    /// `input_tokens` includes cached tokens; `output_tokens` includes thoughts.
+    fn calculate_cost(&self, input_tokens: i64, output_tokens: i64, cached_tokens: i64) -> f64 {
+        let non_cached_input = input_tokens - cached_tokens;
+
+        (non_cached_input as f64 / 1_000_000.0) * self.input
+            + (cached_tokens as f64 / 1_000_000.0) * self.cached
+            + (output_tokens as f64 / 1_000_000.0) * self.output
+    }
+}
+
+/// Build a Session from a parsed Gemini transcript
+fn build_gemini_session(
+    path: &Path,
+    project_path: &str,
+    session_id: String,
+    messages: &[SessionMessage],
+    totals: GeminiTotals,
+) -> Session {
+    let timestamps = || messages.iter().map(|m| m.timestamp).filter(|&ts| ts > 0);
+    let (file_created, file_modified) = file_timestamps(path);
+    let started_at = timestamps().min().unwrap_or(file_created);
+    let last_active_at = timestamps().max().unwrap_or(file_modified);
+    let last_human_message_at = messages
+        .iter()
+        .filter(|m| m.role == "user" && m.timestamp > 0)
+        .map(|m| m.timestamp)
+        .max()
+        .unwrap_or(last_active_at);
+
+    let pricing = GeminiModelPricing::for_model(totals.model.as_deref());
+
+    let status = if crate::session::is_session_active(path) {
+        SessionStatus::Active
+    } else {
+        SessionStatus::Completed
+    };
+
+    Session {
+        session_id,
+        project_path: project_path.to_string(),
+        project_name: crate::session::extract_project_name(project_path),
+        first_prompt: messages
+            .iter()
+            .find(|m| m.role == "user")
+            .map(|m| m.content.clone()),
+        label: None,
+        tags: vec![],
+        started_at,
+        last_active_at,
+        last_human_message_at,
+        message_count: messages.iter().filter(|m| m.role != "tool").count() as i32,
+        total_tokens: totals.input + totals.output,
+        total_cost_usd: pricing.calculate_cost(totals.input, totals.output, totals.cached),
+        input_tokens: totals.input,
+        output_tokens: totals.output,
+        cache_read_tokens: totals.cached,
+        cache_write_tokens: 0,
+        model: totals.model,
+        status,
+        provider: ProviderId::Gemini,
+        parent_session_id: None,
+        pinned: false,
+        notes: None,
+        archived: false,
+    }
+}
+
+/// (created, modified) file times in epoch milliseconds, falling back to now.
+fn file_timestamps(path: &Path) -> (i64, i64) {
+    let now = chrono::Utc::now().timestamp_millis();
+    let to_millis = |t: std::io::Result<std::time::SystemTime>| {
+        t.ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+    };
+    let Ok(meta) = std::fs::metadata(path) else {
+        return (now, now);
+    };
+    let modified = to_millis(meta.modified()).unwrap_or(now);
+    (to_millis(meta.created()).unwrap_or(modified), modified)
+}
+
 // -- OAuth credentials --
 
 #[derive(Debug, Clone, Deserialize)]
@@ -256,3 +728,165 @@ fn build_usage_from_quotas(
         error: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chat_fixture() -> Value {
+        serde_json::json!({
+            "sessionId": "g1",
+            "messages": [
+                { "id": "1", "type": "info", "content": "Logged in" },
+                {
+                    "id": "2",
+                    "type": "user",
+                    "timestamp": "2025-06-01T10:00:00Z",
+                    "content": [{ "text": "List the files" }],
+                },
+                {
+                    "id": "3",
+                    "type": "gemini",
+                    "timestamp": "2025-06-01T10:00:05Z",
+                    "content": "Listing them.",
+                    "model": "gemini-2.5-pro",
+                    "tokens": {
+                        "input": 100, "output": 20, "cached": 40, "thoughts": 5, "tool": 3,
+                    },
+                    "toolCalls": [
+                        {
+                            "id": "c1",
+                            "name": "list_directory",
+                            "args": { "path": "." },
+                            "resultDisplay": "Listed 2 item(s).",
+                            "status": "success",
+                        },
+                        {
+                            "id": "c2",
+                            "name": "read_file",
+                            "args": { "path": "missing" },
+                            "result": [{
+                                "functionResponse": {
+                                    "id": "c2",
+                                    "response": { "error": "File not found" },
+                                },
+                            }],
+                            "status": "error",
+                        },
+                    ],
+                },
+                {
+                    "id": "4",
+                    "type": "gemini",
+                    "timestamp": "2025-06-01T10:00:09Z",
+                    "content": "Done.",
+                    "model": "gemini-2.5-flash",
+                    "tokens": { "input": 200, "output": 10, "cached": 0 },
+                },
+            ],
+        })
+    }
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_session_file_names() {
+        let provider = GeminiProvider::with_data_dir(PathBuf::from("/g"));
+        let project = Path::new("/g/tmp/abc");
+        assert!(provider.is_session_file(&project.join("chats/session-2025-06-01T10-00-g1.json")));
+        assert!(provider.is_session_file(&project.join("checkpoint-work.json")));
+
+        assert!(!provider.is_session_file(&project.join("chats/checkpoint-work.json")));
+        assert!(!provider.is_session_file(&project.join("session-g1.json")));
+        assert!(!provider.is_session_file(&project.join("logs.json")));
+        assert!(!provider.is_session_file(&project.join("chats/session-g1.jsonl")));
+    }
+
+    #[test]
+    fn test_parse_chat() {
+        let chat: GeminiChatFile = serde_json::from_value(chat_fixture()).unwrap();
+        let parsed = parse_chat("g1".to_string(), chat);
+
+        let roles: Vec<&str> = parsed.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "tool", "assistant"]);
+
+        // Tool-use prompt tokens count as input, thoughts as output
+        assert_eq!(parsed.totals.input, 303);
+        assert_eq!(parsed.totals.output, 35);
+        assert_eq!(parsed.totals.cached, 40);
+        assert_eq!(parsed.totals.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!(parsed.messages[1].tokens_in, Some(103));
+        let read = &parsed.messages[1].tool_calls[1];
+        assert_eq!(read.input, serde_json::json!({ "path": "missing" }));
+
+        let (listed, failed) = (&parsed.messages[2], &parsed.messages[3]);
+        assert_eq!(listed.tool_use_id.as_deref(), Some("c1"));
+        assert_eq!(listed.content, "Listed 2 item(s).");
+        assert!(!listed.is_error);
+        assert_eq!(failed.tool_use_id.as_deref(), Some("c2"));
+        assert_eq!(failed.content, "File not found");
+        assert!(failed.is_error);
+    }
+
+    #[test]
+    fn test_parse_checkpoint() {
+        let contents = serde_json::json!([
+            { "role": "user", "parts": [{ "text": "Read main.rs" }] },
+            {
+                "role": "model",
+                "parts": [
+                    { "text": "Reading." },
+                    {
+                        "functionCall": {
+                            "id": "c1", "name": "read_file", "args": { "path": "main.rs" },
+                        },
+                    },
+                ],
+            },
+            {
+                "role": "user",
+                "parts": [{
+                    "functionResponse": { "id": "c1", "response": { "output": "fn main() {}" } },
+                }],
+            },
+        ]);
+        let parsed = parse_checkpoint("abc-checkpoint-work", contents.as_array().unwrap());
+
+        let roles: Vec<&str> = parsed.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool"]);
+        assert_eq!(parsed.messages[1].tool_calls[0].name, "read_file");
+        assert_eq!(parsed.messages[2].tool_use_id.as_deref(), Some("c1"));
+        assert_eq!(parsed.messages[2].content, "fn main() {}");
+        assert_eq!(parsed.totals.input + parsed.totals.output, 0);
+    }
+
+    #[test]
+    fn test_parse_session_project_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = GeminiProvider::with_data_dir(dir.path().to_path_buf());
+        let hash = project_hash("/work/app");
+        let chat_path = dir.path().join("tmp").join(&hash).join("chats/session-g1.json");
+        write(&chat_path, &chat_fixture().to_string());
+
+        // The hash directory is never used as the project
+        assert_eq!(provider.parse_session(&chat_path).unwrap().session.project_path, "");
+        let known = ["/work/other".to_string(), "/work/app".to_string()];
+        assert_eq!(provider.resolve_project_path(&chat_path, &known[..1]), None);
+        assert_eq!(provider.resolve_project_path(&chat_path, &known).as_deref(), Some("/work/app"));
+
+        let project_dir = chat_path.parent().unwrap().parent().unwrap();
+        write(&project_dir.join(".project_root"), "/work/app\n");
+        let session = provider.parse_session(&chat_path).unwrap().session;
+        assert_eq!(session.session_id, "g1");
+        assert_eq!(session.project_path, "/work/app");
+        assert_eq!(session.project_name, "app");
+        assert_eq!(session.first_prompt.as_deref(), Some("List the files"));
+        assert_eq!(session.model.as_deref(), Some("gemini-2.5-pro"));
+        assert_eq!((session.input_tokens, session.output_tokens), (303, 35));
+        assert_eq!(session.total_tokens, 338);
+        assert_eq!(session.message_count, 3);
+    }
+}
@@ -1,6 +1,6 @@
 // Provider abstraction for multi-AI CLI support
 
-use crate::session::Session;
+use crate::session::{KeyHistory, SessionCursor, SessionDetail, SessionUpdate};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -125,8 +125,39 @@ pub trait Provider: Send + Sync {
     /// Get the session directories to monitor
     fn get_session_dirs(&self) -> Vec<PathBuf>;
 
-    /// Parse a session file and extract metadata
-    fn parse_session(&self, path: &Path) -> Result<Session, ProviderError>;
+    /// Check whether a file inside a session directory is a session log
+    fn is_session_file(&self, path: &Path) -> bool {
+        path.extension().is_some_and(|e| e == "jsonl")
+    }
+
+    /// Parse a session file into its metadata and normalized transcript
+    fn parse_session(&self, path: &Path) -> Result<SessionDetail, ProviderError>;
+
+    /// Project root of a session whose file did not record one, matched
+    /// against the project paths already known. None if it stays unresolved.
+    fn resolve_project_path(&self, _path: &Path, _known_roots: &[String]) -> Option<String> {
+        None
+    }
+
+    /// Parse only what was appended to a session file since `cursor`, with
+    /// `history` holding the keys recorded by the reads before it.
+    /// Providers without tail parsing re-parse the whole file and return no cursor.
+    fn parse_session_incremental(
+        &self,
+        path: &Path,
+        _cursor: Option<SessionCursor>,
+        _history: &dyn KeyHistory,
+    ) -> Result<SessionUpdate, ProviderError> {
+        let detail = self.parse_session(path)?;
+        Ok(SessionUpdate {
+            session: detail.session,
+            messages: detail.messages,
+            full: true,
+            cursor: None,
+            agents: Vec::new(),
+            new_keys: Vec::new(),
+        })
+    }
 
     /// Get current usage statistics from OAuth API.
     /// Returns None if provider doesn't support usage tracking.
@@ -173,6 +204,15 @@ pub fn get_provider(id: ProviderId) -> Box<dyn Provider> {
     }
 }
 
+/// Fetch a provider's live usage from async code
+pub async fn fetch_usage(id: ProviderId) -> Result<ProviderUsage, String> {
+    match id {
+        ProviderId::Claude => Ok(claude::get_claude_usage().await),
+        ProviderId::Codex => codex::get_codex_usage().await,
+        ProviderId::Gemini => gemini::get_gemini_usage().await,
+    }
+}
+
 /// Get enabled providers from configuration
 pub fn get_enabled_providers() -> Vec<Box<dyn Provider>> {
     let config = crate::config::load_config();
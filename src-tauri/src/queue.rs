@@ -1,23 +1,85 @@
 // Task Queue Engine - Execute tasks via provider CLI subprocesses
 
-use crate::database::{self, Task, TaskStatus};
+use crate::config::InterruptedTaskAction;
+use crate::database::{self, ExecutionMode, Task, TaskRun, TaskRunStatus, TaskStatus};
 use crate::notification;
+use crate::providers::codex::CodexModelPricing;
+use crate::providers::ProviderId;
+use crate::session::{ModelPricing, TokenUsage};
+use crate::task_graph::{Readiness, TaskIndex};
+use crate::task_log::{LogStream, TaskLog};
+use crate::terminal_run::TerminalRun;
+use crate::worktree::Worktree;
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::process::Stdio;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinSet;
 
-/// Queue executor state
+/// How long a terminated process tree gets to exit before it is killed outright
+const TERMINATE_GRACE: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Attempts an overloaded provider gets, even under a stricter retry policy
+const OVERLOADED_MAX_ATTEMPTS: i32 = 5;
+
+/// Rate-limited runs a task may wait out before it fails, since it last entered the queue
+const RATE_LIMITED_MAX_RETRIES: usize = 10;
+
+/// Verification output quoted in the prompt of a task fixing it
+const FIX_PROMPT_OUTPUT_CHARS: usize = 8000;
+
+/// How long a provider's usage check is trusted before it is fetched again
+const USAGE_CHECK_INTERVAL: chrono::Duration = chrono::Duration::minutes(1);
+
+/// How often a terminal run's log and exit status are checked
+const TERMINAL_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Most of a terminal run's log read in one poll
+const TERMINAL_READ_BYTES: u64 = 1024 * 1024;
+
+/// Queue executor state. Clones share state, so workers can run tasks concurrently.
+#[derive(Clone)]
 pub struct QueueExecutor {
     app: AppHandle,
     running: Arc<AtomicBool>,
-    current_task: Arc<Mutex<Option<String>>>,
+    /// Counts starts of the queue. A loop left draining its workers after a
+    /// stop launches nothing once a later start has taken over.
+    generation: Arc<std::sync::Mutex<u64>>,
+    /// Running tasks by ID
+    running_tasks: Arc<Mutex<HashMap<String, RunningTask>>>,
+    /// Notified to rouse the queue loop while it waits for a retry
+    wake: Arc<Notify>,
+    /// Latest usage check of each provider the queue has tasks for
+    usage_checks: Arc<Mutex<HashMap<ProviderId, UsageCheck>>>,
+}
+
+/// A provider's usage as last checked, and whether it holds the provider's tasks
+#[derive(Debug, Clone, Copy)]
+struct UsageCheck {
+    checked_at: DateTime<Utc>,
+    /// Usage is at the pause threshold, or a run hit the rate limit, until this time
+    paused_until: Option<DateTime<Utc>>,
+}
+
+/// A task occupying a worker slot
+#[derive(Clone)]
+struct RunningTask {
+    project_path: Option<String>,
+    /// Runs in its own worktree, clear of the project's working copy
+    isolated: bool,
+    /// Notified to cancel the task
+    cancel: Arc<Notify>,
 }
 
+/// A finished worker: the task it ran and how that went
+type WorkerOutcome = (Task, Result<TaskResult, String>);
+
 /// Task execution result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskResult {
@@ -27,20 +89,155 @@ pub struct TaskResult {
     pub tokens_used: i64,
     pub cost_usd: f64,
     pub duration_secs: u64,
+    /// Session the run wrote to, when known
+    pub session_id: Option<String>,
+    /// Why Alice stopped the run early
+    pub error: Option<String>,
+    /// How the run ended
+    pub status: TaskStatus,
+    pub stderr: String,
+    /// When the task runs again, if the attempt is retried
+    pub retry_at: Option<String>,
+}
+
+/// What the queue is doing
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueState {
+    Stopped,
+    Running,
+    /// Nothing is running and queued tasks wait for provider usage to reset
+    WaitingForReset,
 }
 
 /// Queue status event for frontend
 #[derive(Debug, Clone, Serialize)]
 pub struct QueueStatusEvent {
     pub is_running: bool,
-    pub current_task_id: Option<String>,
+    pub state: QueueState,
+    pub running_task_ids: Vec<String>,
     pub queued_count: usize,
+    /// When held tasks may start again, while usage limits hold any back
+    pub resume_at: Option<String>,
 }
 
 // ============================================================================
 // Helpers
 // ============================================================================
 
+/// Parse a stored RFC 3339 timestamp
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Whether a queued task is still waiting out a retry delay
+fn is_delayed(task: &Task, now: DateTime<Utc>) -> bool {
+    task.next_attempt_at
+        .as_deref()
+        .and_then(parse_timestamp)
+        .is_some_and(|at| at > now)
+}
+
+/// Whether a task should run in its own git worktree. Only new sessions are
+/// isolated: resumed ones have to run where their session was recorded.
+fn wants_worktree(task: &Task, queue: &crate::config::QueueConfig) -> bool {
+    task.project_path.is_some()
+        && task.use_worktree.unwrap_or(queue.use_worktrees)
+        && task.execution_mode == ExecutionMode::New
+}
+
+/// Until when a provider's usage holds its tasks back, once it reaches
+/// `threshold` percent. The weekly limit outlasts the session one. A reset
+/// that is unknown is checked again after the interval; one already past
+/// holds nothing back.
+fn pause_until(
+    usage: &crate::providers::ProviderUsage,
+    threshold: f64,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if threshold <= 0.0 {
+        return None;
+    }
+    let reset_at = |at: &Option<String>| at.as_deref().and_then(parse_timestamp);
+    let limited_until = if usage.weekly_percent.is_some_and(|p| p >= threshold) {
+        reset_at(&usage.weekly_reset_at)
+    } else if usage.session_percent >= threshold {
+        reset_at(&usage.session_reset_at)
+    } else {
+        return None;
+    };
+    match limited_until {
+        Some(at) => (at > now).then_some(at),
+        None => Some(now + USAGE_CHECK_INTERVAL),
+    }
+}
+
+/// What the queue is doing: waiting for a reset only while nothing runs
+fn queue_state(
+    is_running: bool,
+    tasks_running: bool,
+    resume_at: Option<DateTime<Utc>>,
+) -> QueueState {
+    match (is_running, tasks_running, resume_at) {
+        (false, _, _) => QueueState::Stopped,
+        (true, false, Some(_)) => QueueState::WaitingForReset,
+        (true, _, _) => QueueState::Running,
+    }
+}
+
+/// How long a task may run before it is stopped; None for no limit
+fn task_time_limit(task: &Task, queue: &crate::config::QueueConfig) -> Option<std::time::Duration> {
+    let minutes = task
+        .timeout_minutes
+        .map_or(queue.timeout_minutes, |m| m.max(0) as u64);
+    (minutes > 0).then(|| std::time::Duration::from_secs(minutes * 60))
+}
+
+/// The command verifying a task's runs: its own, else its project's
+fn verify_command(task: &Task, queue: &crate::config::QueueConfig) -> Option<String> {
+    task.verify_command
+        .clone()
+        .or_else(|| queue.verify_commands.get(task.project_path.as_deref()?).cloned())
+        .map(|command| command.trim().to_string())
+        .filter(|command| !command.is_empty())
+}
+
+/// How many fix tasks lead back from `task` to the task that first failed verification
+fn fix_depth(task: &Task) -> u32 {
+    let mut depth = 0;
+    let mut fix_of = task.fix_of.clone();
+    while let Some(id) = fix_of {
+        depth += 1;
+        fix_of = database::get_task(&id).ok().and_then(|t| t.fix_of);
+    }
+    depth
+}
+
+/// Whether a task that failed verification may get a fix task: not when it
+/// ran in a worktree, and only so many follow-ups per original task
+fn fix_task_allowed(task: &Task, isolated: bool, max_fix_tasks: u32) -> bool {
+    !isolated && fix_depth(task) < max_fix_tasks
+}
+
+/// The last `max_chars` characters of a command's output
+fn output_tail(output: &str, max_chars: usize) -> &str {
+    let trimmed = output.trim_end();
+    let start = trimmed
+        .char_indices()
+        .rev()
+        .nth(max_chars.saturating_sub(1))
+        .map_or(0, |(i, _)| i);
+    &trimmed[start..]
+}
+
+/// The status and reason recorded for a run stopped at its time limit
+fn timeout_error(time_limit: Option<std::time::Duration>) -> (TaskStatus, String) {
+    let minutes = time_limit.unwrap_or_default().as_secs() / 60;
+    (TaskStatus::Failed, format!("Timed out after {} minutes", minutes))
+}
+
 /// Extract the project display name from a task, falling back to "Unknown".
 fn task_project_name(task: &Task) -> String {
     task.project_path
@@ -75,14 +272,6 @@ fn apply_env_config(cmd: &mut Command, env: &crate::config::ClaudeEnvironment) {
     }
 }
 
-/// Build a shell-compatible env prefix string for terminal execution.
-fn build_env_prefix(env: &crate::config::ClaudeEnvironment) -> String {
-    env_entries(env)
-        .into_iter()
-        .map(|(name, value)| format!("{}='{}' ", name, value))
-        .collect()
-}
-
 // ============================================================================
 // QueueExecutor
 // ============================================================================
@@ -92,20 +281,39 @@ impl QueueExecutor {
         Self {
             app,
             running: Arc::new(AtomicBool::new(false)),
-            current_task: Arc::new(Mutex::new(None)),
+            generation: Arc::new(std::sync::Mutex::new(0)),
+            running_tasks: Arc::new(Mutex::new(HashMap::new())),
+            wake: Arc::new(Notify::new()),
+            usage_checks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     /// Start the queue executor
     pub async fn start(&self) -> Result<(), String> {
-        if self.running.swap(true, Ordering::SeqCst) {
-            return Err("Queue already running".to_string());
+        let generation = {
+            let mut generation = self.generation.lock().unwrap_or_else(|e| e.into_inner());
+            if self.running.swap(true, Ordering::SeqCst) {
+                return Err("Queue already running".to_string());
+            }
+            *generation += 1;
+            *generation
+        };
+        if let Err(e) = std::fs::write(queue_marker(), "") {
+            tracing::warn!("Failed to mark the queue as running: {}", e);
         }
 
         self.emit_status().await;
-        self.run_queue().await;
+        self.run_queue(generation).await;
 
-        self.running.store(false, Ordering::SeqCst);
+        {
+            let current = self.generation.lock().unwrap_or_else(|e| e.into_inner());
+            // Restarted while this loop drained its workers: the queue runs on
+            if *current != generation {
+                return Ok(());
+            }
+            self.running.store(false, Ordering::SeqCst);
+        }
+        let _ = std::fs::remove_file(queue_marker());
         self.emit_status().await;
 
         // Start auto-action timer if all tasks have been processed
@@ -122,9 +330,37 @@ impl QueueExecutor {
         Ok(())
     }
 
-    /// Stop the queue executor
-    pub fn stop(&self) {
+    /// Stop the queue executor: no new tasks start, and running ones are
+    /// cancelled if `cancel_running` is set or else left to finish.
+    pub async fn stop(&self, cancel_running: bool) {
         self.running.store(false, Ordering::SeqCst);
+        self.wake.notify_one();
+        if cancel_running {
+            for running in self.running_tasks.lock().await.values() {
+                running.cancel.notify_one();
+            }
+        }
+    }
+
+    /// Cancel a task. A running task's process tree is killed and its output so
+    /// far kept; a task that is not running is simply marked cancelled.
+    pub async fn cancel_task(&self, task_id: &str) -> Result<(), String> {
+        if let Some(running) = self.running_tasks.lock().await.get(task_id) {
+            running.cancel.notify_one();
+            return Ok(());
+        }
+
+        let task = database::get_task(task_id).map_err(|e| e.to_string())?;
+        if matches!(
+            task.status,
+            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Skipped | TaskStatus::Cancelled
+        ) {
+            return Err(format!("Task {} already finished", task_id));
+        }
+        database::update_task(&self.app, task_id, Some(TaskStatus::Cancelled), None, None, None)
+            .map_err(|e| e.to_string())?;
+        self.emit_status().await;
+        Ok(())
     }
 
     /// Check if queue is running
@@ -132,47 +368,46 @@ impl QueueExecutor {
         self.running.load(Ordering::SeqCst)
     }
 
-    /// Get current task ID
-    #[allow(dead_code)]
-    pub async fn current_task_id(&self) -> Option<String> {
-        self.current_task.lock().await.clone()
+    /// Whether the loop of the given start may launch tasks: the queue is
+    /// running, and has not been stopped and started again since
+    fn is_current(&self, generation: u64) -> bool {
+        let current = self.generation.lock().unwrap_or_else(|e| e.into_inner());
+        self.running.load(Ordering::SeqCst) && *current == generation
+    }
+
+    /// Get the IDs of running tasks
+    pub async fn running_task_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.running_tasks.lock().await.keys().cloned().collect();
+        ids.sort();
+        ids
     }
 
-    /// Find the next task whose dependencies are satisfied.
-    /// Tasks with failed/skipped dependencies are automatically marked as skipped.
-    fn find_next_executable_task(&self, tasks: &[Task]) -> Option<Task> {
+    /// Find the next task whose dependencies are satisfied and that `can_start` accepts.
+    /// Tasks whose dependencies can no longer be satisfied, directly or further
+    /// up the graph, are automatically marked as skipped.
+    fn find_next_executable_task(
+        &self,
+        tasks: &[Task],
+        can_start: impl Fn(&Task) -> bool,
+    ) -> Option<Task> {
         let all_tasks = database::get_tasks(&self.app, None, None).ok()?;
+        let graph = TaskIndex::new(&all_tasks);
 
         for task in tasks {
-            let depends_on_id = match task.depends_on {
-                Some(ref id) => id,
-                None => return Some(task.clone()),
-            };
-
-            let dependency = all_tasks.iter().find(|t| &t.id == depends_on_id);
-
-            match dependency {
-                None => {
-                    // Dependency deleted -- treat as satisfied
-                    tracing::warn!(
-                        "Dependency {} for task {} not found, proceeding anyway",
-                        depends_on_id, task.id
-                    );
-                    return Some(task.clone());
-                }
-                Some(dep) if dep.status == TaskStatus::Completed => {
-                    return Some(task.clone());
-                }
-                Some(dep) if dep.status == TaskStatus::Failed || dep.status == TaskStatus::Skipped => {
-                    tracing::warn!(
-                        "Skipping task {} because dependency {} has status {:?}",
-                        task.id, depends_on_id, dep.status
-                    );
+            match graph.readiness(task) {
+                Readiness::Ready if can_start(task) => return Some(task.clone()),
+                Readiness::Unsatisfiable => {
+                    let reasons: Vec<String> = graph
+                        .blocked_by(task)
+                        .into_iter()
+                        .map(|b| format!("{} {}", b.task_id, b.reason))
+                        .collect();
+                    tracing::warn!("Skipping task {}: {}", task.id, reasons.join(", "));
                     let _ = database::update_task(
                         &self.app, &task.id, Some(TaskStatus::Skipped), None, None, None,
                     );
                 }
-                // Dependency still pending/running -- skip to next candidate
+                // Dependencies still pending/running, or no free slot -- skip to next candidate
                 _ => {}
             }
         }
@@ -185,42 +420,176 @@ impl QueueExecutor {
         let queued_count = database::get_tasks(&self.app, Some(TaskStatus::Queued), None)
             .map(|t| t.len())
             .unwrap_or(0);
+        let is_running = self.running.load(Ordering::SeqCst);
+        let running_task_ids = self.running_task_ids().await;
+        let resume_at = if is_running { self.usage_paused_until().await } else { None };
+        let state = queue_state(is_running, !running_task_ids.is_empty(), resume_at);
 
         let event = QueueStatusEvent {
-            is_running: self.running.load(Ordering::SeqCst),
-            current_task_id: self.current_task.lock().await.clone(),
+            is_running,
+            state,
+            running_task_ids,
             queued_count,
+            resume_at: resume_at.map(|at| at.to_rfc3339()),
         };
 
         let _ = self.app.emit("queue-status", &event);
     }
 
-    /// Run the queue loop
-    async fn run_queue(&self) {
-        while self.running.load(Ordering::SeqCst) {
-            let tasks = match database::get_tasks(&self.app, Some(TaskStatus::Queued), None) {
-                Ok(t) => t,
-                Err(e) => {
+    /// Check whether a provider's usage holds its tasks back, and until when.
+    /// Checks are cached for a while, and redone once a hold runs out.
+    async fn usage_pause(&self, provider: ProviderId, threshold: f64) -> Option<DateTime<Utc>> {
+        let now = Utc::now();
+        let cached = self.usage_checks.lock().await.get(&provider).copied();
+        if let Some(check) = cached {
+            let fresh = now - check.checked_at < USAGE_CHECK_INTERVAL;
+            match check.paused_until {
+                Some(until) if until > now => return Some(until),
+                None if fresh => return None,
+                _ => {}
+            }
+        }
+        if threshold <= 0.0 {
+            return None;
+        }
+
+        let usage = crate::providers::fetch_usage(provider)
+            .await
+            .and_then(|usage| match usage.error {
+                Some(e) => Err(e),
+                None => Ok(usage),
+            });
+        let paused_until = match usage {
+            Ok(usage) => pause_until(&usage, threshold, now),
+            Err(e) => {
+                tracing::debug!("No {} usage to check: {}", provider, e);
+                None
+            }
+        };
+
+        let was_paused = cached.and_then(|c| c.paused_until).is_some();
+        match paused_until {
+            Some(until) if !was_paused => tracing::info!(
+                "{} usage reached {}%, holding its tasks until {}",
+                provider, threshold, until
+            ),
+            None if was_paused => {
+                tracing::info!("{} usage has reset, resuming its tasks", provider)
+            }
+            _ => {}
+        }
+        self.usage_checks
+            .lock()
+            .await
+            .insert(provider, UsageCheck { checked_at: now, paused_until });
+        paused_until
+    }
+
+    /// Hold a provider's tasks until `until`, after one of them hit its rate limit
+    async fn hold_provider(&self, provider: ProviderId, until: DateTime<Utc>) {
+        let mut checks = self.usage_checks.lock().await;
+        if checks.get(&provider).and_then(|c| c.paused_until) >= Some(until) {
+            return;
+        }
+        tracing::info!("{} rate limit reached, holding its tasks until {}", provider, until);
+        checks.insert(provider, UsageCheck { checked_at: Utc::now(), paused_until: Some(until) });
+    }
+
+    /// Earliest time usage holds on queued tasks run out, while any are held
+    async fn usage_paused_until(&self) -> Option<DateTime<Utc>> {
+        let queued = database::get_tasks(&self.app, Some(TaskStatus::Queued), None).ok()?;
+        let checks = self.usage_checks.lock().await;
+        let now = Utc::now();
+        queued
+            .iter()
+            .filter_map(|t| checks.get(&t.provider)?.paused_until)
+            .filter(|until| *until > now)
+            .min()
+    }
+
+    /// When the queue next has a task to start: the next run window opening
+    /// while tasks wait outside one, or else the earliest retry or usage
+    /// reset coming due
+    async fn next_wake_at(&self) -> Option<DateTime<Utc>> {
+        let queued = database::get_tasks(&self.app, Some(TaskStatus::Queued), None).ok()?;
+        if queued.is_empty() {
+            return None;
+        }
+
+        let windows = crate::config::load_config().queue.run_windows;
+        if !crate::scheduler::in_run_window(&windows, Local::now()) {
+            return crate::scheduler::next_window_start(&windows, Local::now())
+                .map(|t| t.with_timezone(&Utc));
+        }
+
+        let now = Utc::now();
+        let next_retry = queued
+            .iter()
+            .filter_map(|t| t.next_attempt_at.as_deref().and_then(parse_timestamp))
+            .filter(|at| *at > now)
+            .min();
+        next_retry.into_iter().chain(self.usage_paused_until().await).min()
+    }
+
+    /// Run the queue loop: keep the worker pool filled until nothing more can
+    /// start, then wait for the running tasks and pending retries.
+    async fn run_queue(&self, generation: u64) {
+        let mut workers: JoinSet<WorkerOutcome> = JoinSet::new();
+        // Set when the queue cannot be read; running tasks finish but no new ones start
+        let mut halted = false;
+        let mut paused_until = None;
+
+        loop {
+            let current = self.is_current(generation) && !halted;
+            if current {
+                if let Err(e) = self.launch_ready_tasks(&mut workers).await {
                     tracing::error!("Failed to get queued tasks: {}", e);
-                    break;
+                    halted = true;
                 }
+                // Tell the frontend when usage limits start or stop holding tasks back
+                let held = self.usage_paused_until().await;
+                if held != paused_until {
+                    paused_until = held;
+                    self.emit_status().await;
+                }
+            }
+
+            // Tasks waiting for a run window, a backoff or a rate limit keep the queue alive
+            let wake_in = if current {
+                self.next_wake_at()
+                    .await
+                    .map(|at| (at - Utc::now()).to_std().unwrap_or_default())
+            } else {
+                None
             };
+            if workers.is_empty() && wake_in.is_none() {
+                break;
+            }
 
-            let task = match self.find_next_executable_task(&tasks) {
-                Some(t) => t,
-                None => {
-                    if tasks.is_empty() {
-                        tracing::info!("Queue empty, stopping executor");
-                    } else {
-                        tracing::info!("All queued tasks have unmet dependencies, stopping executor");
-                    }
-                    break;
+            let wake_wait = tokio::time::sleep(wake_in.unwrap_or_default());
+            let joined = tokio::select! {
+                Some(joined) = workers.join_next() => joined,
+                _ = wake_wait, if wake_in.is_some() => continue,
+                // Only the current loop answers wakes, so a draining one cannot take them
+                _ = self.wake.notified(), if current => continue,
+            };
+            let (task, outcome) = match joined {
+                Ok(finished) => finished,
+                Err(e) => {
+                    tracing::error!("Queue worker panicked: {}", e);
+                    continue;
                 }
             };
 
             let project_name = task_project_name(&task);
 
-            match self.execute_task(&task).await {
+            match outcome {
+                Ok(TaskResult { retry_at: Some(at), .. }) => {
+                    tracing::info!("Task {} will run again at {}", task.id, at);
+                }
+                Ok(TaskResult { error: Some(reason), .. }) => {
+                    tracing::warn!("Task {} stopped: {}", task.id, reason);
+                }
                 Ok(result) => {
                     tracing::info!("Task {} completed with exit code {}", task.id, result.exit_code);
                     let _ = notification::notify_task_completed(
@@ -234,32 +603,322 @@ impl QueueExecutor {
                 Err(e) => {
                     tracing::error!("Task {} failed: {}", task.id, e);
                     let _ = notification::notify_task_error(&self.app, &project_name, &e);
-                    break;
                 }
             }
 
             self.emit_status().await;
         }
+
+        let queued = database::get_tasks(&self.app, Some(TaskStatus::Queued), None)
+            .map(|t| t.len())
+            .unwrap_or(0);
+        if queued == 0 {
+            tracing::info!("Queue empty, stopping executor");
+        } else if self.is_current(generation) && !halted {
+            tracing::info!("All queued tasks have unmet dependencies, stopping executor");
+        }
     }
 
-    /// Mark a task as finished (completed/failed) and clear the current-task slot.
+    /// Start queued tasks on new workers until the global or per-project
+    /// concurrency cap is reached, or no queued task is ready. Nothing starts
+    /// outside the configured run windows.
+    async fn launch_ready_tasks(
+        &self,
+        workers: &mut JoinSet<WorkerOutcome>,
+    ) -> Result<(), database::DatabaseError> {
+        let limits = crate::config::load_config().queue;
+        if !crate::scheduler::in_run_window(&limits.run_windows, Local::now()) {
+            return Ok(());
+        }
+        let max_total = limits.max_concurrent_tasks.max(1);
+        let max_per_project = limits.max_tasks_per_project.max(1);
+
+        loop {
+            // Usage is checked first, as it may go over the network
+            let tasks = database::get_tasks(&self.app, Some(TaskStatus::Queued), None)?;
+            let mut held = HashSet::new();
+            for provider in tasks.iter().map(|t| t.provider).collect::<HashSet<_>>() {
+                if self.usage_pause(provider, limits.pause_at_usage_percent).await.is_some() {
+                    held.insert(provider);
+                }
+            }
+
+            // The capacity check and the claim share one lock, so no task is
+            // claimed twice. Tasks are read again under it: a worker leaves
+            // the map only after its task's final status is recorded.
+            let mut running = self.running_tasks.lock().await;
+            if running.len() >= max_total {
+                return Ok(());
+            }
+            let tasks = database::get_tasks(&self.app, Some(TaskStatus::Queued), None)?;
+            let now = Utc::now();
+            let can_start = |task: &Task| {
+                let in_project = running
+                    .values()
+                    .filter(|r| !r.isolated && r.project_path == task.project_path)
+                    .count();
+                !running.contains_key(&task.id)
+                    && (wants_worktree(task, &limits) || in_project < max_per_project)
+                    && !is_delayed(task, now)
+                    && !held.contains(&task.provider)
+            };
+            let Some(task) = self.find_next_executable_task(&tasks, can_start) else {
+                return Ok(());
+            };
+
+            // Claim the slot before the worker starts so the next pass sees it
+            let slot = RunningTask {
+                project_path: task.project_path.clone(),
+                isolated: wants_worktree(&task, &limits),
+                cancel: Arc::new(Notify::new()),
+            };
+            running.insert(task.id.clone(), slot);
+            drop(running);
+
+            let executor = self.clone();
+            workers.spawn(async move {
+                let outcome = executor.execute_task(&task).await;
+                executor.running_tasks.lock().await.remove(&task.id);
+                (task, outcome)
+            });
+        }
+    }
+
+    /// Mark a task as finished (completed/failed).
     async fn finalize_task(&self, task_id: &str, status: TaskStatus) -> Result<(), String> {
         database::update_task(&self.app, task_id, Some(status), None, None, None)
             .map_err(|e| e.to_string())?;
-        *self.current_task.lock().await = None;
         Ok(())
     }
 
-    /// Execute a single task
+    /// The notifier that cancels a running task
+    async fn cancel_handle(&self, task_id: &str) -> Arc<Notify> {
+        match self.running_tasks.lock().await.get(task_id) {
+            Some(running) => running.cancel.clone(),
+            None => Arc::new(Notify::new()),
+        }
+    }
+
+    /// Execute a single task, recording the attempt and settling the task's status
     async fn execute_task(&self, task: &Task) -> Result<TaskResult, String> {
-        *self.current_task.lock().await = Some(task.id.clone());
         database::update_task(&self.app, &task.id, Some(TaskStatus::Running), None, None, None)
             .map_err(|e| e.to_string())?;
+        // A terminal run that outlived the last session of Alice is followed, not started over
+        if let Some((run, terminal)) = reattachable_run(&task.id) {
+            self.emit_status().await;
+            let outcome = self.reattach_attempt(task, &run, &terminal).await;
+            return self.settle_attempt(task, &run, outcome).await;
+        }
+        // An open run that cannot be followed any more was interrupted
+        if let Some(stale) = open_run(&task.id) {
+            let closed = database::finish_task_run(
+                stale.id, TaskRunStatus::Failed, None, Some(INTERRUPTED_REASON), None, None, None,
+            );
+            if let Err(e) = closed {
+                tracing::warn!("Failed to record run of task {}: {}", task.id, e);
+            }
+        }
+        let run = database::start_task_run(&task.id).map_err(|e| e.to_string())?;
         self.emit_status().await;
 
         let project_name = task_project_name(task);
         let _ = notification::notify_queue_started(&self.app, &project_name, &task.prompt);
 
+        let outcome = self.run_attempt(task, &run).await;
+        self.settle_attempt(task, &run, outcome).await
+    }
+
+    /// Record how an attempt ended, then either finish the task or queue it
+    /// again when the failure is worth another try.
+    async fn settle_attempt(
+        &self,
+        task: &Task,
+        run: &TaskRun,
+        outcome: Result<TaskResult, String>,
+    ) -> Result<TaskResult, String> {
+        let mut result = match outcome {
+            Ok(result) => result,
+            Err(e) => {
+                let recorded = database::finish_task_run(
+                    run.id, TaskRunStatus::Failed, None, Some(&e), None, None, None,
+                );
+                if let Err(db_err) = recorded {
+                    tracing::warn!("Failed to record run of task {}: {}", task.id, db_err);
+                }
+                self.finish_worktree(&task.id, &TaskStatus::Failed).await;
+                self.finalize_task(&task.id, TaskStatus::Failed).await?;
+                return Err(e);
+            }
+        };
+
+        // Runs Alice stopped on purpose (budget, cancel) are never retried
+        let retry = match result.status {
+            TaskStatus::Failed if result.error.is_none() => self.plan_retry(task, &result).await,
+            _ => None,
+        };
+
+        let run_status = match (&retry, &result.status) {
+            (Some(retry), _) if retry.rate_limited => TaskRunStatus::RateLimited,
+            (_, TaskStatus::Completed) => TaskRunStatus::Completed,
+            (_, TaskStatus::Cancelled) => TaskRunStatus::Cancelled,
+            _ => TaskRunStatus::Failed,
+        };
+        let run_error = retry
+            .as_ref()
+            .map(|r| r.reason.clone())
+            .or_else(|| result.error.clone());
+        if let Err(e) = database::finish_task_run(
+            run.id,
+            run_status,
+            Some(result.exit_code),
+            run_error.as_deref(),
+            Some(result.tokens_used),
+            Some(result.cost_usd),
+            result.session_id.as_deref(),
+        ) {
+            tracing::warn!("Failed to record run of task {}: {}", task.id, e);
+        }
+
+        match retry {
+            Some(retry) => {
+                let at = retry.at.to_rfc3339();
+                tracing::warn!("Task {} attempt {} failed: {}", task.id, run.attempt, retry.reason);
+                // The rest of the provider's tasks would only run into the same limit
+                if retry.rate_limited {
+                    self.hold_provider(task.provider, retry.at).await;
+                }
+                database::schedule_task_retry(&task.id, &at).map_err(|e| e.to_string())?;
+                result.retry_at = Some(at);
+            }
+            None => {
+                self.finish_worktree(&task.id, &result.status).await;
+                self.finalize_task(&task.id, result.status.clone()).await?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Set up the worktree a task runs in and record it on the task.
+    /// None when the project is not a git repository: the task runs in place.
+    async fn prepare_worktree(&self, task: &Task) -> Result<Option<Worktree>, String> {
+        let Some(project) = task.project_path.as_deref() else {
+            return Ok(None);
+        };
+        let worktree = crate::worktree::prepare(project, &task.id).await?;
+        match &worktree {
+            Some(w) => {
+                database::set_task_worktree(&task.id, Some(&w.path), Some(&w.branch))
+                    .map_err(|e| e.to_string())?;
+            }
+            None => tracing::warn!(
+                "{} is not a git repository, running task {} in place",
+                project,
+                task.id
+            ),
+        }
+        Ok(worktree)
+    }
+
+    /// Settle a finished task's worktree. A completed run's changes are
+    /// committed to its branch for review; worktrees with nothing to review,
+    /// and those of failed runs unless configured to keep them, are removed.
+    /// A branch is deleted with its worktree only when it holds no new commits.
+    async fn finish_worktree(&self, task_id: &str, status: &TaskStatus) {
+        let Ok(task) = database::get_task(task_id) else { return };
+        let (Some(project), Some(path), Some(branch)) = (
+            task.project_path.as_deref(),
+            task.worktree_path.as_deref(),
+            task.worktree_branch.as_deref(),
+        ) else {
+            return;
+        };
+
+        let settled: Result<(), String> = async {
+            let completed = *status == TaskStatus::Completed;
+            if completed {
+                let title: String =
+                    task.prompt.lines().next().unwrap_or_default().chars().take(72).collect();
+                crate::worktree::commit_changes(path, &format!("Alice task: {}", title)).await?;
+            } else if crate::config::load_config().queue.keep_failed_worktrees {
+                return Ok(());
+            }
+            let has_commits = crate::worktree::has_new_commits(project, branch).await?;
+            if completed && has_commits {
+                return Ok(());
+            }
+            // Commits a failed run made stay on its branch, only the worktree goes
+            let kept_branch = has_commits.then_some(branch);
+            let deleted_branch = (!has_commits).then_some(branch);
+            crate::worktree::remove(project, Some(path), deleted_branch).await?;
+            database::set_task_worktree(task_id, None, kept_branch).map_err(|e| e.to_string())?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = settled {
+            tracing::warn!("Failed to clean up worktree of task {}: {}", task_id, e);
+        }
+    }
+
+    /// Decide whether a failed attempt is tried again, and when. Rate-limited
+    /// runs wait for the provider's reset and do not count against the policy,
+    /// though only so many are waited out. Only runs since the task last
+    /// entered the queue count, so queueing it again by hand starts afresh.
+    async fn plan_retry(&self, task: &Task, result: &TaskResult) -> Option<Retry> {
+        let policy = crate::config::load_config().queue;
+        let since_queued = database::get_task(&task.id)
+            .map_or(1, |t| t.attempt_count.max(1) as usize);
+        let runs = database::get_task_runs(&self.app, &task.id, Some(since_queued as u32), None)
+            .unwrap_or_default();
+        let rate_limited = runs
+            .iter()
+            .filter(|r| r.status == TaskRunStatus::RateLimited)
+            .count();
+        let attempts = (runs.len() - rate_limited).max(1) as i32;
+        let max_attempts = task.max_attempts.unwrap_or(policy.max_attempts as i32).max(1);
+        let base_delay = task
+            .retry_delay_secs
+            .map_or(policy.retry_delay_secs, |d| d.max(0) as u64);
+        let now = Utc::now();
+        let backoff = now + retry_backoff(base_delay, policy.max_retry_delay_secs, attempts);
+
+        match detect_transient_failure(&result.output, &result.stderr) {
+            Some(TransientFailure::RateLimited(_)) if rate_limited >= RATE_LIMITED_MAX_RETRIES => {
+                tracing::warn!(
+                    "Task {} was rate limited {} times, giving up",
+                    task.id, rate_limited
+                );
+                None
+            }
+            Some(TransientFailure::RateLimited(reported)) => {
+                let reset_at = provider_reset_at(task.provider)
+                    .await
+                    .or(reported)
+                    .filter(|at| *at > now);
+                Some(Retry {
+                    at: reset_at.unwrap_or(backoff),
+                    reason: format!("{} rate limit reached", task.provider),
+                    rate_limited: true,
+                })
+            }
+            Some(TransientFailure::Overloaded) => {
+                (attempts < max_attempts.max(OVERLOADED_MAX_ATTEMPTS)).then(|| Retry {
+                    at: backoff,
+                    reason: format!("{} is overloaded", task.provider),
+                    rate_limited: false,
+                })
+            }
+            None => (attempts < max_attempts).then(|| Retry {
+                at: backoff,
+                reason: format!("Exited with code {}", result.exit_code),
+                rate_limited: false,
+            }),
+        }
+    }
+
+    /// Launch one run of a task with its provider's CLI
+    async fn run_attempt(&self, task: &Task, run: &TaskRun) -> Result<TaskResult, String> {
         let start_time = std::time::Instant::now();
 
         // Validate provider CLI is installed
@@ -270,7 +929,6 @@ impl QueueExecutor {
                 task.provider,
                 provider.get_cli_command()
             );
-            self.finalize_task(&task.id, TaskStatus::Failed).await?;
             return Err(error_msg);
         }
 
@@ -281,63 +939,470 @@ impl QueueExecutor {
         let cli_command = provider.get_cli_command();
         let cmd_name = env_config.command.as_deref().unwrap_or(&cli_command);
         let max_turns = task.max_turns.unwrap_or(50);
-        let args = build_provider_args(task, max_turns);
-        let working_dir = task.project_path.as_deref();
+        let in_background = config.terminal_app == crate::config::TerminalApp::Background;
+        let prepared = resolve_task_session(task).and_then(|session| {
+            let args = build_provider_args(task, max_turns, &session, in_background)?;
+            Ok((session, args))
+        });
+        let (session, args) = prepared?;
+        let worktree = if wants_worktree(task, &config.queue) {
+            self.prepare_worktree(task).await?
+        } else {
+            None
+        };
+        let working_dir = worktree
+            .as_ref()
+            .map(|w| w.working_dir.as_str())
+            .or(task.project_path.as_deref());
+
+        let mut log = match run.log_path.as_deref() {
+            Some(path) => TaskLog::create(path.as_ref()),
+            None => TaskLog::disabled(),
+        };
+        let result = if in_background {
+            self.execute_in_background(
+                task, run.id, cmd_name, &args, &env_config, &cli_command, working_dir, &session,
+                start_time, &mut log,
+            )
+            .await?
+        } else {
+            let log_path = run.log_path.as_deref().ok_or("Task run has no log file")?;
+            self.execute_in_terminal(
+                task, run, cmd_name, &args, &env_config, &config, working_dir, &session, start_time,
+                log_path.as_ref(),
+            )
+            .await?
+        };
+
+        Ok(self.verify_run(task, result, working_dir, worktree.is_some(), &mut log).await)
+    }
+
+    /// Follow a terminal run started before Alice last exited through to its end
+    async fn reattach_attempt(
+        &self,
+        task: &Task,
+        run: &TaskRun,
+        terminal: &TerminalRun,
+    ) -> Result<TaskResult, String> {
+        tracing::info!("Following task {} in its terminal again", task.id);
+        let config = crate::config::load_config();
+        let elapsed = parse_timestamp(&run.started_at)
+            .and_then(|at| (Utc::now() - at).to_std().ok())
+            .unwrap_or_default();
+        let now = std::time::Instant::now();
+        let start_time = now.checked_sub(elapsed).unwrap_or(now);
+        // Its worktree, if it had one, is still in place
+        let worktree = match task.worktree_path {
+            Some(_) => self.prepare_worktree(task).await?,
+            None => None,
+        };
+        let working_dir = worktree
+            .as_ref()
+            .map(|w| w.working_dir.as_str())
+            .or(task.project_path.as_deref());
+
+        // The run's own output names its session
+        let session = TaskSession::default();
+        let result = self
+            .follow_terminal(task, run, terminal, &session, start_time, &config.queue)
+            .await?;
+        let mut log = TaskLog::create(&terminal.log);
+        Ok(self.verify_run(task, result, working_dir, worktree.is_some(), &mut log).await)
+    }
 
-        if config.terminal_app != crate::config::TerminalApp::Background {
-            return self
-                .execute_in_terminal(task, cmd_name, &args, &env_config, &config, working_dir, start_time)
-                .await;
+    /// Check a completed run with the task's verification command, whose exit
+    /// code decides whether the run really completed. A failed check can queue
+    /// a follow-up task that resumes the session to fix it.
+    async fn verify_run(
+        &self,
+        task: &Task,
+        mut result: TaskResult,
+        working_dir: Option<&str>,
+        isolated: bool,
+        log: &mut TaskLog,
+    ) -> TaskResult {
+        let queue = crate::config::load_config().queue;
+        if result.status != TaskStatus::Completed {
+            return result;
         }
+        let Some(command) = verify_command(task, &queue) else {
+            return result;
+        };
+
+        tracing::info!("Verifying task {} with `{}`", task.id, command);
+        let mut output = String::new();
+        let verified = self
+            .run_verify_command(task, &command, working_dir, &queue, log, &mut output)
+            .await;
+        let failure = match verified {
+            Ok(0) => None,
+            Ok(code) => {
+                let fix = self.queue_fix_task(task, &result, &command, code, &output, isolated);
+                if let Err(e) = fix {
+                    tracing::warn!("Failed to queue a fix for task {}: {}", task.id, e);
+                }
+                let reason = format!("Verification `{}` exited with code {}", command, code);
+                Some((TaskStatus::Failed, reason))
+            }
+            Err(stopped) => Some(stopped),
+        };
 
-        self.execute_in_background(task, cmd_name, &args, &env_config, &cli_command, working_dir, start_time)
+        let Some((status, reason)) = failure else {
+            return result;
+        };
+        tracing::warn!("Task {} failed verification: {}", task.id, reason);
+        result.output.push_str(&format!("$ {}\n{}", command, output));
+        if let Err(e) = database::set_task_result(
+            &task.id,
+            result.exit_code,
+            &result.output,
+            result.tokens_used,
+            result.cost_usd,
+            Some(&reason),
+            result.session_id.as_deref(),
+        ) {
+            tracing::warn!("Failed to record result of task {}: {}", task.id, e);
+        }
+        result.status = status;
+        result.error = Some(reason);
+        result
+    }
+
+    /// Run a verification command, streaming its output like the run's.
+    /// Returns its exit code, or the status and reason when Alice stopped it.
+    async fn run_verify_command(
+        &self,
+        task: &Task,
+        command: &str,
+        working_dir: Option<&str>,
+        queue: &crate::config::QueueConfig,
+        log: &mut TaskLog,
+        output: &mut String,
+    ) -> Result<i32, (TaskStatus, String)> {
+        let mut cmd = if cfg!(target_os = "windows") {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", &format!("{} 2>&1", command)]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", &format!("{} 2>&1", command)]);
+            cmd
+        };
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::null());
+        crate::platform::isolate_process_group(&mut cmd);
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        }
+
+        let failed = |reason: String| (TaskStatus::Failed, reason);
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| failed(format!("Failed to run verification `{}`: {}", command, e)))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| failed("Failed to capture verification output".to_string()))?;
+        let mut lines = BufReader::new(stdout).lines();
+
+        let cancel = self.cancel_handle(&task.id).await;
+        // Verification gets the same time limit as the run it checks
+        let time_limit = task_time_limit(task, queue);
+        let deadline = time_limit.map(|limit| tokio::time::Instant::now() + limit);
+        let header = format!("$ {}", command);
+        log.write_line(LogStream::Verify, &header);
+
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) => line,
+                    _ => break,
+                },
+                _ = cancel.notified() => {
+                    terminate_child(&mut child, &task.id).await;
+                    return Err((TaskStatus::Cancelled, "Cancelled".to_string()));
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)),
+                    if deadline.is_some() =>
+                {
+                    tracing::warn!("Verification of task {} timed out, stopping", task.id);
+                    terminate_child(&mut child, &task.id).await;
+                    return Err(timeout_error(time_limit));
+                }
+            };
+
+            log.write_line(LogStream::Verify, &line);
+            let _ = self.app.emit("task-output", serde_json::json!({
+                "task_id": task.id,
+                "line": line,
+                "stream": LogStream::Verify.as_str(),
+            }));
+            output.push_str(&line);
+            output.push('\n');
+        }
+
+        let status = child
+            .wait()
             .await
+            .map_err(|e| failed(format!("Verification process error: {}", e)))?;
+        Ok(status.code().unwrap_or(-1))
+    }
+
+    /// Queue a task resuming the failed run's session to fix its failing
+    /// checks, within the configured number of follow-ups per original task.
+    /// Worktree runs are left alone: a resumed session cannot run in one.
+    fn queue_fix_task(
+        &self,
+        task: &Task,
+        result: &TaskResult,
+        command: &str,
+        exit_code: i32,
+        output: &str,
+        isolated: bool,
+    ) -> Result<(), String> {
+        let queue = crate::config::load_config().queue;
+        let Some(session_id) = result.session_id.as_deref() else {
+            return Ok(());
+        };
+        if !fix_task_allowed(task, isolated, queue.max_fix_tasks) {
+            return Ok(());
+        }
+
+        let prompt = format!(
+            "The verification command `{}` failed with exit code {} after your changes. \
+             Fix the failing checks.\n\n```\n{}\n```",
+            command,
+            exit_code,
+            output_tail(output, FIX_PROMPT_OUTPUT_CHARS)
+        );
+        let fix = database::create_fix_task(&task.id, &prompt, session_id)
+            .map_err(|e| e.to_string())?;
+        tracing::info!("Queued task {} to fix the checks task {} failed", fix.id, task.id);
+        // Wake the queue loop so the follow-up starts once a slot frees up
+        self.wake.notify_one();
+        Ok(())
     }
 
-    /// Execute a task in a visible terminal window.
+    /// Execute a task in a visible terminal window, following it to the end
+    /// through its wrapper script.
     async fn execute_in_terminal(
         &self,
         task: &Task,
+        run: &TaskRun,
         cmd_name: &str,
         args: &[String],
         env_config: &crate::config::ClaudeEnvironment,
         config: &crate::config::AppConfig,
         working_dir: Option<&str>,
+        session: &TaskSession,
         start_time: std::time::Instant,
+        log_path: &std::path::Path,
     ) -> Result<TaskResult, String> {
-        let args_str: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-        let env_prefix = build_env_prefix(env_config);
-
-        // Wrap in a shell command when env vars are needed and no custom command is set
-        let (final_cmd, final_args) = if !env_prefix.is_empty() && env_config.command.is_none() {
-            let full_cmd = format!("{}{} {}", env_prefix, cmd_name, args_str.join(" "));
-            ("sh".to_string(), vec!["-c".to_string(), full_cmd])
+        let terminal = TerminalRun::new(log_path);
+        // A custom command brings its own environment
+        let env = if env_config.command.is_none() {
+            env_entries(env_config)
         } else {
-            (cmd_name.to_string(), args.to_vec())
+            Vec::new()
         };
+        terminal.write_script(working_dir, &env, cmd_name, args)?;
 
-        let final_args_str: Vec<&str> = final_args.iter().map(|s| s.as_str()).collect();
+        let (command, launch_args) = terminal.launch_command();
+        let launch_args: Vec<&str> = launch_args.iter().map(|s| s.as_str()).collect();
         crate::platform::execute_in_terminal(
             &config.terminal_app,
             &config.custom_terminal_command,
             working_dir,
-            &final_cmd,
-            &final_args_str,
+            &command,
+            &launch_args,
         )?;
 
-        // Wait briefly to let the terminal open
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        self.follow_terminal(task, run, &terminal, session, start_time, &config.queue).await
+    }
+
+    /// Follow a run in a terminal window until its CLI exits, tailing its log
+    /// for output and spend, and stopping it on cancel, timeout or budget.
+    async fn follow_terminal(
+        &self,
+        task: &Task,
+        run: &TaskRun,
+        terminal: &TerminalRun,
+        session: &TaskSession,
+        start_time: std::time::Instant,
+        queue: &crate::config::QueueConfig,
+    ) -> Result<TaskResult, String> {
+        let cancel = self.cancel_handle(&task.id).await;
+        let time_limit = task_time_limit(task, queue);
+        let started = tokio::time::Instant::from_std(start_time);
+        let deadline = time_limit.map(|limit| started + limit);
+        // The script's PID once it has started, and whether the PID is still
+        // the script's. One recorded before Alice restarted is checked against
+        // its start time once; after that, polls only check it is alive.
+        let mut process = run.pid.map(|pid| {
+            (pid, process_running(pid, run.pid_started_at.as_deref()) != Some(false))
+        });
+        let stop_terminal = |process: &Option<(u32, bool)>| match process {
+            Some((pid, true)) => crate::platform::kill_process_tree(*pid),
+            Some((_, false)) => Err("the run's process has already exited".to_string()),
+            None => Err("the run has not recorded its PID".to_string()),
+        };
+
+        let mut output = String::new();
+        let mut costs = CostTracker::default();
+        let mut log_offset = 0;
+        let mut partial_line = String::new();
+        let stopped: Option<(TaskStatus, String)>;
+        // Set when Alice meant to stop the run but could not: it is followed
+        // to its end, keeping its slot, and then recorded with this outcome
+        let mut unstoppable: Option<(TaskStatus, String)> = None;
+
+        let exit_code = loop {
+            let stop = tokio::select! {
+                _ = tokio::time::sleep(TERMINAL_POLL_INTERVAL) => None,
+                _ = cancel.notified() => {
+                    tracing::info!("Cancelling task {}", task.id);
+                    Some((TaskStatus::Cancelled, "Cancelled".to_string()))
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)),
+                    if deadline.is_some() && unstoppable.is_none() =>
+                {
+                    tracing::warn!("Task {} timed out, stopping", task.id);
+                    Some(timeout_error(time_limit))
+                }
+            };
+
+            if process.is_none() {
+                if let Some(pid) = terminal.pid() {
+                    let started_at = crate::platform::process_start_time(pid);
+                    if let Err(e) = database::set_task_run_pid(run.id, pid, started_at.as_deref()) {
+                        tracing::warn!("Failed to record PID of task {}: {}", task.id, e);
+                    }
+                    process = Some((pid, true));
+                }
+            }
+
+            // Read the status before the log: the script publishes it only
+            // after the last line is written
+            let exited = terminal.exit_code();
+            loop {
+                let chunk = crate::task_log::read_chunk(
+                    &terminal.log,
+                    Some(log_offset),
+                    TERMINAL_READ_BYTES,
+                );
+                match chunk {
+                    Ok((chunk, next)) if next > log_offset => {
+                        log_offset = next;
+                        partial_line.push_str(&chunk);
+                    }
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::warn!("Failed to read log of task {}: {}", task.id, e);
+                        break;
+                    }
+                }
+            }
+            // Once the CLI has exited, an unterminated last line is complete too
+            if exited.is_some() && !partial_line.is_empty() && !partial_line.ends_with('\n') {
+                partial_line.push('\n');
+            }
+            while let Some(end) = partial_line.find('\n') {
+                let line: String = partial_line.drain(..=end).collect();
+                // PowerShell may start the log with a byte order mark
+                let line = line.trim_start_matches('\u{feff}').trim_end_matches(['\r', '\n']);
+                let _ = self.app.emit("task-output", serde_json::json!({
+                    "task_id": task.id,
+                    "line": line,
+                    "stream": LogStream::Stdout.as_str(),
+                }));
+                costs.observe(line);
+                output.push_str(line);
+                output.push('\n');
+            }
+
+            if let Some(code) = exited {
+                stopped = unstoppable.take();
+                break code;
+            }
+            let over_budget = task.max_budget_usd.filter(|b| costs.cost_usd() > *b);
+            let stop = match (stop, over_budget) {
+                (Some(stop), _) => Some(stop),
+                (None, Some(budget)) if unstoppable.is_none() => {
+                    let spent = costs.cost_usd();
+                    tracing::warn!(
+                        "Task {} exceeded its ${:.2} budget (${:.2}), stopping",
+                        task.id, budget, spent
+                    );
+                    let project_name = task_project_name(task);
+                    notification::alert_budget_exceeded(&self.app, &project_name, spent, budget);
+                    let reason = format!("Budget exceeded: ${:.2} spent of ${:.2}", spent, budget);
+                    Some((TaskStatus::Failed, reason))
+                }
+                _ => None,
+            };
+            if let Some((status, reason)) = stop {
+                match stop_terminal(&process) {
+                    Ok(()) => {
+                        stopped = Some((status, reason));
+                        break -1;
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            "Could not stop task {}, still following it: {}",
+                            task.id, e
+                        );
+                        let reason = format!("{}, but the run could not be stopped: {}", reason, e);
+                        unstoppable = Some((status, reason));
+                    }
+                }
+            }
+            // A closed window takes the script down before it can report
+            let gone = process.is_some_and(|(pid, owned)| {
+                !owned || crate::platform::is_process_alive(pid) == Some(false)
+            });
+            if gone && terminal.exit_code().is_none() {
+                tracing::warn!("Terminal running task {} closed before it finished", task.id);
+                let reason = "Terminal closed before the task finished".to_string();
+                stopped = Some(unstoppable.take().unwrap_or((TaskStatus::Failed, reason)));
+                break -1;
+            }
+        };
 
         let duration_secs = start_time.elapsed().as_secs();
-        self.finalize_task(&task.id, TaskStatus::Completed).await?;
+        let tokens_used = costs.tokens;
+        let cost_usd = costs.cost_usd();
+        let session_id = parse_output_session_id(&output).or_else(|| session.expected_id.clone());
+
+        if let Err(e) = database::set_task_result(
+            &task.id,
+            exit_code,
+            &output,
+            tokens_used,
+            cost_usd,
+            stopped.as_ref().map(|(_, reason)| reason.as_str()),
+            session_id.as_deref(),
+        ) {
+            tracing::warn!("Failed to record result of task {}: {}", task.id, e);
+        }
+
+        let (task_status, error) = match stopped {
+            Some((status, reason)) => (status, Some(reason)),
+            None if exit_code == 0 => (TaskStatus::Completed, None),
+            None => (TaskStatus::Failed, None),
+        };
 
         Ok(TaskResult {
             task_id: task.id.clone(),
-            exit_code: 0,
-            output: "Task opened in terminal window".to_string(),
-            tokens_used: 0,
-            cost_usd: 0.0,
+            exit_code,
+            output,
+            tokens_used,
+            cost_usd,
             duration_secs,
+            session_id,
+            error,
+            status: task_status,
+            stderr: String::new(),
+            retry_at: None,
         })
     }
 
@@ -345,12 +1410,15 @@ impl QueueExecutor {
     async fn execute_in_background(
         &self,
         task: &Task,
+        run_id: i64,
         cmd_name: &str,
         args: &[String],
         env_config: &crate::config::ClaudeEnvironment,
         cli_command: &str,
         working_dir: Option<&str>,
+        session: &TaskSession,
         start_time: std::time::Instant,
+        log: &mut TaskLog,
     ) -> Result<TaskResult, String> {
         let mut cmd = Command::new(cmd_name);
         cmd.args(args);
@@ -358,39 +1426,133 @@ impl QueueExecutor {
         cmd.stderr(Stdio::piped());
 
         apply_env_config(&mut cmd, env_config);
+        crate::platform::isolate_process_group(&mut cmd);
 
         if let Some(dir) = working_dir {
             cmd.current_dir(dir);
         }
 
+        let cancel = self.cancel_handle(&task.id).await;
+        let time_limit = task_time_limit(task, &crate::config::load_config().queue);
+        let deadline = time_limit.map(|limit| tokio::time::Instant::now() + limit);
         let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn {}: {}", cli_command, e))?;
+        if let Some(pid) = child.id() {
+            let started_at = crate::platform::process_start_time(pid);
+            if let Err(e) = database::set_task_run_pid(run_id, pid, started_at.as_deref()) {
+                tracing::warn!("Failed to record PID of task {}: {}", task.id, e);
+            }
+        }
 
-        // Stream stdout to the frontend
+        // Stream both pipes to the log and the frontend, tracking spend as each
+        // turn is reported on stdout
         let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
-        let mut reader = BufReader::new(stdout).lines();
+        let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+        let mut stdout_lines = BufReader::new(stdout).lines();
+        let mut stderr_lines = BufReader::new(stderr).lines();
+        let (mut stdout_open, mut stderr_open) = (true, true);
         let mut output = String::new();
+        let mut stderr = String::new();
+        let mut costs = CostTracker::default();
+        // Set when Alice terminates the run: the status to record and why
+        let mut stopped: Option<(TaskStatus, String)> = None;
+
+        while stdout_open || stderr_open {
+            let (line, stream) = tokio::select! {
+                line = stdout_lines.next_line(), if stdout_open => match line {
+                    Ok(Some(line)) => (line, LogStream::Stdout),
+                    _ => {
+                        stdout_open = false;
+                        continue;
+                    }
+                },
+                line = stderr_lines.next_line(), if stderr_open => match line {
+                    Ok(Some(line)) => (line, LogStream::Stderr),
+                    _ => {
+                        stderr_open = false;
+                        continue;
+                    }
+                },
+                _ = cancel.notified() => {
+                    tracing::info!("Cancelling task {}", task.id);
+                    terminate_child(&mut child, &task.id).await;
+                    stopped = Some((TaskStatus::Cancelled, "Cancelled".to_string()));
+                    break;
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)),
+                    if deadline.is_some() =>
+                {
+                    tracing::warn!("Task {} timed out, stopping", task.id);
+                    terminate_child(&mut child, &task.id).await;
+                    stopped = Some(timeout_error(time_limit));
+                    break;
+                }
+            };
 
-        while let Ok(Some(line)) = reader.next_line().await {
-            output.push_str(&line);
-            output.push('\n');
-
+            log.write_line(stream, &line);
             let _ = self.app.emit("task-output", serde_json::json!({
                 "task_id": task.id,
                 "line": line,
+                "stream": stream.as_str(),
             }));
+
+            if stream == LogStream::Stderr {
+                stderr.push_str(&line);
+                stderr.push('\n');
+                continue;
+            }
+            costs.observe(&line);
+            output.push_str(&line);
+            output.push('\n');
+
+            if let Some(budget) = task.max_budget_usd.filter(|b| costs.cost_usd() > *b) {
+                let spent = costs.cost_usd();
+                tracing::warn!(
+                    "Task {} exceeded its ${:.2} budget (${:.2}), stopping",
+                    task.id, budget, spent
+                );
+                terminate_child(&mut child, &task.id).await;
+                let project_name = task_project_name(task);
+                notification::alert_budget_exceeded(&self.app, &project_name, spent, budget);
+                let reason = format!("Budget exceeded: ${:.2} spent of ${:.2}", spent, budget);
+                stopped = Some((TaskStatus::Failed, reason));
+                break;
+            }
+        }
+
+        // The CLI may linger after closing its output; the deadline still holds
+        if let (None, Some(deadline)) = (&stopped, deadline) {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if tokio::time::timeout(remaining, child.wait()).await.is_err() {
+                tracing::warn!("Task {} timed out, stopping", task.id);
+                terminate_child(&mut child, &task.id).await;
+                stopped = Some(timeout_error(time_limit));
+            }
         }
 
         let status = child.wait().await.map_err(|e| format!("Process error: {}", e))?;
         let exit_code = status.code().unwrap_or(-1);
         let duration_secs = start_time.elapsed().as_secs();
-        let (tokens_used, cost_usd) = parse_output_stats(&output);
+        let tokens_used = costs.tokens;
+        let cost_usd = costs.cost_usd();
+        let session_id = parse_output_session_id(&output).or_else(|| session.expected_id.clone());
 
-        let task_status = if exit_code == 0 {
-            TaskStatus::Completed
-        } else {
-            TaskStatus::Failed
+        if let Err(e) = database::set_task_result(
+            &task.id,
+            exit_code,
+            &output,
+            tokens_used,
+            cost_usd,
+            stopped.as_ref().map(|(_, reason)| reason.as_str()),
+            session_id.as_deref(),
+        ) {
+            tracing::warn!("Failed to record result of task {}: {}", task.id, e);
+        }
+
+        let (task_status, error) = match stopped {
+            Some((status, reason)) => (status, Some(reason)),
+            None if exit_code == 0 => (TaskStatus::Completed, None),
+            None => (TaskStatus::Failed, None),
         };
-        self.finalize_task(&task.id, task_status).await?;
 
         Ok(TaskResult {
             task_id: task.id.clone(),
@@ -399,14 +1561,100 @@ impl QueueExecutor {
             tokens_used,
             cost_usd,
             duration_secs,
+            session_id,
+            error,
+            status: task_status,
+            stderr,
+            retry_at: None,
         })
     }
 }
 
+// ============================================================================
+// Session continuity
+// ============================================================================
+
+/// The session a task run builds on
+#[derive(Debug, Clone, Default, PartialEq)]
+struct TaskSession {
+    mode: ExecutionMode,
+    /// Session to resume or fork
+    resume_id: Option<String>,
+    /// Session the run is expected to write to, recorded if the output names none
+    expected_id: Option<String>,
+}
+
+/// Work out which session a task resumes from its execution mode.
+/// Resume and fork fall back to the session of the task it depends on, so a
+/// queued follow-up keeps the previous task's context.
+fn resolve_task_session(task: &Task) -> Result<TaskSession, String> {
+    let mode = task.execution_mode;
+
+    match mode {
+        ExecutionMode::New => Ok(TaskSession {
+            mode,
+            resume_id: None,
+            // Claude accepts a preassigned ID, so even terminal runs are traceable
+            expected_id: (task.provider == crate::providers::ProviderId::Claude)
+                .then(|| uuid::Uuid::new_v4().to_string()),
+        }),
+        ExecutionMode::Resume | ExecutionMode::Fork => {
+            let resume_id = task
+                .session_id
+                .clone()
+                .or_else(|| dependency_session_id(task))
+                .ok_or_else(|| format!("Task {} has no session to {}", task.id, mode))?;
+            Ok(TaskSession {
+                mode,
+                expected_id: (mode == ExecutionMode::Resume).then(|| resume_id.clone()),
+                resume_id: Some(resume_id),
+            })
+        }
+        ExecutionMode::Continue => Ok(TaskSession {
+            mode,
+            resume_id: None,
+            expected_id: task.project_path.as_deref().and_then(|project| {
+                database::get_latest_project_session_id(project, task.provider)
+                    .ok()
+                    .flatten()
+            }),
+        }),
+    }
+}
+
+/// Session produced by the first dependency that has run into one, or else
+/// the first dependency's own session
+fn dependency_session_id(task: &Task) -> Option<String> {
+    task.depends_on.iter().find_map(|id| {
+        let dependency = database::get_task(id).ok()?;
+        dependency.output_session_id.or(dependency.session_id)
+    })
+}
+
 // ============================================================================
 // Provider argument builders
 // ============================================================================
 
+/// Kill a task's process tree, forcing the child down if it outlives the grace period.
+async fn terminate_child(child: &mut tokio::process::Child, task_id: &str) {
+    if let Some(pid) = child.id() {
+        if let Err(e) = crate::platform::kill_process_tree(pid) {
+            tracing::warn!("Task {}: {}", task_id, e);
+        }
+    }
+    if tokio::time::timeout(TERMINATE_GRACE, child.wait()).await.is_err() {
+        // Children that ignored SIGTERM share the leader's group
+        if let Some(pid) = child.id() {
+            if let Err(e) = crate::platform::kill_process_group(pid, true) {
+                tracing::warn!("Task {}: {}", task_id, e);
+            }
+        }
+        if let Err(e) = child.kill().await {
+            tracing::error!("Failed to kill task {}: {}", task_id, e);
+        }
+    }
+}
+
 /// Push `--flag value` onto `args` when `value` is `Some`.
 fn push_optional(args: &mut Vec<String>, flag: &str, value: Option<&str>) {
     if let Some(v) = value {
@@ -416,10 +1664,15 @@ fn push_optional(args: &mut Vec<String>, flag: &str, value: Option<&str>) {
 }
 
 /// Build CLI arguments based on provider type.
-fn build_provider_args(task: &Task, max_turns: i32) -> Vec<String> {
-    use crate::providers::ProviderId;
-
+/// Background runs stream JSON events so spend can be tracked while they work.
+fn build_provider_args(
+    task: &Task,
+    max_turns: i32,
+    session: &TaskSession,
+    streaming: bool,
+) -> Result<Vec<String>, String> {
     let system_prompt = task.system_prompt.as_deref();
+    let resume_id = session.resume_id.as_deref();
 
     match task.provider {
         ProviderId::Claude => {
@@ -427,10 +1680,26 @@ fn build_provider_args(task: &Task, max_turns: i32) -> Vec<String> {
                 "-p".to_string(),
                 task.prompt.clone(),
                 "--output-format".to_string(),
-                "json".to_string(),
-                "--max-turns".to_string(),
-                max_turns.to_string(),
             ];
+            if streaming {
+                // `-p` only emits stream-json with --verbose
+                args.extend(["stream-json".to_string(), "--verbose".to_string()]);
+            } else {
+                args.push("json".to_string());
+            }
+            args.push("--max-turns".to_string());
+            args.push(max_turns.to_string());
+            match session.mode {
+                ExecutionMode::New => {
+                    push_optional(&mut args, "--session-id", session.expected_id.as_deref())
+                }
+                ExecutionMode::Resume => push_optional(&mut args, "--resume", resume_id),
+                ExecutionMode::Continue => args.push("--continue".to_string()),
+                ExecutionMode::Fork => {
+                    push_optional(&mut args, "--resume", resume_id);
+                    args.push("--fork-session".to_string());
+                }
+            }
             push_optional(&mut args, "--system-prompt", system_prompt);
 
             if let Some(ref allowed_tools) = task.allowed_tools {
@@ -441,18 +1710,34 @@ fn build_provider_args(task: &Task, max_turns: i32) -> Vec<String> {
                     }
                 }
             }
-            args
+            Ok(args)
         }
         ProviderId::Codex => {
-            let mut args = vec![task.prompt.clone(), "--json".to_string()];
+            // `codex resume` is the interactive picker: piped runs resume through `exec`
+            let mut args = match session.mode {
+                ExecutionMode::New => Vec::new(),
+                ExecutionMode::Resume => {
+                    let resume_id = resume_id.ok_or("Codex task has no session to resume")?;
+                    vec!["exec".to_string(), "resume".to_string(), resume_id.to_string()]
+                }
+                ExecutionMode::Continue => {
+                    vec!["exec".to_string(), "resume".to_string(), "--last".to_string()]
+                }
+                ExecutionMode::Fork => return Err("Codex cannot fork sessions".to_string()),
+            };
+            args.push(task.prompt.clone());
+            args.push("--json".to_string());
             if max_turns > 0 {
                 args.push("--max-turns".to_string());
                 args.push(max_turns.to_string());
             }
             push_optional(&mut args, "--system", system_prompt);
-            args
+            Ok(args)
         }
         ProviderId::Gemini => {
+            if session.mode != ExecutionMode::New {
+                return Err(format!("Gemini tasks cannot {} a session", session.mode));
+            }
             let mut args = vec![
                 task.prompt.clone(),
                 "--format".to_string(),
@@ -463,29 +1748,370 @@ fn build_provider_args(task: &Task, max_turns: i32) -> Vec<String> {
                 args.push(max_turns.to_string());
             }
             push_optional(&mut args, "--system-instruction", system_prompt);
-            args
+            Ok(args)
         }
     }
 }
 
-/// Parse JSON output for token usage stats.
-/// Returns `(total_tokens, estimated_cost_usd)`.
-fn parse_output_stats(output: &str) -> (i64, f64) {
-    let Ok(json) = serde_json::from_str::<serde_json::Value>(output) else {
-        return (0, 0.0);
+/// Token and cost totals accumulated from a run's streamed JSON events
+#[derive(Debug, Default)]
+struct CostTracker {
+    tokens: i64,
+    estimated_cost_usd: f64,
+    /// Exact total from Claude's final `result` event
+    reported_cost_usd: Option<f64>,
+    /// Claude repeats an assistant message's usage for each content block
+    seen_messages: HashSet<String>,
+}
+
+impl CostTracker {
+    fn cost_usd(&self) -> f64 {
+        self.reported_cost_usd.unwrap_or(self.estimated_cost_usd)
+    }
+
+    /// Account for one line of output; lines that are not usage events are ignored.
+    fn observe(&mut self, line: &str) {
+        let Ok(event) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+            return;
+        };
+        let usage_field = |usage: &serde_json::Value, key: &str| {
+            usage.get(key).and_then(|v| v.as_i64()).unwrap_or(0)
+        };
+
+        match event.get("type").and_then(|t| t.as_str()) {
+            // Claude: one event per content block of each assistant turn
+            Some("assistant") => {
+                let Some(message) = event.get("message") else { return };
+                let Some(usage) = message.get("usage") else { return };
+                if let Some(id) = message.get("id").and_then(|v| v.as_str()) {
+                    if !self.seen_messages.insert(id.to_string()) {
+                        return;
+                    }
+                }
+                let usage = TokenUsage {
+                    input_tokens: usage_field(usage, "input_tokens"),
+                    output_tokens: usage_field(usage, "output_tokens"),
+                    cache_read_input_tokens: usage_field(usage, "cache_read_input_tokens"),
+                    cache_creation_input_tokens: usage_field(usage, "cache_creation_input_tokens"),
+                    cache_creation: usage
+                        .get("cache_creation")
+                        .and_then(|c| serde_json::from_value(c.clone()).ok()),
+                };
+                let model = message.get("model").and_then(|v| v.as_str()).unwrap_or_default();
+                self.tokens +=
+                    usage.input_tokens + usage.output_tokens + usage.cache_read_input_tokens;
+                self.estimated_cost_usd += ModelPricing::for_model(model).calculate_cost(&usage);
+            }
+            // Claude: final summary with the CLI's own cost figure
+            Some("result") => {
+                if let Some(cost) = event.get("total_cost_usd").and_then(|v| v.as_f64()) {
+                    self.reported_cost_usd = Some(cost);
+                }
+                // Plain JSON output (terminal runs) reports usage only here
+                if let (true, Some(usage)) = (self.seen_messages.is_empty(), event.get("usage")) {
+                    self.tokens = usage_field(usage, "input_tokens")
+                        + usage_field(usage, "output_tokens")
+                        + usage_field(usage, "cache_read_input_tokens");
+                }
+            }
+            // Codex: usage for each completed turn (cached input is a subset of input)
+            Some("turn.completed") => {
+                let Some(usage) = event.get("usage") else { return };
+                let input = usage_field(usage, "input_tokens");
+                let cached = usage_field(usage, "cached_input_tokens");
+                let output = usage_field(usage, "output_tokens");
+                self.tokens += input + output;
+                self.estimated_cost_usd +=
+                    CodexModelPricing::for_model(None).calculate_cost(input, output, cached);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Find the session ID a run reports: Claude's JSON result carries
+/// `session_id`, Codex's event stream `thread_id` (or `session_id` in older releases).
+fn parse_output_session_id(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line.trim()).ok())
+        .find_map(|json| {
+            ["session_id", "thread_id"]
+                .iter()
+                .find_map(|key| json.get(key).or_else(|| json.get("msg")?.get(key)))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        })
+}
+
+// ============================================================================
+// Retries
+// ============================================================================
+
+/// A failed attempt that will be tried again
+struct Retry {
+    at: DateTime<Utc>,
+    /// Why the attempt failed
+    reason: String,
+    rate_limited: bool,
+}
+
+/// Why a failed run is worth trying again
+#[derive(Debug, Clone, PartialEq)]
+enum TransientFailure {
+    /// A usage or rate limit was hit, with the reset time if the output names one
+    RateLimited(Option<DateTime<Utc>>),
+    /// The provider was temporarily overloaded
+    Overloaded,
+}
+
+const RATE_LIMIT_MARKERS: &[&str] = &[
+    "rate_limit_error",
+    "rate limit",
+    "usage limit",
+    "5-hour limit",
+    "weekly limit",
+    "too many requests",
+    "resource_exhausted",
+    "quota exceeded",
+    "api error: 429",
+];
+
+const OVERLOADED_MARKERS: &[&str] = &["overloaded_error", "overloaded", "api error: 529"];
+
+/// Exponential backoff: `base_secs` after the first attempt, doubling with
+/// each further one, capped at `max_secs`
+fn retry_backoff(base_secs: u64, max_secs: u64, attempt: i32) -> chrono::Duration {
+    let exponent = attempt.saturating_sub(1).clamp(0, 31) as u32;
+    let secs = base_secs.saturating_mul(1 << exponent).min(max_secs);
+    chrono::Duration::seconds(secs as i64)
+}
+
+/// The error a line of output reports: the event if it is a JSON error event,
+/// or the whole line if it is plain text and `plain` allows it
+fn error_text(line: &str, plain: bool) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let Ok(event) = serde_json::from_str::<serde_json::Value>(line) else {
+        return plain.then(|| line.to_string());
+    };
+    let is_error = event.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false)
+        || event.get("error").is_some_and(|e| !e.is_null())
+        || matches!(
+            event.get("type").and_then(|t| t.as_str()),
+            Some("error" | "turn.failed")
+        );
+    is_error.then(|| event.to_string())
+}
+
+/// Spot a rate limit or overload in a failed run's output. Only error events,
+/// and plain text on stderr, are read, so a task that merely discusses rate
+/// limits is not taken for one that hit them.
+fn detect_transient_failure(output: &str, stderr: &str) -> Option<TransientFailure> {
+    // Gemini prints a single pretty-printed JSON document
+    let lines: Vec<&str> = if serde_json::from_str::<serde_json::Value>(output.trim()).is_ok() {
+        vec![output.trim()]
+    } else {
+        output.lines().collect()
     };
-    let Some(usage) = json.get("usage") else {
-        return (0, 0.0);
+    let errors: Vec<String> = lines
+        .into_iter()
+        .filter_map(|line| error_text(line, false))
+        .chain(stderr.lines().filter_map(|line| error_text(line, true)))
+        .map(|text| text.to_lowercase())
+        .collect();
+
+    let mentions = |markers: &[&str]| {
+        errors
+            .iter()
+            .find(|text| markers.iter().any(|m| text.contains(m)))
+            .cloned()
     };
+    if let Some(text) = mentions(RATE_LIMIT_MARKERS) {
+        return Some(TransientFailure::RateLimited(reported_reset_at(&text)));
+    }
+    mentions(OVERLOADED_MARKERS).map(|_| TransientFailure::Overloaded)
+}
 
-    let input = usage.get("input_tokens").and_then(|v| v.as_i64()).unwrap_or(0);
-    let output_tokens = usage.get("output_tokens").and_then(|v| v.as_i64()).unwrap_or(0);
-    let total = input + output_tokens;
+/// Reset time Claude appends to its usage-limit message as `|<unix seconds>`
+fn reported_reset_at(text: &str) -> Option<DateTime<Utc>> {
+    let (_, rest) = text.split_once("limit reached|")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    DateTime::from_timestamp(digits.parse().ok()?, 0)
+}
 
-    // Estimate cost (simplified, using Sonnet pricing)
-    let cost = (input as f64 * 3.0 / 1_000_000.0) + (output_tokens as f64 * 15.0 / 1_000_000.0);
+/// When a provider's exhausted usage window resets, from its live usage
+async fn provider_reset_at(provider: ProviderId) -> Option<DateTime<Utc>> {
+    let usage = match crate::providers::fetch_usage(provider).await {
+        Ok(usage) => usage,
+        Err(e) => {
+            tracing::warn!("Failed to fetch {} usage: {}", provider, e);
+            return None;
+        }
+    };
+    if let Some(e) = usage.error {
+        tracing::warn!("Failed to fetch {} usage: {}", provider, e);
+        return None;
+    }
 
-    (total, cost)
+    // A spent weekly allowance outlasts the session window
+    let reset_at = match usage.weekly_percent {
+        Some(percent) if percent >= 100.0 => usage.weekly_reset_at,
+        _ => usage.session_reset_at,
+    };
+    reset_at.as_deref().and_then(parse_timestamp)
+}
+
+// ============================================================================
+// Startup recovery
+// ============================================================================
+
+/// Why a run cut short by Alice exiting failed
+const INTERRUPTED_REASON: &str = "Interrupted: Alice exited while the task was running";
+
+/// Kept in the Alice directory while the queue runs, so the next launch can
+/// tell whether the queue was running when Alice exited
+fn queue_marker() -> std::path::PathBuf {
+    crate::platform::get_alice_dir().join("queue-running")
+}
+
+/// A task's latest run, if it never finished
+fn open_run(task_id: &str) -> Option<TaskRun> {
+    database::get_latest_task_run(task_id)
+        .ok()
+        .flatten()
+        .filter(|run| run.status == TaskRunStatus::Running)
+}
+
+/// Whether `pid` is still the process that started at `started_at`: false
+/// once it has exited or the PID has gone to another process. None when
+/// that cannot be checked.
+fn process_running(pid: u32, started_at: Option<&str>) -> Option<bool> {
+    if !crate::platform::is_process_alive(pid)? {
+        return Some(false);
+    }
+    let started_at = started_at?;
+    let current = crate::platform::process_start_time(pid)?;
+    Some(current == started_at)
+}
+
+/// The process of an unfinished run, if it is certainly still running.
+/// A run recorded without a start time cannot be told from a reused PID.
+fn run_process(run: &TaskRun) -> Option<u32> {
+    let pid = run.pid?;
+    (process_running(pid, run.pid_started_at.as_deref()) == Some(true)).then_some(pid)
+}
+
+/// A task's unfinished run in a terminal window that can still be followed:
+/// its CLI is still running there, or has exited and left its status behind
+fn reattachable_run(task_id: &str) -> Option<(TaskRun, TerminalRun)> {
+    let run = open_run(task_id)?;
+    let terminal = TerminalRun::new(run.log_path.as_deref()?.as_ref());
+    if !terminal.script.exists() {
+        return None;
+    }
+    let alive = run_process(&run).is_some() && terminal.pid() == run.pid;
+    (alive || terminal.exit_code().is_some()).then_some((run, terminal))
+}
+
+/// What became of a task the last session of Alice left running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recovery {
+    /// Queued to follow its run in a terminal window again
+    Reattach,
+    Requeued,
+    Failed,
+}
+
+/// Settle a task the last session of Alice left running. A run still going
+/// in a terminal window is queued to be followed again; otherwise the open
+/// run is closed and the task requeued or failed as configured.
+fn recover_task(
+    task: &Task,
+    action: InterruptedTaskAction,
+    now: &str,
+) -> Result<Recovery, database::DatabaseError> {
+    if reattachable_run(&task.id).is_some() {
+        tracing::info!("Task {} is still running in a terminal", task.id);
+        database::schedule_task_retry(&task.id, now)?;
+        return Ok(Recovery::Reattach);
+    }
+
+    if let Some(run) = open_run(&task.id) {
+        // A background CLI lost its output along with Alice, so it is
+        // stopped, unless its PID has since gone to another process
+        if let Some(pid) = run_process(&run) {
+            tracing::warn!("Stopping orphaned process {} of task {}", pid, task.id);
+            if let Err(e) = crate::platform::kill_process_tree(pid) {
+                tracing::warn!("{}", e);
+            }
+        }
+        database::finish_task_run(
+            run.id,
+            TaskRunStatus::Failed,
+            None,
+            Some(INTERRUPTED_REASON),
+            None,
+            None,
+            None,
+        )?;
+    }
+
+    match action {
+        InterruptedTaskAction::Requeue => {
+            tracing::info!("Requeueing interrupted task {}", task.id);
+            database::schedule_task_retry(&task.id, now)?;
+            Ok(Recovery::Requeued)
+        }
+        InterruptedTaskAction::Fail => {
+            tracing::info!("Failing interrupted task {}", task.id);
+            database::fail_task(&task.id, INTERRUPTED_REASON)?;
+            Ok(Recovery::Failed)
+        }
+    }
+}
+
+/// Whether the queue was running when Alice exited and should start again.
+/// The marker is cleared unless the queue resumes, which keeps it.
+fn take_queue_marker(marker: &std::path::Path, resume_after_restart: bool) -> bool {
+    let resume = marker.exists() && resume_after_restart;
+    if !resume {
+        let _ = std::fs::remove_file(marker);
+    }
+    resume
+}
+
+impl QueueExecutor {
+    /// Settle the tasks the last session of Alice left running, skipping any
+    /// this session has started. Returns how many can be followed again.
+    async fn recover_interrupted_tasks(&self) -> usize {
+        let tasks = match database::get_tasks(&self.app, Some(TaskStatus::Running), None) {
+            Ok(tasks) => tasks,
+            Err(e) => {
+                tracing::error!("Failed to look for interrupted tasks: {}", e);
+                return 0;
+            }
+        };
+        let action = crate::config::load_config().queue.interrupted_tasks;
+        let now = Utc::now().to_rfc3339();
+        let mut reattached = 0;
+
+        for task in tasks {
+            if self.running_tasks.lock().await.contains_key(&task.id) {
+                continue;
+            }
+            match recover_task(&task, action, &now) {
+                Ok(Recovery::Reattach) => reattached += 1,
+                Ok(Recovery::Failed) => self.finish_worktree(&task.id, &TaskStatus::Failed).await,
+                Ok(Recovery::Requeued) => {}
+                Err(e) => tracing::error!("Failed to recover interrupted task {}: {}", task.id, e),
+            }
+        }
+
+        reattached
+    }
 }
 
 // ============================================================================
@@ -495,10 +2121,30 @@ fn parse_output_stats(output: &str) -> (i64, f64) {
 static QUEUE_EXECUTOR: once_cell::sync::OnceCell<tokio::sync::Mutex<Option<QueueExecutor>>> =
     once_cell::sync::OnceCell::new();
 
-/// Initialize the queue executor
+/// Held while the tasks of Alice's last session are recovered, so the queue
+/// cannot start on them halfway
+static RECOVERY: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Initialize the queue executor and recover what the last session left
+/// running. The queue waits for recovery before it starts.
 pub fn init_queue(app: &AppHandle) {
     let executor = QueueExecutor::new(app.clone());
     let _ = QUEUE_EXECUTOR.set(tokio::sync::Mutex::new(Some(executor)));
+
+    // Taken before anything else can start the queue
+    let recovering = RECOVERY.try_lock().ok();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let resume = recover_queue(&app).await;
+        drop(recovering);
+        if resume {
+            tracing::info!("Resuming the queue after restart");
+            // Already started by the scheduler or by hand
+            if let Err(e) = start_queue(&app).await {
+                tracing::debug!("Queue not resumed: {}", e);
+            }
+        }
+    });
 }
 
 /// Try to acquire the global queue executor, returning a reference if available.
@@ -507,19 +2153,428 @@ async fn with_executor<T>(f: impl FnOnce(&QueueExecutor) -> T) -> Option<T> {
     guard.as_ref().map(f)
 }
 
-/// Start queue execution
+/// Start queue execution on the shared executor, so stop and status requests
+/// reach it, once startup recovery is done
 pub async fn start_queue(app: &AppHandle) -> Result<(), String> {
-    let executor = QueueExecutor::new(app.clone());
+    drop(RECOVERY.lock().await);
+    let executor = match with_executor(|e| e.clone()).await {
+        Some(executor) => executor,
+        None => QueueExecutor::new(app.clone()),
+    };
     executor.start().await
 }
 
-/// Stop queue execution
-pub async fn stop_queue() -> Result<(), String> {
-    with_executor(|e| e.stop()).await;
+/// Recover the tasks Alice was running when it last exited. Returns whether
+/// the queue was running then and is set to resume; otherwise terminal runs
+/// still going are left queued, and followed once the queue is started.
+async fn recover_queue(app: &AppHandle) -> bool {
+    let resume_after_restart = crate::config::load_config().queue.resume_after_restart;
+    let resume = take_queue_marker(&queue_marker(), resume_after_restart);
+    let executor = match with_executor(|e| e.clone()).await {
+        Some(executor) => executor,
+        None => QueueExecutor::new(app.clone()),
+    };
+    let reattached = executor.recover_interrupted_tasks().await;
+    if !resume {
+        if reattached > 0 {
+            tracing::info!("{} terminal runs wait for the queue to start", reattached);
+        }
+        executor.emit_status().await;
+    }
+    resume
+}
+
+/// Stop queue execution, optionally cancelling the running tasks
+pub async fn stop_queue(cancel_running: bool) -> Result<(), String> {
+    if let Some(executor) = with_executor(|e| e.clone()).await {
+        executor.stop(cancel_running).await;
+    }
     Ok(())
 }
 
+/// Cancel a queued or running task
+pub async fn cancel_task(task_id: &str) -> Result<(), String> {
+    let executor = with_executor(|e| e.clone())
+        .await
+        .ok_or("Queue executor unavailable")?;
+    executor.cancel_task(task_id).await
+}
+
 /// Check if queue is running
 pub async fn is_queue_running() -> bool {
     with_executor(|e| e.is_running()).await.unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_tracker() {
+        let mut costs = CostTracker::default();
+        let block = r#"{"type":"assistant","message":{"id":"msg_1","model":"claude-sonnet-4","usage":{"input_tokens":100,"output_tokens":50,"cache_read_input_tokens":10}}}"#;
+        costs.observe(block);
+        // Repeated for each content block of the same message
+        costs.observe(block);
+        costs.observe("not json");
+        assert_eq!(costs.tokens, 160);
+        assert!(costs.cost_usd() > 0.0);
+
+        costs.observe(r#"{"type":"result","total_cost_usd":0.25,"usage":{"input_tokens":1}}"#);
+        assert_eq!(costs.cost_usd(), 0.25);
+        assert_eq!(costs.tokens, 160);
+    }
+
+    #[test]
+    fn test_cost_tracker_result_only() {
+        let mut costs = CostTracker::default();
+        costs.observe(r#"{"type":"result","total_cost_usd":0.5,"usage":{"input_tokens":7,"output_tokens":3}}"#);
+        assert_eq!(costs.tokens, 10);
+        assert_eq!(costs.cost_usd(), 0.5);
+    }
+
+    #[test]
+    fn test_cost_tracker_codex() {
+        let mut costs = CostTracker::default();
+        let turn = r#"{"type":"turn.completed","usage":{"input_tokens":200,"cached_input_tokens":50,"output_tokens":20}}"#;
+        costs.observe(turn);
+        costs.observe(turn);
+        assert_eq!(costs.tokens, 440);
+        assert!(costs.cost_usd() > 0.0);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        assert_eq!(retry_backoff(30, 3600, 1), chrono::Duration::seconds(30));
+        assert_eq!(retry_backoff(30, 3600, 3), chrono::Duration::seconds(120));
+        assert_eq!(retry_backoff(30, 3600, 10), chrono::Duration::seconds(3600));
+        assert_eq!(retry_backoff(30, 3600, 0), chrono::Duration::seconds(30));
+        assert_eq!(retry_backoff(30, 3600, i32::MAX), chrono::Duration::seconds(3600));
+    }
+
+    #[test]
+    fn test_task_time_limit() {
+        let queue = crate::config::QueueConfig { timeout_minutes: 30, ..Default::default() };
+        let limit = |timeout_minutes| {
+            let mut task = database::test_task("t1");
+            task.timeout_minutes = timeout_minutes;
+            task_time_limit(&task, &queue)
+        };
+        assert_eq!(limit(None), Some(std::time::Duration::from_secs(30 * 60)));
+        assert_eq!(limit(Some(5)), Some(std::time::Duration::from_secs(5 * 60)));
+        // 0 lifts the limit, and so does a negative value
+        assert_eq!(limit(Some(0)), None);
+        assert_eq!(limit(Some(-10)), None);
+
+        let unlimited = crate::config::QueueConfig { timeout_minutes: 0, ..Default::default() };
+        assert_eq!(task_time_limit(&database::test_task("t1"), &unlimited), None);
+    }
+
+    #[test]
+    fn test_verify_command_precedence() {
+        let mut queue = crate::config::QueueConfig::default();
+        queue.verify_commands.insert("/work/app".to_string(), " npm test ".to_string());
+        let command = |own: Option<&str>, project: Option<&str>| {
+            let mut task = database::test_task("t1");
+            task.verify_command = own.map(str::to_string);
+            task.project_path = project.map(str::to_string);
+            verify_command(&task, &queue)
+        };
+
+        assert_eq!(command(None, Some("/work/app")).as_deref(), Some("npm test"));
+        // The task's own command beats its project's, and an empty one turns checks off
+        assert_eq!(command(Some("cargo test"), Some("/work/app")).as_deref(), Some("cargo test"));
+        assert_eq!(command(Some(""), Some("/work/app")), None);
+        assert_eq!(command(Some("  "), Some("/work/app")), None);
+        assert_eq!(command(None, Some("/work/other")), None);
+        assert_eq!(command(None, None), None);
+    }
+
+    #[test]
+    fn test_output_tail() {
+        assert_eq!(output_tail("short\n\n", 100), "short");
+        assert_eq!(output_tail("abcdef", 3), "def");
+        // Counted in characters, never cutting one in half
+        assert_eq!(output_tail("テストが失敗しました\n", 4), "しました");
+        assert_eq!(output_tail("é🦀ü", 2), "🦀ü");
+    }
+
+    #[test]
+    fn test_fix_depth_and_cap() {
+        let _db = database::reset_test_database();
+        let original = database::test_task("original");
+        let mut first = database::test_task("first");
+        first.fix_of = Some("original".to_string());
+        let mut second = database::test_task("second");
+        second.fix_of = Some("first".to_string());
+        for task in [&original, &first, &second] {
+            database::insert_test_task(task);
+        }
+
+        assert_eq!(fix_depth(&original), 0);
+        assert_eq!(fix_depth(&second), 2);
+        assert!(!fix_task_allowed(&original, false, 0));
+        assert!(fix_task_allowed(&original, false, 2));
+        assert!(fix_task_allowed(&first, false, 2));
+        assert!(!fix_task_allowed(&second, false, 2));
+        // Worktree runs never get a fix task
+        assert!(!fix_task_allowed(&original, true, 2));
+    }
+
+    #[test]
+    fn test_pause_until() {
+        let now = DateTime::parse_from_rfc3339("2025-06-01T10:00:00Z").unwrap().to_utc();
+        let usage = |session: f64, weekly: Option<f64>, session_reset: &str, weekly_reset: &str| {
+            crate::providers::ProviderUsage {
+                id: ProviderId::Claude,
+                session_percent: session,
+                session_reset_at: Some(session_reset.to_string()).filter(|s| !s.is_empty()),
+                weekly_percent: weekly,
+                weekly_reset_at: Some(weekly_reset.to_string()).filter(|s| !s.is_empty()),
+                last_updated: 0,
+                error: None,
+            }
+        };
+        let at = |s: &str| Some(DateTime::parse_from_rfc3339(s).unwrap().to_utc());
+        let session_reset = "2025-06-01T12:00:00Z";
+        let weekly_reset = "2025-06-05T00:00:00Z";
+
+        assert_eq!(pause_until(&usage(50.0, Some(50.0), session_reset, weekly_reset), 90.0, now), None);
+        assert_eq!(
+            pause_until(&usage(95.0, Some(50.0), session_reset, weekly_reset), 90.0, now),
+            at(session_reset)
+        );
+        // The weekly limit wins over the session one
+        assert_eq!(
+            pause_until(&usage(95.0, Some(92.0), session_reset, weekly_reset), 90.0, now),
+            at(weekly_reset)
+        );
+        // An unknown reset is checked again in a minute
+        assert_eq!(
+            pause_until(&usage(95.0, None, "", ""), 90.0, now),
+            Some(now + chrono::Duration::minutes(1))
+        );
+        // A reset already past is no pause, and neither is a threshold of 0
+        let past = usage(95.0, None, "2025-06-01T09:00:00Z", "");
+        assert_eq!(pause_until(&past, 90.0, now), None);
+        assert_eq!(pause_until(&usage(100.0, None, session_reset, ""), 0.0, now), None);
+    }
+
+    #[test]
+    fn test_queue_state() {
+        let resume_at = Some(Utc::now());
+        assert_eq!(queue_state(false, false, resume_at), QueueState::Stopped);
+        assert_eq!(queue_state(true, false, None), QueueState::Running);
+        // Waiting for a reset only while nothing else runs
+        assert_eq!(queue_state(true, false, resume_at), QueueState::WaitingForReset);
+        assert_eq!(queue_state(true, true, resume_at), QueueState::Running);
+    }
+
+    #[test]
+    fn test_detect_transient_failure() {
+        let limited = r#"{"type":"result","is_error":true,"result":"Claude AI usage limit reached|1767225600"}"#;
+        assert_eq!(
+            detect_transient_failure(limited, ""),
+            Some(TransientFailure::RateLimited(DateTime::from_timestamp(1767225600, 0)))
+        );
+        assert_eq!(
+            detect_transient_failure("", "API Error: 529 Overloaded"),
+            Some(TransientFailure::Overloaded)
+        );
+        assert_eq!(
+            detect_transient_failure("", "Error: 429 Too Many Requests"),
+            Some(TransientFailure::RateLimited(None))
+        );
+
+        // Output that only talks about rate limits is not a failure
+        let discussed = r#"{"type":"assistant","message":{"content":"Added a rate limit"}}"#;
+        assert_eq!(detect_transient_failure(discussed, ""), None);
+        assert_eq!(detect_transient_failure("rate limit handling done", ""), None);
+    }
+
+    #[test]
+    fn test_reported_reset_at() {
+        assert_eq!(
+            reported_reset_at("claude ai usage limit reached|1767225600"),
+            DateTime::from_timestamp(1767225600, 0)
+        );
+        assert_eq!(reported_reset_at("usage limit reached"), None);
+        assert_eq!(reported_reset_at("limit reached|soon"), None);
+    }
+
+    #[test]
+    fn test_resolve_task_session() {
+        let _db = database::reset_test_database();
+        let mut upstream = database::test_task("upstream");
+        upstream.session_id = Some("s-up".to_string());
+        database::insert_test_task(&upstream);
+
+        // Claude runs get a preassigned session ID so they can be traced
+        let session = resolve_task_session(&database::test_task("t1")).unwrap();
+        assert_eq!((session.mode, session.resume_id), (ExecutionMode::New, None));
+        assert!(session.expected_id.is_some());
+        let mut codex = database::test_task("t1");
+        codex.provider = ProviderId::Codex;
+        assert_eq!(resolve_task_session(&codex).unwrap().expected_id, None);
+
+        let mut resume = database::test_task("t2");
+        resume.execution_mode = ExecutionMode::Resume;
+        resume.session_id = Some("s-own".to_string());
+        let session = resolve_task_session(&resume).unwrap();
+        assert_eq!(session.resume_id.as_deref(), Some("s-own"));
+        assert_eq!(session.expected_id.as_deref(), Some("s-own"));
+
+        // Without a session of its own a task builds on its dependency's
+        let mut fork = database::test_task("t3");
+        fork.execution_mode = ExecutionMode::Fork;
+        fork.depends_on = vec!["upstream".to_string()];
+        let session = resolve_task_session(&fork).unwrap();
+        assert_eq!(session.resume_id.as_deref(), Some("s-up"));
+        assert_eq!(session.expected_id, None);
+
+        // Once the dependency has run, its fork comes from the session it produced
+        database::set_task_result("upstream", 0, "", 0, 0.0, None, Some("s-up-2")).unwrap();
+        let session = resolve_task_session(&fork).unwrap();
+        assert_eq!(session.resume_id.as_deref(), Some("s-up-2"));
+        // A later run that reported no session keeps the last one
+        database::set_task_result("upstream", 1, "", 0, 0.0, None, None).unwrap();
+        let upstream = database::get_task("upstream").unwrap();
+        assert_eq!(upstream.output_session_id.as_deref(), Some("s-up-2"));
+        assert_eq!(upstream.session_id.as_deref(), Some("s-up"));
+
+        fork.depends_on.clear();
+        assert!(resolve_task_session(&fork).is_err());
+    }
+
+    #[test]
+    fn test_build_provider_args_for_modes() {
+        let session = |mode: ExecutionMode| TaskSession {
+            mode,
+            resume_id: Some("s1".to_string()),
+            expected_id: Some("s2".to_string()),
+        };
+        let args = |task: &Task, mode: ExecutionMode| {
+            build_provider_args(task, 10, &session(mode), true).map(|args| args.join(" "))
+        };
+
+        let claude = database::test_task("t1");
+        assert!(args(&claude, ExecutionMode::New).unwrap().contains("--session-id s2"));
+        assert!(args(&claude, ExecutionMode::Resume).unwrap().contains("--resume s1"));
+        assert!(args(&claude, ExecutionMode::Continue).unwrap().contains("--continue"));
+        let fork = args(&claude, ExecutionMode::Fork).unwrap();
+        assert!(fork.contains("--resume s1 --fork-session"));
+
+        let mut codex = database::test_task("t1");
+        codex.provider = ProviderId::Codex;
+        let resume = args(&codex, ExecutionMode::Resume).unwrap();
+        assert!(resume.starts_with("exec resume s1 Fix the build --json"));
+        let last = args(&codex, ExecutionMode::Continue).unwrap();
+        assert!(last.starts_with("exec resume --last Fix the build --json"));
+        assert!(args(&codex, ExecutionMode::Fork).is_err());
+        let no_session = TaskSession { resume_id: None, ..session(ExecutionMode::Resume) };
+        assert!(build_provider_args(&codex, 10, &no_session, true).is_err());
+
+        let mut gemini = database::test_task("t1");
+        gemini.provider = ProviderId::Gemini;
+        assert!(args(&gemini, ExecutionMode::New).is_ok());
+        assert!(args(&gemini, ExecutionMode::Resume).is_err());
+    }
+
+    /// A running task with an open run, logging to `dir`
+    fn interrupted_task(id: &str, dir: &std::path::Path) -> TaskRun {
+        let mut task = database::test_task(id);
+        task.status = TaskStatus::Running;
+        database::insert_test_task(&task);
+        let run = database::start_task_run(id).unwrap();
+        database::set_test_run_log_path(run.id, &dir.join("1.log"));
+        run
+    }
+
+    #[test]
+    fn test_open_run() {
+        let _db = database::reset_test_database();
+        let dir = tempfile::tempdir().unwrap();
+        assert!(open_run("t1").is_none());
+
+        let run = interrupted_task("t1", dir.path());
+        assert_eq!(open_run("t1").map(|open| open.id), Some(run.id));
+
+        database::finish_task_run(run.id, TaskRunStatus::Completed, Some(0), None, None, None, None)
+            .unwrap();
+        assert!(open_run("t1").is_none());
+    }
+
+    #[test]
+    fn test_reattachable_run() {
+        let _db = database::reset_test_database();
+        let dir = tempfile::tempdir().unwrap();
+        let run = interrupted_task("t1", dir.path());
+        let terminal = TerminalRun::new(&dir.path().join("1.log"));
+
+        // Without its script the run was never in a terminal
+        assert!(reattachable_run("t1").is_none());
+        std::fs::write(&terminal.script, "").unwrap();
+        assert!(reattachable_run("t1").is_none());
+
+        // The CLI still running there, as this test stands in for
+        let pid = std::process::id();
+        let started_at = crate::platform::process_start_time(pid);
+        database::set_task_run_pid(run.id, pid, started_at.as_deref()).unwrap();
+        std::fs::write(&terminal.pid_file, pid.to_string()).unwrap();
+        assert_eq!(reattachable_run("t1").map(|(open, _)| open.id), Some(run.id));
+
+        // Or exited, leaving its status behind
+        std::fs::remove_file(&terminal.pid_file).unwrap();
+        assert!(reattachable_run("t1").is_none());
+        std::fs::write(&terminal.exit_file, "0").unwrap();
+        let (_, found) = reattachable_run("t1").unwrap();
+        assert_eq!(found.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn test_recover_task() {
+        let _db = database::reset_test_database();
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now().to_rfc3339();
+        let recover = |id: &str, action| recover_task(&database::get_task(id).unwrap(), action, &now);
+
+        let requeued = interrupted_task("t1", dir.path());
+        assert_eq!(recover("t1", InterruptedTaskAction::Requeue).unwrap(), Recovery::Requeued);
+        let run = database::get_task_run(requeued.id).unwrap();
+        assert_eq!(run.status, TaskRunStatus::Failed);
+        assert_eq!(run.error.as_deref(), Some(INTERRUPTED_REASON));
+        let task = database::get_task("t1").unwrap();
+        assert_eq!(task.status, TaskStatus::Queued);
+        assert_eq!(task.next_attempt_at.as_deref(), Some(now.as_str()));
+
+        let failed = interrupted_task("t2", dir.path());
+        assert_eq!(recover("t2", InterruptedTaskAction::Fail).unwrap(), Recovery::Failed);
+        assert_eq!(database::get_task_run(failed.id).unwrap().status, TaskRunStatus::Failed);
+        let task = database::get_task("t2").unwrap();
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(task.result_error.as_deref(), Some(INTERRUPTED_REASON));
+        assert!(task.completed_at.is_some());
+
+        // A run that can be followed again is left open
+        let followed = interrupted_task("t3", dir.path());
+        let terminal = TerminalRun::new(&dir.path().join("1.log"));
+        std::fs::write(&terminal.script, "").unwrap();
+        std::fs::write(&terminal.exit_file, "0").unwrap();
+        assert_eq!(recover("t3", InterruptedTaskAction::Fail).unwrap(), Recovery::Reattach);
+        assert_eq!(database::get_task_run(followed.id).unwrap().status, TaskRunStatus::Running);
+        assert_eq!(database::get_task("t3").unwrap().status, TaskStatus::Queued);
+    }
+
+    #[test]
+    fn test_take_queue_marker() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("queue-running");
+        assert!(!take_queue_marker(&marker, true));
+
+        std::fs::write(&marker, "").unwrap();
+        assert!(take_queue_marker(&marker, true));
+        assert!(marker.exists());
+        assert!(!take_queue_marker(&marker, false));
+        assert!(!marker.exists());
+    }
+}
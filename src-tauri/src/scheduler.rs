@@ -0,0 +1,380 @@
+// Task Scheduler - enqueue tasks at their run time, on cron recurrences and within run windows
+
+use crate::config::RunWindow;
+use crate::database::{self, TaskStatus};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use tauri::AppHandle;
+
+/// How often the scheduler looks for due tasks
+const SCHEDULER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// How far ahead `CronSchedule::next_after` searches (covers a Feb 29 schedule)
+const CRON_SEARCH_DAYS: i64 = 5 * 366;
+
+// ============================================================================
+// Cron expressions
+// ============================================================================
+
+/// A five-field cron expression (minute hour day-of-month month day-of-week),
+/// evaluated in local time. Fields accept `*`, values, `a-b` ranges, `/step`
+/// and comma lists; `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly`
+/// are accepted as shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day-of-month was restricted (not `*`)
+    days_restricted: bool,
+    /// Day-of-week was restricted (not `*`)
+    weekdays_restricted: bool,
+}
+
+/// Parse one cron field into a bit set of the values it allows
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err(format!("zero step in '{}'", part));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let value = |v: &str| -> Result<u32, String> {
+            let n: u32 = v.parse().map_err(|_| format!("invalid value '{}'", v))?;
+            if n < min || n > max {
+                return Err(format!("{} is outside {}-{}", n, min, max));
+            }
+            Ok(n)
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                // `5/15` runs from 5 to the end of the range
+                None if step > 1 => (value(range)?, max),
+                None => {
+                    let n = value(range)?;
+                    (n, n)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("empty range '{}'", range));
+        }
+        for n in (start..=end).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("expected 5 fields, got {}", fields.len()));
+        };
+
+        let mut weekdays = parse_cron_field(weekday, 0, 7)?;
+        // 7 is another name for Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_cron_field(minute, 0, 59)?,
+            hours: parse_cron_field(hour, 0, 23)?,
+            days: parse_cron_field(day, 1, 31)?,
+            months: parse_cron_field(month, 1, 12)?,
+            weekdays,
+            // As in Vixie cron, a field starting with `*` (even `*/2`) counts as unrestricted
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let by_day = self.days & (1 << date.day()) != 0;
+        let by_weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        // As in classic cron, restricting both fields matches either of them
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => by_day || by_weekday,
+            _ => by_day && by_weekday,
+        }
+    }
+
+    /// The first time strictly after `after` that the schedule fires, in its time zone
+    pub fn next_after<Tz: TimeZone>(&self, after: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(CRON_SEARCH_DAYS);
+        let mut t = start;
+
+        while t < limit {
+            if self.months & (1 << t.month()) == 0 {
+                let (year, month) = if t.month() == 12 {
+                    (t.year() + 1, 1)
+                } else {
+                    (t.year(), t.month() + 1)
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_time(NaiveTime::MIN);
+            } else if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_time(NaiveTime::MIN);
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = t.with_minute(0)? + Duration::hours(1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::minutes(1);
+            } else {
+                // Times skipped by a DST change never fire
+                match tz.from_local_datetime(&t).earliest() {
+                    Some(time) => return Some(time),
+                    None => t += Duration::minutes(1),
+                }
+            }
+        }
+
+        None
+    }
+}
+
+// ============================================================================
+// Run windows
+// ============================================================================
+
+/// Start and end times of the windows; malformed ones are left out when the
+/// config loads, and ignored here
+fn valid_windows(windows: &[RunWindow]) -> Vec<(NaiveTime, NaiveTime)> {
+    windows.iter().filter_map(RunWindow::bounds).collect()
+}
+
+/// Whether tasks may start at `now`. No windows means no restriction.
+pub fn in_run_window<Tz: TimeZone>(windows: &[RunWindow], now: DateTime<Tz>) -> bool {
+    let windows = valid_windows(windows);
+    if windows.is_empty() {
+        return true;
+    }
+
+    let time = now.time();
+    windows.iter().any(|&(start, end)| {
+        if start <= end {
+            start <= time && time < end
+        } else {
+            // Crosses midnight
+            time >= start || time < end
+        }
+    })
+}
+
+/// When the next run window opens after `now`
+pub fn next_window_start<Tz: TimeZone>(
+    windows: &[RunWindow],
+    now: DateTime<Tz>,
+) -> Option<DateTime<Tz>> {
+    let today = now.date_naive();
+    let tz = now.timezone();
+    valid_windows(windows)
+        .iter()
+        .filter_map(|&(start, _)| {
+            [today, today.succ_opt()?]
+                .into_iter()
+                .filter_map(|date| tz.from_local_datetime(&date.and_time(start)).earliest())
+                .find(|t| *t > now)
+        })
+        .min()
+}
+
+// ============================================================================
+// Scheduler service
+// ============================================================================
+
+/// Next run time of a recurrence, as stored on a task
+pub fn next_occurrence(recurrence: &str, after: DateTime<Utc>) -> Result<String, String> {
+    let schedule = CronSchedule::parse(recurrence)?;
+    schedule
+        .next_after(after.with_timezone(&Local))
+        .map(|t| t.with_timezone(&Utc).to_rfc3339())
+        .ok_or_else(|| format!("'{}' never fires", recurrence))
+}
+
+/// Queue every task whose run time has come. Recurring tasks get their next
+/// run time; one-off schedules are cleared. A task still queued or running
+/// skips the run, moving on to its next one if it recurs. Returns how many
+/// were queued.
+fn enqueue_due_tasks(app: &AppHandle) -> Result<usize, database::DatabaseError> {
+    let now = Utc::now();
+    let due: Vec<_> = database::get_tasks(app, None, None)?
+        .into_iter()
+        .filter(|t| {
+            t.run_at
+                .as_deref()
+                .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                .is_some_and(|at| at <= now)
+        })
+        .collect();
+
+    let mut queued = 0;
+    for task in &due {
+        let busy = matches!(task.status, TaskStatus::Queued | TaskStatus::Running);
+        let next_run_at = task.recurrence.as_deref().and_then(|recurrence| {
+            next_occurrence(recurrence, now)
+                .map_err(|e| tracing::warn!("Task {} recurrence: {}", task.id, e))
+                .ok()
+        });
+        if busy {
+            tracing::info!(
+                "Scheduled task {} is still {}, skipping this run",
+                task.id, task.status
+            );
+            database::set_task_run_at(&task.id, next_run_at.as_deref())?;
+            continue;
+        }
+        tracing::info!("Scheduled task {} is due, queueing it", task.id);
+        database::enqueue_scheduled_task(app, &task.id, next_run_at.as_deref())?;
+        queued += 1;
+    }
+
+    Ok(queued)
+}
+
+/// Start the scheduler: every interval, queue due tasks and start the queue for them
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let queued = match enqueue_due_tasks(&app) {
+                Ok(queued) => queued,
+                Err(e) => {
+                    tracing::error!("Scheduler failed to queue due tasks: {}", e);
+                    continue;
+                }
+            };
+            if queued == 0
+                || crate::queue::is_queue_running().await
+                || !crate::config::load_config().terminal_choice_made
+            {
+                continue;
+            }
+
+            let queue_app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = crate::queue::start_queue(&queue_app).await {
+                    tracing::error!("Queue execution error: {}", e);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Times in UTC, so results do not depend on the machine's time zone
+    fn local(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn window(start: &str, end: &str) -> RunWindow {
+        RunWindow { start: start.to_string(), end: end.to_string() }
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(CronSchedule::parse("0 9 * * 1-5").is_ok());
+        assert_eq!(CronSchedule::parse("@daily"), CronSchedule::parse("0 0 * * *"));
+        assert_eq!(CronSchedule::parse("0 0 * * 7"), CronSchedule::parse("0 0 * * 0"));
+        assert!(CronSchedule::parse("0 9 * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("0 5-1 * * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        let schedule = CronSchedule::parse("30 9 * * *").unwrap();
+        assert_eq!(schedule.next_after(local(2025, 3, 10, 8, 0)), Some(local(2025, 3, 10, 9, 30)));
+        // Strictly after: the current minute does not count
+        assert_eq!(schedule.next_after(local(2025, 3, 10, 9, 30)), Some(local(2025, 3, 11, 9, 30)));
+
+        // 2025-03-14 is a Friday
+        let weekdays = CronSchedule::parse("0 9 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(local(2025, 3, 14, 10, 0)), Some(local(2025, 3, 17, 9, 0)));
+
+        let leap = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap.next_after(local(2025, 1, 1, 0, 0)), Some(local(2028, 2, 29, 0, 0)));
+    }
+
+    #[test]
+    fn test_restricted_day_fields() {
+        // Both fields restricted: either may match (the 1st, or a Monday)
+        let either = CronSchedule::parse("0 0 1 * 1").unwrap();
+        assert_eq!(either.next_after(local(2025, 3, 1, 12, 0)), Some(local(2025, 3, 3, 0, 0)));
+
+        // A day field starting with `*` does not count as restricted: both must match
+        let stepped = CronSchedule::parse("0 0 */10 * 1").unwrap();
+        assert_eq!(stepped.next_after(local(2025, 3, 1, 12, 0)), Some(local(2025, 3, 31, 0, 0)));
+        let ranged = CronSchedule::parse("0 0 1-31/10 * 1").unwrap();
+        assert_eq!(ranged.next_after(local(2025, 3, 1, 12, 0)), Some(local(2025, 3, 3, 0, 0)));
+
+        // An unrestricted day field leaves only the weekday
+        let weekly = CronSchedule::parse("0 0 * * 1").unwrap();
+        assert_eq!(weekly.next_after(local(2025, 3, 1, 12, 0)), Some(local(2025, 3, 3, 0, 0)));
+        assert_eq!(weekly.next_after(local(2025, 3, 3, 12, 0)), Some(local(2025, 3, 10, 0, 0)));
+    }
+
+    #[test]
+    fn test_in_run_window() {
+        assert!(in_run_window(&[], local(2025, 3, 10, 3, 0)));
+
+        let day = [window("09:00", "17:00")];
+        assert!(in_run_window(&day, local(2025, 3, 10, 9, 0)));
+        assert!(!in_run_window(&day, local(2025, 3, 10, 17, 0)));
+        assert!(!in_run_window(&day, local(2025, 3, 10, 8, 59)));
+
+        let night = [window("22:00", "06:00")];
+        assert!(in_run_window(&night, local(2025, 3, 10, 23, 0)));
+        assert!(in_run_window(&night, local(2025, 3, 10, 5, 59)));
+        assert!(!in_run_window(&night, local(2025, 3, 10, 12, 0)));
+
+        // Malformed windows are ignored, leaving no restriction
+        assert!(in_run_window(&[window("9am", "5pm")], local(2025, 3, 10, 3, 0)));
+    }
+
+    #[test]
+    fn test_invalid_run_windows_dropped_on_load() {
+        let queue: crate::config::QueueConfig = serde_json::from_value(serde_json::json!({
+            "run_windows": [{"start": "22:00", "end": "06:00"}, {"start": "9am", "end": "5pm"}],
+        }))
+        .unwrap();
+        assert_eq!(queue.run_windows.len(), 1);
+        assert_eq!(queue.run_windows[0].start, "22:00");
+    }
+
+    #[test]
+    fn test_next_window_start() {
+        let windows = [window("09:00", "12:00"), window("14:00", "18:00")];
+        let next = |now| next_window_start(&windows, now);
+        assert_eq!(next(local(2025, 3, 10, 8, 0)), Some(local(2025, 3, 10, 9, 0)));
+        assert_eq!(next(local(2025, 3, 10, 13, 0)), Some(local(2025, 3, 10, 14, 0)));
+        // After the last window closes, the first one opens the next day
+        assert_eq!(next(local(2025, 3, 10, 19, 0)), Some(local(2025, 3, 11, 9, 0)));
+        assert_eq!(next_window_start(&[], local(2025, 3, 10, 8, 0)), None);
+    }
+}
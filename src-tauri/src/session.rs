@@ -2,14 +2,15 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::path::Path;
+use std::time::Duration;
 
 /// Session status
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub enum SessionStatus {
     Idle,
     Active,
@@ -26,7 +27,7 @@ impl SessionStatus {
             SessionStatus::Active => "active",
             SessionStatus::Completed => "completed",
             SessionStatus::Error => "error",
-            SessionStatus::NeedsInput => "needsinput",
+            SessionStatus::NeedsInput => "needs_input",
         }
     }
 }
@@ -58,6 +59,16 @@ pub struct Session {
     /// Provider that created this session
     #[serde(default)]
     pub provider: crate::providers::ProviderId,
+    /// Session that spawned this one, for sub-agent (sidechain) sessions
+    #[serde(default)]
+    pub parent_session_id: Option<String>,
+    /// User metadata, stored apart from the parsed transcript
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
 }
 
 /// Detailed session information
@@ -67,10 +78,64 @@ pub struct SessionDetail {
     pub messages: Vec<SessionMessage>,
 }
 
-/// Individual message in a session
+/// A search result: the matching session plus its rank and matched excerpt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSearchHit {
+    #[serde(flatten)]
+    pub session: Session,
+    /// bm25 score (lower is more relevant); None without full-text terms
+    pub rank: Option<f64>,
+    /// Excerpt around the best match
+    pub snippet: Option<String>,
+    /// `[start, end)` offsets of matched terms in `snippet`, in UTF-16 code units
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// Result of parsing a session file from a cursor
+#[derive(Debug, Clone)]
+pub struct SessionUpdate {
+    pub session: Session,
+    /// Messages parsed in this pass: the whole transcript when `full` is set,
+    /// otherwise only those appended since the previous cursor
+    pub messages: Vec<SessionMessage>,
+    pub full: bool,
+    /// Cursor to resume from next time (None if the provider re-parses whole files)
+    pub cursor: Option<SessionCursor>,
+    /// Sub-agent sessions logged inside this file that this pass touched,
+    /// with their messages from this pass (same `full` semantics)
+    pub agents: Vec<SessionDetail>,
+    /// Digests of the keys first seen in this pass, to record with the cursor
+    pub new_keys: Vec<u64>,
+}
+
+/// A session with its sub-agent sessions and their usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTree {
+    #[serde(flatten)]
+    pub detail: SessionDetail,
+    pub agents: Vec<AgentNode>,
+    /// Tokens of the session and all of its sub-agents
+    pub tree_tokens: i64,
+    /// Cost of the session and all of its sub-agents
+    pub tree_cost_usd: f64,
+}
+
+/// A sub-agent session within a `SessionTree`
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentNode {
+    pub session: Session,
+    pub children: Vec<AgentNode>,
+    /// Tokens of this agent and its descendants
+    pub tree_tokens: i64,
+    /// Cost of this agent and its descendants
+    pub tree_cost_usd: f64,
+}
+
+/// Individual message in a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SessionMessage {
     pub id: Option<String>,
+    /// "user", "assistant" or "tool"
     pub role: String,
     pub content: String,
     pub timestamp: i64,
@@ -79,6 +144,117 @@ pub struct SessionMessage {
     pub model: Option<String>,
     #[serde(default)]
     pub images: Vec<ImageContent>,
+    /// Tool invocations requested in this turn (assistant messages)
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    /// ID of the tool call this message is the result of (tool messages)
+    #[serde(default)]
+    pub tool_use_id: Option<String>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+/// A single tool invocation inside an assistant turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub input: Value,
+}
+
+/// Tool names that run a shell command, across providers
+const SHELL_TOOLS: &[&str] = &[
+    "Bash", "shell", "local_shell", "exec_command", "container.exec", "run_shell_command",
+];
+
+/// Input fields that best describe a tool call, in order of preference
+const SUMMARY_FIELDS: &[&str] = &[
+    "file_path", "notebook_path", "path", "pattern", "url", "query", "description", "prompt",
+];
+
+/// Tools that write the file named by their input, across providers
+const FILE_EDIT_TOOLS: &[&str] = &[
+    "Edit", "Write", "MultiEdit", "NotebookEdit", "replace", "write_file",
+];
+
+/// Marker that opens an `apply_patch` envelope
+const PATCH_MARKER: &str = "*** Begin Patch";
+
+/// Longest tool input summary kept for analytics
+pub const TOOL_SUMMARY_CHARS: usize = 200;
+
+impl ToolCall {
+    /// The shell command this call runs, for Bash-like tools
+    pub fn shell_command(&self) -> Option<String> {
+        if !SHELL_TOOLS.contains(&self.name.as_str()) {
+            return None;
+        }
+        match self.input.get("command").or_else(|| self.input.get("cmd"))? {
+            Value::String(command) => Some(command.clone()),
+            Value::Array(argv) => {
+                let argv: Vec<&str> = argv.iter().filter_map(|a| a.as_str()).collect();
+                // `["bash", "-lc", "<script>"]` runs the script itself
+                match argv.as_slice() {
+                    [_, flag, script] if flag.starts_with('-') && flag.ends_with('c') => {
+                        Some(script.to_string())
+                    }
+                    _ => Some(argv.join(" ")),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Files this call writes: edit/write tools and `apply_patch` envelopes,
+    /// whether called directly or through a shell. Paths may be relative.
+    pub fn modified_files(&self) -> Vec<String> {
+        if FILE_EDIT_TOOLS.contains(&self.name.as_str()) {
+            return ["file_path", "notebook_path", "path"]
+                .iter()
+                .find_map(|field| self.input.get(field)?.as_str())
+                .map(|path| vec![path.to_string()])
+                .unwrap_or_default();
+        }
+
+        let patch = if self.name == "apply_patch" {
+            match &self.input {
+                Value::String(patch) => Some(patch.clone()),
+                input => ["input", "patch"]
+                    .iter()
+                    .find_map(|field| input.get(field)?.as_str().map(String::from)),
+            }
+        } else {
+            self.shell_command().filter(|command| command.contains(PATCH_MARKER))
+        };
+
+        patch
+            .map(|patch| crate::providers::codex::patch_file_paths(&patch))
+            .unwrap_or_default()
+    }
+
+    /// One-line description of the call's input (command, path, pattern, ...)
+    pub fn input_summary(&self) -> String {
+        let summary = self
+            .shell_command()
+            .or_else(|| {
+                SUMMARY_FIELDS
+                    .iter()
+                    .find_map(|field| self.input.get(field)?.as_str().map(String::from))
+            })
+            .unwrap_or_else(|| match &self.input {
+                Value::Null => String::new(),
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            });
+
+        let line = summary.lines().next().unwrap_or_default().trim();
+        match line.char_indices().nth(TOOL_SUMMARY_CHARS) {
+            Some((byte_index, _)) => format!("{}…", &line[..byte_index]),
+            None if summary.trim().lines().nth(1).is_some() => format!("{}…", line),
+            None => line.to_string(),
+        }
+    }
 }
 
 /// Image content in a message
@@ -160,6 +336,19 @@ pub struct JsonlLine {
     pub project: Option<String>,
     #[serde(rename = "requestId")]
     pub request_id: Option<String>,
+    #[serde(default)]
+    pub uuid: Option<String>,
+    /// Working directory of the CLI when the line was written
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Written by a sub-agent (Task tool) rather than the main conversation
+    #[serde(rename = "isSidechain", default)]
+    pub is_sidechain: bool,
+    #[serde(rename = "agentId", default)]
+    pub agent_id: Option<String>,
+    /// Kind of a `system` line (e.g. `stop_hook_summary`)
+    #[serde(default)]
+    pub subtype: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -249,11 +438,11 @@ pub fn parse_session_file(path: &Path) -> Result<Vec<JsonlLine>, std::io::Error>
 
         match serde_json::from_str::<JsonlLine>(&line) {
             Ok(parsed) => {
-                // Dedup by message.id + requestId (streaming chunks)
-                let msg_id = parsed.message.as_ref().and_then(|m| m.id.as_deref());
-                let req_id = parsed.request_id.as_deref();
-                if let Some(key) = dedup_key(msg_id, req_id) {
-                    if !seen.insert(key) {
+                // Dedup exact repeats by line UUID. Streaming chunks sharing a
+                // message.id + requestId are kept so every content block reaches
+                // the transcript; token accounting dedups them separately.
+                if let Some(ref uuid) = parsed.uuid {
+                    if !seen.insert(uuid.clone()) {
                         continue; // already seen
                     }
                 }
@@ -357,8 +546,28 @@ fn extract_content_ids(line: &JsonlLine, type_name: &str, id_field: &str) -> Vec
         .collect()
 }
 
+/// Extract (id, name) pairs of the tool_use blocks in a line's message content.
+fn extract_tool_uses(line: &JsonlLine) -> Vec<(String, String)> {
+    let content = line
+        .message
+        .as_ref()
+        .and_then(|msg| msg.content.as_ref())
+        .and_then(|c| c.as_array());
+
+    let Some(arr) = content else { return vec![] };
+
+    arr.iter()
+        .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+        .filter_map(|item| {
+            let id = item.get("id")?.as_str()?;
+            let name = item.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+            Some((id.to_string(), name.to_string()))
+        })
+        .collect()
+}
+
 /// Accumulated token counts and cost for a session.
-#[derive(Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct TokenAccumulator {
     input: i64,
     output: i64,
@@ -383,104 +592,254 @@ impl TokenAccumulator {
     }
 }
 
+/// Tools that stop and wait for the user's answer as soon as they are called
+const INTERACTIVE_TOOLS: &[&str] = &["AskUserQuestion", "ExitPlanMode"];
+
+/// Tools that run without a permission prompt, so a long wait on them is just work
+const AUTO_APPROVED_TOOLS: &[&str] = &[
+    "Read", "Glob", "Grep", "LS", "TodoWrite", "Task", "BashOutput", "KillShell",
+];
+
+/// A permission-gated tool call left unanswered this long is assumed to be
+/// showing a permission prompt (the transcript records nothing until it is answered).
+pub const PERMISSION_PROMPT_IDLE: Duration = Duration::from_secs(20);
+
+/// An assistant turn ending on a question is taken as waiting for the user once
+/// the file has been quiet this long, unless the CLI already logged the end of the turn.
+pub const QUESTION_REPLY_IDLE: Duration = Duration::from_secs(20);
+
+/// `system` line subtypes the CLI logs once an assistant turn has finished
+const TURN_END_SUBTYPES: &[&str] = &["stop_hook_summary", "turn_duration"];
+
+/// A session still waiting on the user after this long is treated as abandoned.
+pub const NEEDS_INPUT_STALE: Duration = Duration::from_secs(2 * 60 * 60);
+
 /// Determine session status from accumulated indicators.
 /// The watcher may later override Completed -> Active based on file modification time.
 fn determine_status(
     has_error: bool,
-    has_pending_tools: bool,
+    pending_tools: &HashMap<String, String>,
     last_message_type: Option<&str>,
     any_assistant_seen: bool,
+    awaiting_reply: bool,
 ) -> SessionStatus {
+    let asking_user = pending_tools
+        .values()
+        .any(|name| INTERACTIVE_TOOLS.contains(&name.as_str()));
+
     if has_error {
         SessionStatus::Error
-    } else if has_pending_tools || (last_message_type == Some("user") && !any_assistant_seen) {
+    } else if asking_user || (last_message_type == Some("assistant") && awaiting_reply) {
+        SessionStatus::NeedsInput
+    } else if !pending_tools.is_empty() || (last_message_type == Some("user") && !any_assistant_seen) {
         SessionStatus::Active
     } else {
         SessionStatus::Completed
     }
 }
 
-/// Extract session metadata from parsed JSONL
-pub fn extract_session_metadata(
-    session_id: &str,
-    project_path: &str,
-    lines: &[JsonlLine],
-) -> Session {
-    let project_name = extract_project_name(project_path);
-
-    let mut first_prompt: Option<String> = None;
-    let mut started_at: i64 = 0;
-    let mut last_active_at: i64 = 0;
-    let mut last_human_message_at: i64 = 0;
-    let mut message_count: i32 = 0;
-    let mut tokens = TokenAccumulator::default();
-    let mut model: Option<String> = None;
-    let mut seen_keys: HashSet<String> = HashSet::new();
-    let mut any_assistant_seen = false;
-    let mut last_message_type: Option<String> = None;
-    let mut has_error = false;
-    let mut pending_tool_use_ids: HashSet<String> = HashSet::new();
+/// Keys recorded by earlier reads of a session file: line UUIDs and the
+/// message+request keys whose usage was counted. They are stored apart from
+/// the cursor, so saving it costs the same however long the file grows.
+/// History re-logged after a resume or compaction can repeat a key far from
+/// where it first appeared, so they cover the whole file.
+pub trait KeyHistory {
+    /// Whether the key with this digest was recorded
+    fn contains(&self, digest: u64) -> bool;
+}
+
+/// No history, as for a parse from the start of the file
+impl KeyHistory for () {
+    fn contains(&self, _digest: u64) -> bool {
+        false
+    }
+}
+
+/// FNV-1a digest of a key (stable across runs, unlike the std hasher)
+fn key_digest(key: &str) -> u64 {
+    key.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether an assistant turn ends by asking the user a question
+fn ends_with_question(line: &JsonlLine) -> Option<bool> {
+    let content = line.message.as_ref()?.content.as_ref()?.as_array()?;
+    let text = content
+        .iter()
+        .rev()
+        .find(|block| block.get("type").and_then(|t| t.as_str()) == Some("text"))?
+        .get("text")?
+        .as_str()?
+        .trim_end();
+    Some(text.ends_with('?') || text.ends_with('\u{FF1F}'))
+}
+
+/// File name prefix of the files Claude Code writes sub-agent transcripts to
+pub const AGENT_FILE_PREFIX: &str = "agent-";
+
+/// Session ID for a sub-agent whose turns are logged inside its parent's file.
+/// Older CLI versions did not record an agent ID: those share one session per parent.
+fn inline_agent_session_id(parent_session_id: &str, line: &JsonlLine) -> String {
+    match line.agent_id.as_deref().filter(|id| !id.is_empty()) {
+        Some(agent_id) => format!("{}{}", AGENT_FILE_PREFIX, agent_id),
+        None => format!("{}-sidechain", parent_session_id),
+    }
+}
+
+/// Running session metadata built up one JSONL line at a time.
+/// Serializable so a file cursor can resume where the last read stopped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionAccumulator {
+    /// Directory the session was started in (first recorded `cwd`)
+    cwd: Option<String>,
+    first_prompt: Option<String>,
+    started_at: i64,
+    last_active_at: i64,
+    last_human_message_at: i64,
+    message_count: i32,
+    tokens: TokenAccumulator,
+    model: Option<String>,
+    /// Digests of the keys first seen by the current read, recorded with the
+    /// cursor once it is saved (repeated lines and chunks are skipped)
+    #[serde(skip)]
+    new_keys: HashSet<u64>,
+    any_assistant_seen: bool,
+    last_message_type: Option<String>,
+    has_error: bool,
+    /// Unanswered tool calls: tool_use ID -> tool name
+    pending_tools: HashMap<String, String>,
+    /// The last assistant turn ended on a question to the user
+    awaiting_reply: bool,
+    /// The CLI logged the end of the turn after the last message
+    turn_ended: bool,
+    /// `sessionId` recorded on the lines (a sub-agent file records its parent's)
+    logged_session_id: Option<String>,
+    /// Sub-agent turns logged inline in this file, keyed by agent session ID
+    sidechains: BTreeMap<String, SessionAccumulator>,
+}
+
+impl SessionAccumulator {
+    /// Record a key. Returns false if this read or an earlier one saw it already.
+    fn insert_key(&mut self, key: &str, history: &dyn KeyHistory) -> bool {
+        let digest = key_digest(key);
+        if self.new_keys.contains(&digest) || history.contains(digest) {
+            return false;
+        }
+        self.new_keys.insert(digest)
+    }
+
+    /// Keys first seen by this read, here and in inline sub-agents, for the
+    /// caller to record with the cursor
+    pub fn take_new_keys(&mut self) -> Vec<u64> {
+        let mut keys: Vec<u64> = self.new_keys.drain().collect();
+        for sidechain in self.sidechains.values_mut() {
+            keys.extend(sidechain.new_keys.drain());
+        }
+        keys
+    }
+
+    /// Record the line's UUID. Returns false if the line was already ingested.
+    pub fn accept(&mut self, line: &JsonlLine, history: &dyn KeyHistory) -> bool {
+        match line.uuid {
+            Some(ref uuid) => self.insert_key(uuid, history),
+            None => true,
+        }
+    }
+
+    /// Fold a single line into the running metadata, with no earlier reads.
+    #[cfg(test)]
+    pub fn push(&mut self, line: &JsonlLine) {
+        self.push_line(line, &());
+    }
+
+    /// Fold a single line into the running metadata. Usage already counted by
+    /// this read or one recorded in `history` is not counted again.
+    pub fn push_line(&mut self, line: &JsonlLine, history: &dyn KeyHistory) {
+        if self.cwd.is_none() {
+            self.cwd = line.cwd.as_deref().and_then(non_empty_trimmed);
+        }
+        if self.logged_session_id.is_none() {
+            self.logged_session_id = line.session_id.clone();
+        }
 
-    for line in lines {
         let ts = line.timestamp.as_ref().and_then(parse_timestamp);
         if let Some(ts) = ts {
-            if started_at == 0 || ts < started_at {
-                started_at = ts;
+            if self.started_at == 0 || ts < self.started_at {
+                self.started_at = ts;
             }
-            if ts > last_active_at {
-                last_active_at = ts;
+            if ts > self.last_active_at {
+                self.last_active_at = ts;
             }
         }
 
         match line.message_type.as_str() {
             "user" => {
-                message_count += 1;
-                last_message_type = Some("user".to_string());
+                self.message_count += 1;
+                self.last_message_type = Some("user".to_string());
 
                 if let Some(ts) = ts {
-                    if ts > last_human_message_at {
-                        last_human_message_at = ts;
+                    if ts > self.last_human_message_at {
+                        self.last_human_message_at = ts;
                     }
                 }
-                if first_prompt.is_none() {
-                    first_prompt = extract_user_prompt(line);
+                if self.first_prompt.is_none() {
+                    self.first_prompt = extract_user_prompt(line);
                 }
 
+                self.awaiting_reply = false;
+                self.turn_ended = false;
                 for id in extract_content_ids(line, "tool_result", "tool_use_id") {
-                    pending_tool_use_ids.remove(&id);
+                    self.pending_tools.remove(&id);
                 }
             }
             "assistant" => {
-                message_count += 1;
-                last_message_type = Some("assistant".to_string());
+                self.last_message_type = Some("assistant".to_string());
+                self.any_assistant_seen = true;
+                self.turn_ended = false;
 
-                let Some(ref msg) = line.message else { continue };
+                let Some(ref msg) = line.message else {
+                    self.message_count += 1;
+                    return;
+                };
 
-                if model.is_none() {
-                    model = msg.model.clone();
+                if self.model.is_none() {
+                    self.model = msg.model.clone();
                 }
 
-                for id in extract_content_ids(line, "tool_use", "id") {
-                    pending_tool_use_ids.insert(id);
+                let tool_uses = extract_tool_uses(line);
+                if !tool_uses.is_empty() {
+                    self.awaiting_reply = false;
+                    self.pending_tools.extend(tool_uses);
+                } else if let Some(question) = ends_with_question(line) {
+                    self.awaiting_reply = question;
                 }
 
-                // Accumulate token usage (deduplicated by message+request ID)
-                if let Some(ref usage) = msg.usage {
-                    let is_new = match dedup_key(msg.id.as_deref(), line.request_id.as_deref()) {
-                        Some(key) => seen_keys.insert(key),
-                        None => true, // older logs without IDs -- count each line
-                    };
-                    if is_new {
-                        tokens.add(usage, msg.model.as_deref().unwrap_or("sonnet"));
+                // Streaming chunks of one response share message+request ID:
+                // count the message and its token usage only once.
+                let is_new = match dedup_key(msg.id.as_deref(), line.request_id.as_deref()) {
+                    Some(key) => self.insert_key(&key, history),
+                    None => true, // older logs without IDs -- count each line
+                };
+                if is_new {
+                    self.message_count += 1;
+                    if let Some(ref usage) = msg.usage {
+                        self.tokens.add(usage, msg.model.as_deref().unwrap_or("sonnet"));
                     }
                 }
-
-                any_assistant_seen = true;
             }
             "system" => {
+                if line
+                    .subtype
+                    .as_deref()
+                    .is_some_and(|subtype| TURN_END_SUBTYPES.contains(&subtype))
+                {
+                    self.turn_ended = true;
+                }
                 if let Some(ref content) = line.content {
                     if content.to_string().contains("error") {
-                        has_error = true;
+                        self.has_error = true;
                     }
                 }
             }
@@ -488,48 +847,403 @@ pub fn extract_session_metadata(
         }
     }
 
-    if last_human_message_at == 0 {
-        last_human_message_at = last_active_at;
-    }
-
-    let status = determine_status(
-        has_error,
-        !pending_tool_use_ids.is_empty(),
-        last_message_type.as_deref(),
-        any_assistant_seen,
-    );
-
-    Session {
-        session_id: session_id.to_string(),
-        project_path: project_path.to_string(),
-        project_name,
-        first_prompt,
-        label: None,
-        tags: vec![],
-        started_at,
-        last_active_at,
-        last_human_message_at,
-        message_count,
-        total_tokens: tokens.total,
-        total_cost_usd: tokens.cost_usd,
-        input_tokens: tokens.input,
-        output_tokens: tokens.output,
-        cache_read_tokens: tokens.cache_read,
-        cache_write_tokens: tokens.cache_write,
-        model,
-        status,
-        provider: crate::providers::ProviderId::Claude, // Default to Claude
+    /// Fold the lines of a main session file, routing sub-agent turns logged
+    /// inline into per-agent accumulators. Returns the main-thread lines and
+    /// the sub-agent lines grouped by agent session ID.
+    pub fn push_main_lines(
+        &mut self,
+        session_id: &str,
+        lines: Vec<JsonlLine>,
+        history: &dyn KeyHistory,
+    ) -> (Vec<JsonlLine>, BTreeMap<String, Vec<JsonlLine>>) {
+        let mut main_lines = Vec::new();
+        let mut agent_lines: BTreeMap<String, Vec<JsonlLine>> = BTreeMap::new();
+
+        for line in lines {
+            if line.is_sidechain {
+                let agent_session_id = inline_agent_session_id(session_id, &line);
+                self.sidechains
+                    .entry(agent_session_id.clone())
+                    .or_default()
+                    .push_line(&line, history);
+                agent_lines.entry(agent_session_id).or_default().push(line);
+            } else {
+                self.push_line(&line, history);
+                main_lines.push(line);
+            }
+        }
+
+        (main_lines, agent_lines)
+    }
+
+    /// Accumulated state of a sub-agent logged inline in this file
+    pub fn sidechain(&self, agent_session_id: &str) -> Option<&SessionAccumulator> {
+        self.sidechains.get(agent_session_id)
+    }
+
+    /// Set the logged session ID on a cursor saved before it was recorded
+    pub fn backfill_logged_session_id(&mut self, logged: Option<String>) {
+        if self.logged_session_id.is_none() {
+            self.logged_session_id = logged;
+        }
+    }
+
+    /// The parent session a sub-agent file belongs to, if it is one
+    pub fn parent_session_id(&self, session_id: &str) -> Option<String> {
+        self.logged_session_id
+            .clone()
+            .filter(|logged| !logged.is_empty() && logged != session_id)
+    }
+
+    /// Whether a permission-gated tool call has gone unanswered for `idle`,
+    /// which is how a pending permission prompt shows up in the transcript.
+    pub fn awaiting_permission(&self, idle: Duration) -> bool {
+        idle >= PERMISSION_PROMPT_IDLE
+            && self
+                .pending_tools
+                .values()
+                .any(|name| !AUTO_APPROVED_TOOLS.contains(&name.as_str()))
+    }
+
+    /// Whether the last assistant turn asked the user a question and has
+    /// finished: the CLI logged the end of the turn or the file has been quiet for `idle`.
+    pub fn awaiting_reply(&self, idle: Duration) -> bool {
+        self.awaiting_reply && (self.turn_ended || idle >= QUESTION_REPLY_IDLE)
     }
+
+    /// Build the session summary from the state accumulated so far.
+    /// `fallback_project_path` is called for the path until a line recording `cwd` has been seen.
+    pub fn to_session(
+        &self,
+        session_id: &str,
+        fallback_project_path: impl FnOnce() -> String,
+    ) -> Session {
+        let project_path = self.cwd.clone().unwrap_or_else(fallback_project_path);
+        let project_path = project_path.as_str();
+        let last_human_message_at = if self.last_human_message_at == 0 {
+            self.last_active_at
+        } else {
+            self.last_human_message_at
+        };
+
+        let status = determine_status(
+            self.has_error,
+            &self.pending_tools,
+            self.last_message_type.as_deref(),
+            self.any_assistant_seen,
+            self.awaiting_reply(Duration::ZERO),
+        );
+
+        Session {
+            session_id: session_id.to_string(),
+            project_path: project_path.to_string(),
+            project_name: extract_project_name(project_path),
+            first_prompt: self.first_prompt.clone(),
+            label: None,
+            tags: vec![],
+            started_at: self.started_at,
+            last_active_at: self.last_active_at,
+            last_human_message_at,
+            message_count: self.message_count,
+            total_tokens: self.tokens.total,
+            total_cost_usd: self.tokens.cost_usd,
+            input_tokens: self.tokens.input,
+            output_tokens: self.tokens.output,
+            cache_read_tokens: self.tokens.cache_read,
+            cache_write_tokens: self.tokens.cache_write,
+            model: self.model.clone(),
+            status,
+            provider: crate::providers::ProviderId::Claude, // Default to Claude
+            parent_session_id: None,
+            pinned: false,
+            notes: None,
+            archived: false,
+        }
+    }
+}
+
+// ============================================================================
+// Incremental (tail) parsing
+// ============================================================================
+
+/// Lines scanned by `read_first_field` before giving up.
+const CWD_SCAN_LINES: usize = 50;
+
+/// Read the first non-empty string recorded under `key` in a session file.
+fn read_first_field(path: &Path, key: &str) -> Option<String> {
+    let reader = BufReader::new(File::open(path).ok()?);
+    reader
+        .lines()
+        .take(CWD_SCAN_LINES)
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Value>(&line).ok())
+        .find_map(|value| {
+            value
+                .get(key)
+                .and_then(|field| field.as_str())
+                .and_then(non_empty_trimmed)
+        })
+}
+
+/// Read the first `cwd` recorded in a session file.
+pub fn read_session_cwd(path: &Path) -> Option<String> {
+    read_first_field(path, "cwd")
+}
+
+/// Read the first `sessionId` recorded in a session file.
+pub fn read_logged_session_id(path: &Path) -> Option<String> {
+    read_first_field(path, "sessionId")
+}
+
+/// Only this many bytes of the first line are kept as the file fingerprint.
+const HEAD_FINGERPRINT_BYTES: usize = 256;
+
+/// Position in an append-only session file plus the metadata accumulated up to it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionCursor {
+    /// Byte offset just past the last complete line that was processed
+    pub offset: u64,
+    /// Prefix of the file's first line, used to detect a rotated or rewritten file
+    pub head: String,
+    pub state: SessionAccumulator,
+}
+
+impl SessionCursor {
+    /// True if the file still looks like the one the cursor was built from:
+    /// it has not shrunk below the offset and its first line is unchanged.
+    pub fn matches_file(&self, path: &Path) -> bool {
+        let Ok(meta) = std::fs::metadata(path) else {
+            return false;
+        };
+        meta.len() >= self.offset && read_file_head(path).is_ok_and(|head| head == self.head)
+    }
+}
+
+/// Read the fingerprint of a session file (a prefix of its first line).
+pub fn read_file_head(path: &Path) -> Result<String, std::io::Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut first_line = Vec::new();
+    reader.read_until(b'\n', &mut first_line)?;
+    first_line.truncate(HEAD_FINGERPRINT_BYTES);
+    Ok(String::from_utf8_lossy(&first_line).trim_end().to_string())
+}
+
+/// Parse the complete lines appended after `offset`.
+/// A trailing line without a newline is still being written and is left for the next read.
+/// Returns the parsed lines and the offset just past the last complete line.
+pub fn read_session_lines_from(
+    path: &Path,
+    offset: u64,
+) -> Result<(Vec<JsonlLine>, u64), std::io::Error> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut reader = BufReader::new(file);
+
+    let mut lines = Vec::new();
+    let mut position = offset;
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 || buf.last() != Some(&b'\n') {
+            break;
+        }
+        position += read as u64;
+
+        let text = String::from_utf8_lossy(&buf);
+        if text.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<JsonlLine>(&text) {
+            Ok(parsed) => lines.push(parsed),
+            Err(e) => tracing::warn!("Failed to parse JSONL line: {}", e),
+        }
+    }
+
+    Ok((lines, position))
+}
+
+// ============================================================================
+// Transcript normalization
+// ============================================================================
+
+/// Tool output beyond this many characters is truncated before storage.
+pub const MAX_TOOL_OUTPUT_CHARS: usize = 8_000;
+
+/// Truncate `text` to at most `max_chars` characters (UTF-8 safe).
+pub fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((byte_index, _)) => format!("{}\n... [truncated]", &text[..byte_index]),
+        None => text.to_string(),
+    }
+}
+
+/// Prompt-side token count of a turn (fresh input plus cache reads and writes).
+fn prompt_tokens(usage: &TokenUsage) -> i64 {
+    usage.input_tokens + usage.cache_read_input_tokens + usage.cache_creation_input_tokens
+}
+
+/// Collect the text of every `text` block in a content array, skipping IDE context.
+fn collect_text_blocks(items: &[Value]) -> String {
+    items
+        .iter()
+        .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("text"))
+        .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+        .filter(|text| !(text.starts_with('<') && text.contains("ide_")))
+        .filter_map(non_empty_trimmed)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Build a `tool` message from a `tool_result` content block.
+fn tool_result_message(item: &Value, timestamp: i64) -> SessionMessage {
+    let output = match item.get("content") {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(blocks)) => collect_text_blocks(blocks),
+        _ => String::new(),
+    };
+
+    SessionMessage {
+        role: "tool".to_string(),
+        content: truncate_chars(&output, MAX_TOOL_OUTPUT_CHARS),
+        timestamp,
+        tool_use_id: item
+            .get("tool_use_id")
+            .and_then(|id| id.as_str())
+            .map(|s| s.to_string()),
+        is_error: item.get("is_error").and_then(|e| e.as_bool()).unwrap_or(false),
+        ..Default::default()
+    }
+}
+
+/// Append user text and tool results from a `user` line.
+fn push_user_messages(messages: &mut Vec<SessionMessage>, line: &JsonlLine, timestamp: i64) {
+    let content = line
+        .message
+        .as_ref()
+        .and_then(|msg| msg.content.as_ref())
+        .or(line.content.as_ref());
+
+    let text = match content {
+        Some(Value::Array(items)) => {
+            for item in items {
+                if item.get("type").and_then(|t| t.as_str()) == Some("tool_result") {
+                    messages.push(tool_result_message(item, timestamp));
+                }
+            }
+            collect_text_blocks(items)
+        }
+        Some(other) => extract_text_from_content(other).unwrap_or_default(),
+        None => String::new(),
+    };
+
+    if !text.is_empty() {
+        messages.push(SessionMessage {
+            role: "user".to_string(),
+            content: text,
+            timestamp,
+            ..Default::default()
+        });
+    }
+}
+
+/// Split assistant content blocks into joined text and tool calls.
+fn split_assistant_content(content: Option<&Value>) -> (String, Vec<ToolCall>) {
+    let Some(Value::Array(items)) = content else {
+        let text = content.and_then(extract_text_from_content).unwrap_or_default();
+        return (text, Vec::new());
+    };
+
+    let tool_calls = items
+        .iter()
+        .filter(|item| item.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+        .map(|item| ToolCall {
+            id: item.get("id").and_then(|v| v.as_str()).map(|s| s.to_string()),
+            name: item
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            input: item.get("input").cloned().unwrap_or(Value::Null),
+        })
+        .collect();
+
+    (collect_text_blocks(items), tool_calls)
+}
+
+/// Append an assistant turn, merging streaming chunks that share a message ID.
+fn push_assistant_message(messages: &mut Vec<SessionMessage>, line: &JsonlLine, timestamp: i64) {
+    let Some(ref msg) = line.message else { return };
+    let (text, tool_calls) = split_assistant_content(msg.content.as_ref());
+
+    let chunk = SessionMessage {
+        id: msg.id.clone(),
+        role: "assistant".to_string(),
+        content: text,
+        timestamp,
+        tokens_in: msg.usage.as_ref().map(prompt_tokens),
+        tokens_out: msg.usage.as_ref().map(|u| u.output_tokens),
+        model: msg.model.clone(),
+        tool_calls,
+        ..Default::default()
+    };
+
+    match messages.last_mut() {
+        Some(last) if last.continues_with(&chunk) => last.absorb(chunk),
+        _ => messages.push(chunk),
+    }
+}
+
+impl SessionMessage {
+    /// True if `chunk` is a later streaming chunk of this assistant response.
+    pub fn continues_with(&self, chunk: &SessionMessage) -> bool {
+        self.role == "assistant"
+            && chunk.role == "assistant"
+            && self.id.is_some()
+            && self.id == chunk.id
+    }
+
+    /// Merge a streaming chunk's text and tool calls into this message.
+    pub fn absorb(&mut self, chunk: SessionMessage) {
+        if !chunk.content.is_empty() {
+            if !self.content.is_empty() {
+                self.content.push_str("\n\n");
+            }
+            self.content.push_str(&chunk.content);
+        }
+        self.tool_calls.extend(chunk.tool_calls);
+    }
+}
+
+/// Normalize parsed JSONL lines into the user/assistant/tool transcript.
+pub fn extract_session_messages(lines: &[JsonlLine]) -> Vec<SessionMessage> {
+    let mut messages: Vec<SessionMessage> = Vec::new();
+
+    for line in lines {
+        let timestamp = line.timestamp.as_ref().and_then(parse_timestamp).unwrap_or(0);
+        match line.message_type.as_str() {
+            "user" => push_user_messages(&mut messages, line, timestamp),
+            "assistant" => push_assistant_message(&mut messages, line, timestamp),
+            _ => {}
+        }
+    }
+
+    // Drop thinking-only assistant turns that carry neither text nor tool calls
+    messages.retain(|m| m.role != "assistant" || !m.content.is_empty() || !m.tool_calls.is_empty());
+    messages
 }
 
 /// Check if a session file is currently active (modified within the last 60 seconds).
 /// The generous threshold accounts for long-running CLI operations.
 pub fn is_session_active(path: &Path) -> bool {
-    std::fs::metadata(path)
-        .and_then(|m| m.modified())
-        .ok()
-        .map(|t| t.elapsed().unwrap_or_default().as_secs() < 60)
-        .unwrap_or(false)
+    file_idle_time(path).is_some_and(|idle| idle.as_secs() < 60)
+}
+
+/// Time since a file was last modified
+pub fn file_idle_time(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(modified.elapsed().unwrap_or_default())
 }
 
 /// Extract project name from path
@@ -539,3 +1253,193 @@ pub fn extract_project_name(project_path: &str) -> String {
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| project_path.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(json: serde_json::Value) -> JsonlLine {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn user(text: &str) -> JsonlLine {
+        line(serde_json::json!({
+            "type": "user",
+            "message": { "role": "user", "content": text },
+        }))
+    }
+
+    fn assistant(content: serde_json::Value) -> JsonlLine {
+        line(serde_json::json!({
+            "type": "assistant",
+            "message": { "role": "assistant", "content": content },
+        }))
+    }
+
+    fn text(text: &str) -> serde_json::Value {
+        serde_json::json!([{ "type": "text", "text": text }])
+    }
+
+    fn tool_use(id: &str, name: &str) -> serde_json::Value {
+        serde_json::json!([{ "type": "tool_use", "id": id, "name": name, "input": {} }])
+    }
+
+    fn tool_result(id: &str) -> JsonlLine {
+        line(serde_json::json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{ "type": "tool_result", "tool_use_id": id }],
+            },
+        }))
+    }
+
+    fn status(state: &SessionAccumulator) -> SessionStatus {
+        state.to_session("s", String::new).status
+    }
+
+    #[test]
+    fn test_prompt_and_reply() {
+        let mut state = SessionAccumulator::default();
+        state.push(&user("Fix the build"));
+        assert_eq!(status(&state), SessionStatus::Active);
+
+        state.push(&assistant(text("Done.")));
+        assert_eq!(status(&state), SessionStatus::Completed);
+    }
+
+    #[test]
+    fn test_trailing_question_waits_for_turn_end() {
+        let mut state = SessionAccumulator::default();
+        state.push(&user("Refactor it"));
+        state.push(&assistant(text("Should I keep the old API?")));
+
+        // The turn may still be streaming
+        assert_eq!(status(&state), SessionStatus::Completed);
+        assert!(!state.awaiting_reply(Duration::ZERO));
+        assert!(state.awaiting_reply(QUESTION_REPLY_IDLE));
+
+        state.push(&line(serde_json::json!({ "type": "system", "subtype": "stop_hook_summary" })));
+        assert_eq!(status(&state), SessionStatus::NeedsInput);
+
+        state.push(&user("Yes"));
+        assert_eq!(status(&state), SessionStatus::Completed);
+        assert!(!state.awaiting_reply(QUESTION_REPLY_IDLE));
+    }
+
+    #[test]
+    fn test_pending_tools() {
+        let mut state = SessionAccumulator::default();
+        state.push(&user("Clean up"));
+        state.push(&assistant(tool_use("t1", "Bash")));
+        assert_eq!(status(&state), SessionStatus::Active);
+        assert!(!state.awaiting_permission(Duration::from_secs(1)));
+        assert!(state.awaiting_permission(PERMISSION_PROMPT_IDLE));
+
+        state.push(&tool_result("t1"));
+        state.push(&assistant(tool_use("t2", "Read")));
+        assert!(!state.awaiting_permission(PERMISSION_PROMPT_IDLE));
+
+        state.push(&tool_result("t2"));
+        state.push(&assistant(tool_use("t3", "AskUserQuestion")));
+        assert_eq!(status(&state), SessionStatus::NeedsInput);
+
+        state.push(&tool_result("t3"));
+        state.push(&assistant(text("All clean.")));
+        assert_eq!(status(&state), SessionStatus::Completed);
+    }
+
+    #[test]
+    fn test_system_error() {
+        let mut state = SessionAccumulator::default();
+        state.push(&user("Go"));
+        state.push(&line(serde_json::json!({ "type": "system", "content": "API error" })));
+        assert_eq!(status(&state), SessionStatus::Error);
+    }
+
+    #[test]
+    fn test_transcript_pairs_tool_calls_and_results() {
+        let mut call = assistant(tool_use("t1", "Bash"));
+        call.message.as_mut().unwrap().id = Some("m1".to_string());
+        let mut chunk = assistant(text("Running it."));
+        chunk.message.as_mut().unwrap().id = Some("m1".to_string());
+        let failed = line(serde_json::json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [
+                    {
+                        "type": "tool_result",
+                        "tool_use_id": "t1",
+                        "content": [{ "type": "text", "text": "exit 1" }],
+                        "is_error": true,
+                    },
+                    { "type": "text", "text": "That failed" },
+                ],
+            },
+        }));
+
+        let messages = extract_session_messages(&[user("Test it"), call, chunk, failed]);
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["user", "assistant", "tool", "user"]);
+
+        // Streaming chunks of one response merge into a single turn
+        assert_eq!(messages[1].content, "Running it.");
+        assert_eq!(messages[1].tool_calls.len(), 1);
+        assert_eq!(messages[1].tool_calls[0].id.as_deref(), Some("t1"));
+
+        assert_eq!(messages[2].tool_use_id.as_deref(), Some("t1"));
+        assert_eq!(messages[2].content, "exit 1");
+        assert!(messages[2].is_error);
+        assert_eq!(messages[3].content, "That failed");
+    }
+
+    #[test]
+    fn test_transcript_truncates_tool_output() {
+        let output = "\u{e9}".repeat(MAX_TOOL_OUTPUT_CHARS + 1);
+        let result = line(serde_json::json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{ "type": "tool_result", "tool_use_id": "t1", "content": output }],
+            },
+        }));
+
+        let messages = extract_session_messages(&[result]);
+        assert!(!messages[0].is_error);
+        assert_eq!(
+            messages[0].content,
+            format!("{}\n... [truncated]", "\u{e9}".repeat(MAX_TOOL_OUTPUT_CHARS))
+        );
+
+        assert_eq!(truncate_chars("\u{e9}t\u{e9}", 2), "\u{e9}t\n... [truncated]");
+        assert_eq!(truncate_chars("short", 5), "short");
+    }
+
+    #[test]
+    fn test_parse_session_file_dedups_by_uuid() {
+        let chunk = |uuid: &str, text: &str| {
+            serde_json::json!({
+                "type": "assistant",
+                "uuid": uuid,
+                "requestId": "r1",
+                "message": {
+                    "id": "m1",
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": text }],
+                },
+            })
+            .to_string()
+        };
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let content = [chunk("a", "one"), chunk("b", "two"), chunk("a", "one")].join("\n");
+        std::io::Write::write_all(&mut file, content.as_bytes()).unwrap();
+
+        // The repeated line is dropped; both chunks of the response are kept
+        let lines = parse_session_file(file.path()).unwrap();
+        assert_eq!(lines.len(), 2);
+        let messages = extract_session_messages(&lines);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "one\n\ntwo");
+    }
+}
@@ -0,0 +1,285 @@
+// Task dependency graph - readiness, blockers, skip propagation and cycle checks
+
+use crate::database::{DependencyMode, Task, TaskStatus};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Where a task stands with respect to its dependencies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Readiness {
+    /// Dependencies are satisfied
+    Ready,
+    /// Dependencies may still complete
+    Waiting,
+    /// Dependencies can no longer be satisfied
+    Unsatisfiable,
+}
+
+/// A dependency that keeps a task from starting
+#[derive(Debug, Clone, Serialize)]
+pub struct Blocker {
+    pub task_id: String,
+    /// None when the dependency was deleted
+    pub status: Option<TaskStatus>,
+    pub reason: String,
+}
+
+/// A task with its place in the dependency graph
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskGraphNode {
+    #[serde(flatten)]
+    pub task: Task,
+    pub readiness: Readiness,
+    pub blocked_by: Vec<Blocker>,
+}
+
+/// Edge from a dependency to the task that waits on it
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskGraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskGraph {
+    pub nodes: Vec<TaskGraphNode>,
+    pub edges: Vec<TaskGraphEdge>,
+}
+
+/// Tasks indexed by ID, with the set of tasks that can never run
+pub struct TaskIndex<'a> {
+    by_id: HashMap<&'a str, &'a Task>,
+    /// Failed or skipped tasks, and every task whose dependencies rule it out
+    doomed: HashSet<&'a str>,
+}
+
+/// A task that ended without completing; whatever requires it cannot run
+fn is_dead(status: &TaskStatus) -> bool {
+    matches!(status, TaskStatus::Failed | TaskStatus::Skipped | TaskStatus::Cancelled)
+}
+
+impl<'a> TaskIndex<'a> {
+    pub fn new(tasks: &'a [Task]) -> Self {
+        let by_id: HashMap<&str, &Task> = tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+        let mut doomed: HashSet<&str> = tasks
+            .iter()
+            .filter(|t| is_dead(&t.status))
+            .map(|t| t.id.as_str())
+            .collect();
+
+        // Propagate through the whole graph until nothing changes
+        loop {
+            let newly_doomed: Vec<&str> = tasks
+                .iter()
+                .filter(|t| t.status != TaskStatus::Completed && !doomed.contains(t.id.as_str()))
+                .filter(|t| {
+                    let lost = |dep: &String| {
+                        !by_id.contains_key(dep.as_str()) || doomed.contains(dep.as_str())
+                    };
+                    match t.dependency_mode {
+                        DependencyMode::All => t.depends_on.iter().any(lost),
+                        DependencyMode::Any => {
+                            !t.depends_on.is_empty() && t.depends_on.iter().all(lost)
+                        }
+                    }
+                })
+                .map(|t| t.id.as_str())
+                .collect();
+            if newly_doomed.is_empty() {
+                break;
+            }
+            doomed.extend(newly_doomed);
+        }
+
+        Self { by_id, doomed }
+    }
+
+    fn is_satisfied(&self, dep: &str) -> bool {
+        self.by_id
+            .get(dep)
+            .is_some_and(|t| t.status == TaskStatus::Completed)
+    }
+
+    pub fn readiness(&self, task: &Task) -> Readiness {
+        if task.depends_on.is_empty() {
+            return Readiness::Ready;
+        }
+        let satisfied = |dep: &String| self.is_satisfied(dep);
+        let ready = match task.dependency_mode {
+            DependencyMode::All => task.depends_on.iter().all(satisfied),
+            DependencyMode::Any => task.depends_on.iter().any(satisfied),
+        };
+
+        if ready {
+            Readiness::Ready
+        } else if self.doomed.contains(task.id.as_str()) {
+            Readiness::Unsatisfiable
+        } else {
+            Readiness::Waiting
+        }
+    }
+
+    /// Explain which dependencies keep a task from starting
+    pub fn blocked_by(&self, task: &Task) -> Vec<Blocker> {
+        if self.readiness(task) == Readiness::Ready {
+            return Vec::new();
+        }
+
+        task.depends_on
+            .iter()
+            .filter(|dep| !self.is_satisfied(dep))
+            .map(|dep| {
+                let status = self.by_id.get(dep.as_str()).map(|t| t.status.clone());
+                let reason = match &status {
+                    None => "dependency was deleted".to_string(),
+                    Some(TaskStatus::Failed) => "dependency failed".to_string(),
+                    Some(TaskStatus::Skipped) => "dependency was skipped".to_string(),
+                    Some(TaskStatus::Cancelled) => "dependency was cancelled".to_string(),
+                    Some(_) if self.doomed.contains(dep.as_str()) => {
+                        "dependency can never run".to_string()
+                    }
+                    Some(status) => format!("waiting for dependency ({})", status),
+                };
+                Blocker { task_id: dep.clone(), status, reason }
+            })
+            .collect()
+    }
+}
+
+/// Find the cycle that giving `task_id` the dependencies `depends_on` would
+/// create, as the path of task IDs from `task_id` back to itself.
+pub fn find_cycle(tasks: &[Task], task_id: &str, depends_on: &[String]) -> Option<Vec<String>> {
+    let edges: HashMap<&str, &[String]> = tasks
+        .iter()
+        .map(|t| (t.id.as_str(), t.depends_on.as_slice()))
+        .chain(std::iter::once((task_id, depends_on)))
+        .collect();
+
+    fn visit<'a>(
+        edges: &HashMap<&'a str, &'a [String]>,
+        node: &'a str,
+        target: &str,
+        path: &mut Vec<&'a str>,
+        visited: &mut HashSet<&'a str>,
+    ) -> bool {
+        if node == target {
+            return true;
+        }
+        if !visited.insert(node) {
+            return false;
+        }
+        path.push(node);
+        for dep in edges.get(node).copied().unwrap_or_default() {
+            if visit(edges, dep, target, path, visited) {
+                return true;
+            }
+        }
+        path.pop();
+        false
+    }
+
+    let mut visited = HashSet::new();
+    for dep in depends_on {
+        let mut path = Vec::new();
+        if visit(&edges, dep, task_id, &mut path, &mut visited) {
+            let mut cycle = vec![task_id.to_string()];
+            cycle.extend(path.into_iter().map(str::to_string));
+            cycle.push(task_id.to_string());
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+/// Build the dependency graph of the tasks `include` selects, plus the tasks
+/// they depend on, for visualization
+pub fn build_graph(tasks: &[Task], include: impl Fn(&Task) -> bool) -> TaskGraph {
+    let index = TaskIndex::new(tasks);
+
+    let included: Vec<&Task> = tasks.iter().filter(|t| include(t)).collect();
+    let shown: HashSet<&str> = included
+        .iter()
+        .flat_map(|t| std::iter::once(&t.id).chain(&t.depends_on))
+        .map(String::as_str)
+        .collect();
+
+    let edges = included
+        .iter()
+        .flat_map(|t| {
+            t.depends_on.iter().map(|dep| TaskGraphEdge {
+                from: dep.clone(),
+                to: t.id.clone(),
+            })
+        })
+        .collect();
+
+    let nodes = tasks
+        .iter()
+        .filter(|t| shown.contains(t.id.as_str()))
+        .map(|t| TaskGraphNode {
+            task: t.clone(),
+            readiness: index.readiness(t),
+            blocked_by: index.blocked_by(t),
+        })
+        .collect();
+
+    TaskGraph { nodes, edges }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, status: &str, depends_on: &[&str], mode: &str) -> Task {
+        let mut task = crate::database::test_task(id);
+        task.status = status.parse().unwrap();
+        task.depends_on = depends_on.iter().map(|d| d.to_string()).collect();
+        task.dependency_mode = mode.parse().unwrap();
+        task
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        let tasks = vec![
+            task("a", "queued", &["b"], "all"),
+            task("b", "queued", &["c"], "all"),
+            task("c", "queued", &[], "all"),
+        ];
+        assert_eq!(
+            find_cycle(&tasks, "c", &["a".to_string()]),
+            Some(vec!["c".to_string(), "a".to_string(), "b".to_string(), "c".to_string()])
+        );
+        assert_eq!(
+            find_cycle(&tasks, "c", &["c".to_string()]),
+            Some(vec!["c".to_string(), "c".to_string()])
+        );
+        assert_eq!(find_cycle(&tasks, "d", &["a".to_string()]), None);
+        assert_eq!(find_cycle(&tasks, "a", &["c".to_string()]), None);
+    }
+
+    #[test]
+    fn test_doomed_propagation() {
+        let tasks = vec![
+            task("failed", "failed", &[], "all"),
+            task("done", "completed", &[], "all"),
+            task("needs_failed", "queued", &["failed", "done"], "all"),
+            task("downstream", "queued", &["needs_failed"], "all"),
+            task("either", "queued", &["failed", "done"], "any"),
+            task("either_lost", "queued", &["failed", "missing"], "any"),
+            task("waiting", "queued", &["downstream", "either"], "any"),
+        ];
+        let index = TaskIndex::new(&tasks);
+        let readiness = |id: &str| index.readiness(tasks.iter().find(|t| t.id == id).unwrap());
+
+        assert_eq!(readiness("needs_failed"), Readiness::Unsatisfiable);
+        assert_eq!(readiness("downstream"), Readiness::Unsatisfiable);
+        assert_eq!(readiness("either"), Readiness::Ready);
+        assert_eq!(readiness("either_lost"), Readiness::Unsatisfiable);
+        assert_eq!(readiness("waiting"), Readiness::Waiting);
+
+        let blockers = index.blocked_by(tasks.iter().find(|t| t.id == "downstream").unwrap());
+        assert_eq!(blockers.len(), 1);
+        assert_eq!(blockers[0].reason, "dependency can never run");
+    }
+}
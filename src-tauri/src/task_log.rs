@@ -0,0 +1,200 @@
+// Task logs - one file per run holding its stdout and stderr lines in the order they arrived,
+// each tagged with the stream it came from
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory holding a task's run logs
+fn task_log_dir(task_id: &str) -> PathBuf {
+    crate::platform::get_alice_dir().join("task-logs").join(task_id)
+}
+
+/// Log file of one run of a task. Runs are told apart by their `task_runs`
+/// ID, which unlike the attempt number is never reused when a task is queued again.
+pub fn log_path(task_id: &str, run_id: i64) -> PathBuf {
+    task_log_dir(task_id).join(format!("{}.log", run_id))
+}
+
+/// Delete every log of a task
+pub fn remove_task_logs(task_id: &str) {
+    let dir = task_log_dir(task_id);
+    if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            tracing::warn!("Failed to remove logs of task {}: {}", task_id, e);
+        }
+    }
+}
+
+/// The output a log line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+    /// The verification command, its stdout and stderr combined
+    Verify,
+}
+
+impl LogStream {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+            LogStream::Verify => "verify",
+        }
+    }
+}
+
+/// Appends a run's output to its log file, a line at a time as `[stream] line`. A log that cannot be written is
+/// reported once and then skipped: losing the log must not fail the run.
+pub struct TaskLog {
+    file: Option<File>,
+}
+
+impl TaskLog {
+    /// A log that discards everything
+    pub fn disabled() -> Self {
+        Self { file: None }
+    }
+
+    pub fn create(path: &Path) -> Self {
+        let opened = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(path));
+
+        match opened {
+            Ok(file) => Self { file: Some(file) },
+            Err(e) => {
+                tracing::warn!("Failed to open task log {:?}: {}", path, e);
+                Self { file: None }
+            }
+        }
+    }
+
+    pub fn write_line(&mut self, stream: LogStream, line: &str) {
+        let Some(file) = self.file.as_mut() else { return };
+        if let Err(e) = writeln!(file, "[{}] {}", stream.as_str(), line) {
+            tracing::warn!("Failed to write task log, disabling it: {}", e);
+            self.file = None;
+        }
+    }
+}
+
+/// A slice of a run's log
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskLogChunk {
+    pub content: String,
+    /// Where the next read continues from
+    pub offset: u64,
+    /// The run has ended, so the log will not grow
+    pub finished: bool,
+}
+
+/// Read up to `max_bytes` of a log from `offset`, or its last `max_bytes` when
+/// no offset is given. A log not written yet reads as empty. A character cut
+/// by the end of the chunk is left for the next read.
+pub fn read_chunk(path: &Path, offset: Option<u64>, max_bytes: u64) -> std::io::Result<(String, u64)> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((String::new(), offset.unwrap_or(0)))
+        }
+        Err(e) => return Err(e),
+    };
+    let len = file.metadata()?.len();
+    let start = offset.unwrap_or_else(|| len.saturating_sub(max_bytes)).min(len);
+
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    file.take(max_bytes).read_to_end(&mut bytes)?;
+
+    // Reading from the tail may start inside a character
+    let skipped = if offset.is_none() {
+        bytes.iter().take_while(|b| is_continuation(**b)).count()
+    } else {
+        0
+    };
+    let end = bytes.len() - incomplete_tail(&bytes[skipped..]);
+    let next = start + end as u64;
+    Ok((String::from_utf8_lossy(&bytes[skipped..end]).into_owned(), next))
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Length of a UTF-8 sequence begun but not finished at the end of `bytes`
+fn incomplete_tail(bytes: &[u8]) -> usize {
+    let continuations = bytes.iter().rev().take(3).take_while(|b| is_continuation(**b)).count();
+    let Some(&lead) = bytes.len().checked_sub(continuations + 1).map(|i| &bytes[i]) else {
+        return 0;
+    };
+    let width = match lead {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return 0,
+    };
+    if continuations + 1 < width {
+        continuations + 1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_line_tags_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("runs").join("1.log");
+        let mut log = TaskLog::create(&path);
+        log.write_line(LogStream::Stdout, "{\"type\":\"result\"}");
+        log.write_line(LogStream::Stderr, "warning: slow");
+        log.write_line(LogStream::Verify, "$ cargo test");
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "[stdout] {\"type\":\"result\"}\n[stderr] warning: slow\n[verify] $ cargo test\n"
+        );
+    }
+
+    #[test]
+    fn test_read_chunk_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.log");
+        assert_eq!(read_chunk(&path, Some(7), 10).unwrap(), (String::new(), 7));
+
+        std::fs::write(&path, "line one\nline two\n").unwrap();
+        assert_eq!(read_chunk(&path, Some(0), 9).unwrap(), ("line one\n".to_string(), 9));
+        assert_eq!(read_chunk(&path, Some(9), 100).unwrap(), ("line two\n".to_string(), 18));
+        // Reading at or past the end returns nothing and stays at the end
+        assert_eq!(read_chunk(&path, Some(18), 100).unwrap(), (String::new(), 18));
+        assert_eq!(read_chunk(&path, Some(50), 100).unwrap(), (String::new(), 18));
+        // Without an offset, the tail
+        assert_eq!(read_chunk(&path, None, 9).unwrap(), ("line two\n".to_string(), 18));
+    }
+
+    #[test]
+    fn test_read_chunk_multibyte_boundaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("1.log");
+        // "é" is 2 bytes, "€" is 3 and "🦀" is 4
+        std::fs::write(&path, "aé€🦀").unwrap();
+
+        // A chunk ending inside a character leaves it for the next read
+        assert_eq!(read_chunk(&path, Some(0), 2).unwrap(), ("a".to_string(), 1));
+        assert_eq!(read_chunk(&path, Some(1), 4).unwrap(), ("é".to_string(), 3));
+        assert_eq!(read_chunk(&path, Some(3), 6).unwrap(), ("€".to_string(), 6));
+        assert_eq!(read_chunk(&path, Some(6), 4).unwrap(), ("🦀".to_string(), 10));
+
+        // A tail starting inside a character skips its remaining bytes
+        assert_eq!(read_chunk(&path, None, 6).unwrap(), ("🦀".to_string(), 10));
+        assert_eq!(read_chunk(&path, None, 8).unwrap(), ("€🦀".to_string(), 10));
+    }
+}
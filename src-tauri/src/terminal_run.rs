@@ -0,0 +1,233 @@
+// Terminal runs - follow a task launched in a terminal window through to its exit status
+//
+// The provider CLI runs inside a wrapper script kept beside the run's log: a
+// POSIX shell script, or a PowerShell one on Windows. The script records its
+// PID, copies the CLI's output into the log and, once the CLI returns, writes
+// its exit status to a file the queue watches for.
+
+use std::path::{Path, PathBuf};
+
+/// The wrapper script of one run and the files it leaves behind
+#[derive(Debug, Clone)]
+pub struct TerminalRun {
+    pub script: PathBuf,
+    /// Written with the CLI's exit code when it returns
+    pub exit_file: PathBuf,
+    /// Written with the script's PID when it starts
+    pub pid_file: PathBuf,
+    pub log: PathBuf,
+}
+
+/// Quote a word for a POSIX shell
+fn sh_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Quote a word for PowerShell
+fn ps_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', "''"))
+}
+
+/// Quote a word for cmd.exe, which starts PowerShell in some terminals
+fn cmd_quote(word: &str) -> String {
+    format!("\"{}\"", word.replace('"', "\"\""))
+}
+
+impl TerminalRun {
+    /// The wrapper files of the run logging to `log`
+    pub fn new(log: &Path) -> Self {
+        let script_ext = if cfg!(target_os = "windows") { "ps1" } else { "sh" };
+        Self {
+            script: log.with_extension(script_ext),
+            exit_file: log.with_extension("exit"),
+            pid_file: log.with_extension("pid"),
+            log: log.to_path_buf(),
+        }
+    }
+
+    /// Write the script that runs `command` with `args` in `working_dir`,
+    /// clearing what an earlier launch of the same run left behind.
+    pub fn write_script(
+        &self,
+        working_dir: Option<&str>,
+        env: &[(&str, &str)],
+        command: &str,
+        args: &[String],
+    ) -> Result<(), String> {
+        if let Some(dir) = self.script.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        let _ = std::fs::remove_file(&self.exit_file);
+        let _ = std::fs::remove_file(&self.pid_file);
+
+        let script = if cfg!(target_os = "windows") {
+            self.ps_script(working_dir, env, command, args)
+        } else {
+            self.sh_script(working_dir, env, command, args)
+        };
+        std::fs::write(&self.script, script)
+            .map_err(|e| format!("Failed to write {}: {}", self.script.display(), e))
+    }
+
+    fn sh_script(
+        &self,
+        working_dir: Option<&str>,
+        env: &[(&str, &str)],
+        command: &str,
+        args: &[String],
+    ) -> String {
+        let path = |p: &Path| sh_quote(&p.to_string_lossy());
+        let exit_tmp = self.exit_file.with_extension("exit.tmp");
+        let mut lines = vec![
+            "#!/bin/sh".to_string(),
+            // Lead a process group, so that stopping the run reaches everything it
+            // started. Shells in terminal windows usually see to this already.
+            // The marker stops a setsid that cannot help from re-running the script.
+            "if [ -z \"$ALICE_SETSID\" ] \\".to_string(),
+            "    && [ \"$(ps -o pgid= -p $$ | tr -d ' ')\" != \"$$\" ] \\".to_string(),
+            "    && command -v setsid >/dev/null 2>&1; then".to_string(),
+            "    ALICE_SETSID=1 exec setsid sh \"$0\"".to_string(),
+            "fi".to_string(),
+            format!("echo $$ > {}", path(&self.pid_file)),
+        ];
+        if let Some(dir) = working_dir {
+            lines.push(format!("cd {} || exit 1", sh_quote(dir)));
+        }
+        lines.extend(env.iter().map(|(name, value)| format!("export {}={}", name, sh_quote(value))));
+
+        let words: Vec<String> = std::iter::once(command)
+            .chain(args.iter().map(String::as_str))
+            .map(sh_quote)
+            .collect();
+        // The status is published only after tee has flushed the whole log
+        lines.push(format!(
+            "{{ {} 2>&1; echo $? > {}; }} | tee -a {}",
+            words.join(" "),
+            path(&exit_tmp),
+            path(&self.log)
+        ));
+        lines.push(format!("mv {} {}", path(&exit_tmp), path(&self.exit_file)));
+        lines.join("\n") + "\n"
+    }
+
+    fn ps_script(
+        &self,
+        working_dir: Option<&str>,
+        env: &[(&str, &str)],
+        command: &str,
+        args: &[String],
+    ) -> String {
+        let path = |p: &Path| ps_quote(&p.to_string_lossy());
+        let exit_tmp = self.exit_file.with_extension("exit.tmp");
+        let mut lines = vec![format!(
+            "Set-Content -LiteralPath {} -Value $PID -Encoding Ascii",
+            path(&self.pid_file)
+        )];
+        if let Some(dir) = working_dir {
+            lines.push(format!("Set-Location -LiteralPath {} -ErrorAction Stop", ps_quote(dir)));
+        }
+        lines.extend(
+            env.iter().map(|(name, value)| format!("$env:{} = {}", name, ps_quote(value))),
+        );
+
+        let words: Vec<String> = args.iter().map(|arg| ps_quote(arg)).collect();
+        lines.push(format!(
+            "& {} {} 2>&1 | ForEach-Object {{ $line = \"$_\"; $line; \
+             Add-Content -LiteralPath {} -Value $line -Encoding UTF8 }}",
+            ps_quote(command),
+            words.join(" "),
+            path(&self.log)
+        ));
+        // A command that could not be started leaves no exit code
+        lines.push("$code = if ($null -eq $LASTEXITCODE) { 1 } else { $LASTEXITCODE }".to_string());
+        lines.push(format!(
+            "Set-Content -LiteralPath {} -Value $code -Encoding Ascii",
+            path(&exit_tmp)
+        ));
+        lines.push(format!(
+            "Move-Item -LiteralPath {} -Destination {} -Force",
+            path(&exit_tmp),
+            path(&self.exit_file)
+        ));
+        lines.join("\r\n") + "\r\n"
+    }
+
+    /// The command and arguments that run the script in a terminal
+    pub fn launch_command(&self) -> (String, Vec<String>) {
+        let script = self.script.to_string_lossy();
+        if cfg!(target_os = "windows") {
+            let args = ["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"];
+            let mut args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            args.push(cmd_quote(&script));
+            ("powershell".to_string(), args)
+        } else {
+            ("sh".to_string(), vec![sh_quote(&script)])
+        }
+    }
+
+    /// The CLI's exit code, once it has returned
+    pub fn exit_code(&self) -> Option<i32> {
+        std::fs::read_to_string(&self.exit_file).ok()?.trim().parse().ok()
+    }
+
+    /// PID of the script, once it has started
+    pub fn pid(&self) -> Option<u32> {
+        std::fs::read_to_string(&self.pid_file).ok()?.trim().parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting() {
+        assert_eq!(sh_quote("plain"), "'plain'");
+        assert_eq!(sh_quote("it's $HOME"), "'it'\\''s $HOME'");
+        assert_eq!(ps_quote("it's $env:PATH"), "'it''s $env:PATH'");
+        assert_eq!(cmd_quote("C:\\Alice \"runs\"\\1.ps1"), "\"C:\\Alice \"\"runs\"\"\\1.ps1\"");
+    }
+
+    #[test]
+    fn test_ps_script_quoting() {
+        let run = TerminalRun::new(Path::new("C:/logs/it's/1.log"));
+        let args = ["-p".to_string(), "Fix Bob's `build` $now".to_string()];
+        let script = run.ps_script(Some("C:/work/o'brien"), &[("API_KEY", "k'1")], "claude", &args);
+
+        assert!(script.contains("Set-Location -LiteralPath 'C:/work/o''brien' -ErrorAction Stop"));
+        assert!(script.contains("$env:API_KEY = 'k''1'"));
+        assert!(script.contains("& 'claude' '-p' 'Fix Bob''s `build` $now' 2>&1"));
+        assert!(script.contains("Add-Content -LiteralPath 'C:/logs/it''s/1.log'"));
+        assert!(script.ends_with("\r\n"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sh_script_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let run = TerminalRun::new(&dir.path().join("it's").join("1.log"));
+        let work = dir.path().join("work dir");
+        std::fs::create_dir(&work).unwrap();
+        let args = [
+            "-c".to_string(),
+            "echo \"$1 in $(basename \"$PWD\") as $ALICE_TEST\"; echo oops >&2; exit 3".to_string(),
+            "sh".to_string(),
+            "it's".to_string(),
+        ];
+        run.write_script(Some(&work.to_string_lossy()), &[("ALICE_TEST", "a b")], "sh", &args)
+            .unwrap();
+
+        let status = std::process::Command::new("sh")
+            .arg(&run.script)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(run.exit_code(), Some(3));
+        assert!(run.pid().is_some());
+        assert!(!run.exit_file.with_extension("exit.tmp").exists());
+        let log = std::fs::read_to_string(&run.log).unwrap();
+        assert_eq!(log, "it's in work dir as a b\noops\n");
+    }
+}
@@ -2,14 +2,18 @@
 
 use crate::database;
 use crate::providers::{Provider, ProviderId};
+use crate::session::{SessionStatus, SessionUpdate, PERMISSION_PROMPT_IDLE};
 use crate::tray::{set_tray_state, TrayState};
 use notify::{Config, Event, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, RecvTimeoutError};
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
+/// How often the watcher wakes up without file events to re-check quiet sessions
+const QUIET_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Events emitted to the frontend
 #[derive(Clone, serde::Serialize)]
 pub struct SessionUpdateEvent {
@@ -18,11 +22,6 @@ pub struct SessionUpdateEvent {
     pub status: String,
 }
 
-/// Check whether a path points to a JSONL file
-fn is_jsonl(path: &Path) -> bool {
-    path.extension().is_some_and(|e| e == "jsonl")
-}
-
 /// Build a mapping from session directories to their provider IDs.
 /// Falls back to the default Claude directory when no providers yield directories.
 fn build_provider_dir_map() -> HashMap<PathBuf, ProviderId> {
@@ -60,7 +59,7 @@ fn find_provider_for_path(
         .map(|(_, &id)| id)
 }
 
-/// Walk a directory and process every JSONL session file using the given provider.
+/// Walk a directory and process every session file using the given provider.
 /// Returns the number of successfully processed sessions.
 fn scan_provider_sessions(app: &AppHandle, dir: &Path, provider: &dyn Provider) -> u32 {
     if !dir.exists() {
@@ -73,9 +72,9 @@ fn scan_provider_sessions(app: &AppHandle, dir: &Path, provider: &dyn Provider)
         .filter_map(|e| e.ok())
     {
         let path = entry.path();
-        if is_jsonl(path) {
+        if provider.is_session_file(path) {
             match process_session_file(app, path, provider) {
-                Ok(()) => count += 1,
+                Ok(_) => count += 1,
                 Err(e) => tracing::warn!(
                     "Failed to process {} session file {:?}: {}",
                     provider.id(),
@@ -129,9 +128,22 @@ pub fn start_watcher(app: AppHandle) -> Result<(), Box<dyn std::error::Error + S
     let mut last_processed: HashMap<PathBuf, Instant> = HashMap::new();
     let debounce_duration = Duration::from_millis(500);
 
-    for event in rx {
-        for path in event.paths {
-            if !is_jsonl(&path) {
+    // Active sessions are parsed once more after their file goes quiet: a
+    // permission prompt writes nothing, so only the silence reveals it.
+    let mut quiet_checks: HashMap<PathBuf, Instant> = HashMap::new();
+
+    loop {
+        let paths = match rx.recv_timeout(QUIET_CHECK_INTERVAL) {
+            Ok(event) => event.paths,
+            Err(RecvTimeoutError::Timeout) => Vec::new(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        for path in paths {
+            let provider_id =
+                find_provider_for_path(&path, &dir_to_provider).unwrap_or(ProviderId::Claude);
+            let provider = crate::providers::get_provider(provider_id);
+            if !provider.is_session_file(&path) {
                 continue;
             }
 
@@ -144,17 +156,34 @@ pub fn start_watcher(app: AppHandle) -> Result<(), Box<dyn std::error::Error + S
             }
             last_processed.insert(path.clone(), now);
 
-            let provider_id =
-                find_provider_for_path(&path, &dir_to_provider).unwrap_or(ProviderId::Claude);
-            let provider = crate::providers::get_provider(provider_id);
-
-            if let Err(e) = process_session_file(&app, &path, provider.as_ref()) {
-                tracing::error!(
+            match process_session_file(&app, &path, provider.as_ref()) {
+                Ok(SessionStatus::Active) => {
+                    quiet_checks.insert(path, now);
+                }
+                Ok(_) => {
+                    quiet_checks.remove(&path);
+                }
+                Err(e) => tracing::error!(
                     "Failed to process {} session file {:?}: {}",
                     provider_id,
                     path,
                     e
-                );
+                ),
+            }
+        }
+
+        let due: Vec<PathBuf> = quiet_checks
+            .iter()
+            .filter(|(_, processed_at)| processed_at.elapsed() >= PERMISSION_PROMPT_IDLE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in due {
+            quiet_checks.remove(&path);
+            let provider_id =
+                find_provider_for_path(&path, &dir_to_provider).unwrap_or(ProviderId::Claude);
+            let provider = crate::providers::get_provider(provider_id);
+            if let Err(e) = process_session_file(&app, &path, provider.as_ref()) {
+                tracing::warn!("Failed to re-check quiet session file {:?}: {}", path, e);
             }
         }
 
@@ -166,22 +195,64 @@ pub fn start_watcher(app: AppHandle) -> Result<(), Box<dyn std::error::Error + S
     Ok(())
 }
 
-/// Process a single session JSONL file using the given provider
+/// Process a single session JSONL file using the given provider.
+/// Returns the session's resulting status.
 fn process_session_file(
     app: &AppHandle,
     path: &Path,
     provider: &dyn Provider,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<SessionStatus, Box<dyn std::error::Error + Send + Sync>> {
     tracing::debug!("Processing {} session file: {:?}", provider.id(), path);
 
-    let session = provider
-        .parse_session(path)
+    let file_key = path.to_string_lossy();
+    let cursor = database::get_session_cursor(&file_key)?;
+    let keys = database::get_session_file_keys(&file_key)?;
+
+    let SessionUpdate { mut session, messages, full, cursor, agents, new_keys } = provider
+        .parse_session_incremental(path, cursor, &keys)
         .map_err(|e| format!("Provider parse error: {}", e))?;
 
+    // Some files only identify their project indirectly: match it against the known ones
+    if session.project_path.is_empty() {
+        if let Some(root) = provider.resolve_project_path(path, &database::get_project_paths()?) {
+            session.project_name = crate::session::extract_project_name(&root);
+            session.project_path = root;
+        }
+    }
+
+    // Sub-agents logged inline are stored as child sessions of this one
+    for agent in &agents {
+        database::upsert_session(&agent.session)?;
+        if full {
+            database::replace_session_messages(&agent.session.session_id, &agent.messages)?;
+        } else {
+            database::append_session_messages(&agent.session.session_id, &agent.messages)?;
+        }
+    }
+
+    let previous_status = database::get_session_status(&session.session_id)?;
+
+    // The session row goes first, as its messages reference it. Storing
+    // them adds them to the search and tool indexes.
     database::upsert_session(&session)?;
+    if full {
+        database::replace_session_messages(&session.session_id, &messages)?;
+    } else {
+        database::append_session_messages(&session.session_id, &messages)?;
+    }
+    if let Some(ref cursor) = cursor {
+        database::save_session_cursor(&file_key, &session.session_id, cursor, full, &new_keys)?;
+    }
+
+    // Alert only on the transition, not for sessions first seen while waiting
+    if session.status == SessionStatus::NeedsInput
+        && previous_status.is_some_and(|status| status != SessionStatus::NeedsInput)
+    {
+        crate::notification::alert_needs_input(app, &session.project_name);
+    }
 
     let status_str = session.status.as_str().to_string();
-    set_tray_state(app, TrayState::from(session.status));
+    set_tray_state(app, TrayState::from(session.status.clone()));
 
     app.emit(
         "session-updated",
@@ -192,7 +263,7 @@ fn process_session_file(
         },
     )?;
 
-    Ok(())
+    Ok(session.status)
 }
 
 /// Force rescan all session files to update token data (all providers)
@@ -201,6 +272,9 @@ pub fn rescan_all_sessions(
 ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
     let dir_to_provider = build_provider_dir_map();
 
+    // Re-parse every file from the start rather than resuming saved cursors
+    database::clear_session_cursors()?;
+
     tracing::info!("Rescan: Found {} provider directories", dir_to_provider.len());
 
     let mut count: u32 = 0;
@@ -0,0 +1,220 @@
+// Git worktrees - run queued tasks on their own branch, away from the project's working copy
+
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// A task's worktree and the branch checked out in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Worktree {
+    /// Root of the worktree
+    pub path: String,
+    pub branch: String,
+    /// Where the task runs: the project's subdirectory inside the worktree
+    pub working_dir: String,
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+async fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let git_cmd = crate::platform::get_git_command().ok_or("Git not found on PATH")?;
+    let output = Command::new(git_cmd)
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The start of a task ID, enough to tell its worktree and branch apart
+fn short_id(task_id: &str) -> String {
+    task_id.chars().take(8).collect()
+}
+
+/// Branch a task's worktree checks out
+fn task_branch(task_id: &str) -> String {
+    format!("alice/task-{}", short_id(task_id))
+}
+
+/// Create the worktree a task runs in, on a new branch from the project's
+/// HEAD, or reuse the one an earlier attempt left. Returns None when the
+/// project is not in a git repository.
+pub async fn prepare(project_path: &str, task_id: &str) -> Result<Option<Worktree>, String> {
+    let root = crate::platform::get_alice_dir().join("worktrees");
+    prepare_in(&root, project_path, task_id).await
+}
+
+/// `prepare`, keeping worktrees under `root`
+async fn prepare_in(
+    root: &Path,
+    project_path: &str,
+    task_id: &str,
+) -> Result<Option<Worktree>, String> {
+    let project = std::fs::canonicalize(project_path)
+        .map_err(|e| format!("Project {} not found: {}", project_path, e))?;
+    let Ok(toplevel) = git(&project, &["rev-parse", "--show-toplevel"]).await else {
+        return Ok(None);
+    };
+    let repo = PathBuf::from(&toplevel);
+    // Tasks for a subdirectory of a repository run in the same subdirectory
+    let subdir = project
+        .strip_prefix(std::fs::canonicalize(&repo).unwrap_or_else(|_| repo.clone()))
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    let branch = task_branch(task_id);
+    let repo_name = crate::platform::path_file_name(&toplevel);
+    let path = root.join(format!("{}-{}", repo_name, short_id(task_id)));
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let path_str = path.to_string_lossy();
+        let branch_ref = format!("refs/heads/{}", branch);
+        let branch_exists = git(&repo, &["rev-parse", "--verify", "--quiet", &branch_ref])
+            .await
+            .is_ok();
+        if branch_exists {
+            git(&repo, &["worktree", "add", &path_str, &branch]).await?;
+        } else {
+            git(&repo, &["worktree", "add", "-b", &branch, &path_str, "HEAD"]).await?;
+        }
+        tracing::info!("Created worktree {} on branch {}", path_str, branch);
+    }
+
+    Ok(Some(Worktree {
+        path: path.to_string_lossy().to_string(),
+        working_dir: path.join(subdir).to_string_lossy().to_string(),
+        branch,
+    }))
+}
+
+/// Commit whatever a run left uncommitted in its worktree, so the branch
+/// carries the whole result. Returns whether there was anything to commit.
+pub async fn commit_changes(worktree_path: &str, message: &str) -> Result<bool, String> {
+    let dir = Path::new(worktree_path);
+    git(dir, &["add", "-A"]).await?;
+    if git(dir, &["status", "--porcelain"]).await?.is_empty() {
+        return Ok(false);
+    }
+    git(dir, &["commit", "--no-verify", "-m", message]).await?;
+    Ok(true)
+}
+
+/// Whether `branch` has commits the project's checked-out HEAD lacks
+pub async fn has_new_commits(project_path: &str, branch: &str) -> Result<bool, String> {
+    let range = format!("HEAD..{}", branch);
+    let count = git(Path::new(project_path), &["rev-list", "--count", &range]).await?;
+    Ok(count.parse::<u64>().unwrap_or(0) > 0)
+}
+
+/// Remove a worktree, discarding any changes in it, and optionally its branch
+pub async fn remove(
+    project_path: &str,
+    worktree_path: Option<&str>,
+    branch: Option<&str>,
+) -> Result<(), String> {
+    let project = Path::new(project_path);
+    if let Some(path) = worktree_path.filter(|p| Path::new(p).exists()) {
+        git(project, &["worktree", "remove", "--force", path]).await?;
+    }
+    // Forget worktrees whose directories were deleted by hand
+    let _ = git(project, &["worktree", "prune"]).await;
+    if let Some(branch) = branch {
+        git(project, &["branch", "-D", branch]).await?;
+    }
+    Ok(())
+}
+
+/// Remove a task's worktree once it has been reviewed, optionally with its branch
+pub async fn remove_task_worktree(
+    task_id: &str,
+    delete_branch: bool,
+) -> Result<crate::database::Task, String> {
+    let task = crate::database::get_task(task_id).map_err(|e| e.to_string())?;
+    if task.status == crate::database::TaskStatus::Running {
+        return Err(format!("Task {} is running", task_id));
+    }
+    let project = task
+        .project_path
+        .as_deref()
+        .ok_or_else(|| format!("Task {} has no project", task_id))?;
+
+    let branch = task.worktree_branch.as_deref();
+    remove(project, task.worktree_path.as_deref(), branch.filter(|_| delete_branch)).await?;
+    let kept_branch = branch.filter(|_| !delete_branch);
+    crate::database::set_task_worktree(task_id, None, kept_branch).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A repository with one commit, and the canonical path of its root
+    async fn test_repo(dir: &Path) -> String {
+        let repo = dir.join("app");
+        std::fs::create_dir_all(repo.join("web")).unwrap();
+        std::fs::write(repo.join("web").join("README.md"), "web\n").unwrap();
+        for args in [
+            &["init", "-q", "-b", "main"][..],
+            &["config", "user.email", "alice@example.com"],
+            &["config", "user.name", "Alice"],
+            &["add", "-A"],
+            &["commit", "-q", "-m", "Initial commit"],
+        ] {
+            git(&repo, args).await.unwrap();
+        }
+        std::fs::canonicalize(&repo).unwrap().to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_worktree_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_repo(dir.path()).await;
+        let root = dir.path().join("worktrees");
+        let task_id = "3f2a9c1e-0000-4000-8000-000000000000";
+
+        // A subdirectory task runs in the same subdirectory of its worktree
+        let project = format!("{}/web", repo);
+        let worktree = prepare_in(&root, &project, task_id).await.unwrap().unwrap();
+        assert_eq!(worktree.branch, "alice/task-3f2a9c1e");
+        assert!(worktree.working_dir.ends_with("web"));
+        // A later attempt reuses the worktree
+        assert_eq!(prepare_in(&root, &project, task_id).await.unwrap(), Some(worktree.clone()));
+
+        assert!(!commit_changes(&worktree.path, "Nothing").await.unwrap());
+        assert!(!has_new_commits(&repo, &worktree.branch).await.unwrap());
+        std::fs::write(Path::new(&worktree.working_dir).join("index.html"), "<p>hi</p>").unwrap();
+        assert!(commit_changes(&worktree.path, "Add page").await.unwrap());
+        assert!(has_new_commits(&repo, &worktree.branch).await.unwrap());
+
+        // Removing the worktree can keep its branch
+        remove(&repo, Some(&worktree.path), None).await.unwrap();
+        assert!(!Path::new(&worktree.path).exists());
+        assert!(has_new_commits(&repo, &worktree.branch).await.unwrap());
+        remove(&repo, None, Some(&worktree.branch)).await.unwrap();
+        assert!(has_new_commits(&repo, &worktree.branch).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_outside_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        assert_eq!(prepare_in(&dir.path().join("worktrees"), &project, "t1").await, Ok(None));
+    }
+
+    #[test]
+    fn test_short_id_multibyte() {
+        assert_eq!(task_branch("tâche-ünïcode"), "alice/task-tâche-ün");
+        assert_eq!(task_branch("t1"), "alice/task-t1");
+    }
+}
@@ -32,7 +32,8 @@ export type TaskStatus =
   | "running"
   | "completed"
   | "failed"
-  | "skipped";
+  | "skipped"
+  | "cancelled";
 
 export type ImageSourceType = "base64" | "path";
 
@@ -71,6 +72,46 @@ export interface AutoActionConfig {
   delay_minutes: number;
 }
 
+export interface QueueConfig {
+  /** Tasks run at once across all projects */
+  max_concurrent_tasks: number;
+  /** Tasks run at once in one project */
+  max_tasks_per_project: number;
+  /** Runs a failing task gets, unless the task sets its own */
+  max_attempts: number;
+  /** Delay before the first retry; doubles with each further attempt */
+  retry_delay_secs: number;
+  /** Upper bound on the backoff between attempts */
+  max_retry_delay_secs: number;
+  /** Local times of day tasks may start in; empty means any time */
+  run_windows: RunWindow[];
+  /** Run tasks in a git worktree on their own branch, unless the task says otherwise */
+  use_worktrees: boolean;
+  /** Keep the worktrees of failed and cancelled runs for inspection */
+  keep_failed_worktrees: boolean;
+  /** Minutes a task may run before it is stopped, unless the task sets its own; 0 = no limit */
+  timeout_minutes: number;
+  /** Command run after each task in a project, by project path; a non-zero exit fails the task */
+  verify_commands: Record<string, string>;
+  /** Follow-up tasks queued to fix a task's failed verification; 0 = none */
+  max_fix_tasks: number;
+  /** Hold a provider's tasks once its session or weekly usage reaches this percent; 0 = never */
+  pause_at_usage_percent: number;
+  /** What becomes of tasks whose run was cut short by Alice exiting */
+  interrupted_tasks: InterruptedTaskAction;
+  /** Start the queue on launch if it was running when Alice exited */
+  resume_after_restart: boolean;
+}
+
+/** How a task interrupted by Alice exiting is recovered on the next launch */
+export type InterruptedTaskAction = "requeue" | "fail";
+
+/** A daily span of local time ("HH:MM"); may cross midnight */
+export interface RunWindow {
+  start: string;
+  end: string;
+}
+
 export interface AutoActionState {
   timer_active: boolean;
   action_type: string;
@@ -103,6 +144,7 @@ export interface AppConfig {
     on_task_completed: boolean;
     on_task_error: boolean;
     on_needs_input: boolean;
+    on_budget_exceeded: boolean;
     on_queue_started: boolean;
     on_daily_report: boolean;
   };
@@ -115,6 +157,7 @@ export interface AppConfig {
   custom_terminal_command: string;
   terminal_choice_made: boolean;
   auto_action: AutoActionConfig;
+  queue: QueueConfig;
   claude_environments: ClaudeEnvironment[];
   active_environment_id?: string | null;
 }
@@ -140,6 +183,26 @@ export interface Session extends TokenBreakdown {
   model: string | null;
   status: SessionStatus;
   provider: ProviderId;
+  /** Session that spawned this one (sub-agent sessions only) */
+  parent_session_id: string | null;
+  /** Pinned sessions are listed first */
+  pinned: boolean;
+  notes: string | null;
+  /** Archived sessions are hidden unless filtering by "archived" */
+  archived: boolean;
+}
+
+export interface SessionSearchHit extends Session {
+  /** bm25 score (lower is more relevant); null without full-text terms */
+  rank: number | null;
+  snippet: string | null;
+  /** [start, end) offsets of matched terms in `snippet` */
+  highlights: [number, number][];
+}
+
+export interface TagCount {
+  tag: string;
+  count: number;
 }
 
 export interface SessionMessage {
@@ -151,6 +214,15 @@ export interface SessionMessage {
   tokens_out: number | null;
   model: string | null;
   images: ImageContent[];
+  tool_calls: ToolCall[];
+  tool_use_id: string | null;
+  is_error: boolean;
+}
+
+export interface ToolCall {
+  id: string | null;
+  name: string;
+  input: unknown;
 }
 
 export interface ImageContent {
@@ -165,18 +237,40 @@ export interface SessionDetail {
   messages: SessionMessage[];
 }
 
+export interface SessionTree extends SessionDetail {
+  agents: AgentNode[];
+  /** Tokens/cost of the session plus all of its sub-agents */
+  tree_tokens: number;
+  tree_cost_usd: number;
+}
+
+export interface AgentNode {
+  session: Session;
+  children: AgentNode[];
+  tree_tokens: number;
+  tree_cost_usd: number;
+}
+
 // ---------------------------------------------------------------------------
 // Tasks
 // ---------------------------------------------------------------------------
 
+/** Whether all or any of a task's dependencies must complete */
+export type DependencyMode = "all" | "any";
+
+/** How a task's run relates to earlier sessions */
+export type ExecutionMode = "new" | "resume" | "continue" | "fork";
+
 export interface Task {
   id: string;
   prompt: string;
   project_path: string | null;
   status: TaskStatus;
   priority: string;
-  execution_mode: string;
-  depends_on: string | null;
+  execution_mode: ExecutionMode;
+  /** IDs of the tasks this one waits for */
+  depends_on: string[];
+  dependency_mode: DependencyMode;
   session_id: string | null;
   system_prompt: string | null;
   allowed_tools: string | null;
@@ -189,10 +283,94 @@ export interface Task {
   result_output: string | null;
   result_tokens: number | null;
   result_cost_usd: number | null;
+  /** Why Alice stopped the run, e.g. budget exceeded */
+  result_error: string | null;
   created_at: string;
   started_at: string | null;
   completed_at: string | null;
   provider: ProviderId;
+  /** Runs allowed before a failure sticks; null uses the queue default */
+  max_attempts: number | null;
+  /** Delay before the first retry; null uses the queue default */
+  retry_delay_secs: number | null;
+  attempt_count: number;
+  /** Earliest time the queue runs the task again */
+  next_attempt_at: string | null;
+  /** When the scheduler next queues the task */
+  run_at: string | null;
+  /** Cron expression the task repeats on, e.g. "0 2 * * *" */
+  recurrence: string | null;
+  /** Run in a git worktree; null uses the queue default */
+  use_worktree: boolean | null;
+  /** Worktree the task ran in, kept for review */
+  worktree_path: string | null;
+  /** Branch holding the task's changes */
+  worktree_branch: string | null;
+  /** Minutes the task may run; null uses the queue default, 0 means no limit */
+  timeout_minutes: number | null;
+  /** Command whose exit code decides if a run succeeded; null uses the project's, "" means none */
+  verify_command: string | null;
+  /** Task whose failed verification this task was queued to fix */
+  fix_of: string | null;
+  /** Session the latest run wrote to */
+  output_session_id: string | null;
+}
+
+/**
+ * A change to a task's settings, for `update_task_settings`: omitted fields
+ * keep their value and null clears one back to its default
+ */
+export type TaskSettings = Partial<
+  Pick<
+    Task,
+    | "execution_mode"
+    | "session_id"
+    | "max_budget_usd"
+    | "max_attempts"
+    | "retry_delay_secs"
+    | "timeout_minutes"
+    | "verify_command"
+    | "use_worktree"
+    | "run_at"
+    | "recurrence"
+  >
+>;
+
+/** How one attempt at running a task ended */
+export type TaskRunStatus = "running" | "completed" | "failed" | "rate_limited" | "cancelled";
+
+export interface TaskRun {
+  id: number;
+  task_id: string;
+  /** 1 for the first run of the task */
+  attempt: number;
+  status: TaskRunStatus;
+  exit_code: number | null;
+  error: string | null;
+  tokens: number | null;
+  cost_usd: number | null;
+  session_id: string | null;
+  started_at: string;
+  finished_at: string | null;
+  /**
+   * Stdout and stderr of the run, interleaved, each line tagged with its
+   * stream ("[stdout] ", "[stderr] " or "[verify] "). Runs in a terminal log
+   * the terminal's combined output untagged, followed by tagged verification.
+   */
+  log_path: string | null;
+  /** Process running the provider CLI, or its terminal wrapper script */
+  pid: number | null;
+  /** When that process started, to tell it from a later one given its PID */
+  pid_started_at: string | null;
+}
+
+/** A slice of a run's log, from `tail_task_log` */
+export interface TaskLogChunk {
+  content: string;
+  /** Where the next read continues from */
+  offset: number;
+  /** The run has ended, so the log will not grow */
+  finished: boolean;
 }
 
 export interface QueueStartResult {
@@ -221,6 +399,45 @@ export interface ProjectUsage extends UsageMetrics {
   project_path: string;
 }
 
+export type ToolStatsSort = "calls" | "failure_rate" | "duration";
+
+export interface ToolStat {
+  tool_name: string;
+  call_count: number;
+  error_count: number;
+  failure_rate: number;
+  avg_duration_ms: number | null;
+  max_duration_ms: number | null;
+}
+
+export interface ToolInvocation {
+  session_id: string;
+  project_path: string;
+  provider: ProviderId;
+  tool_name: string;
+  input_summary: string | null;
+  /** Full shell command, for Bash-like tools */
+  command: string | null;
+  started_at: number | null;
+  /** null while the call has no result yet */
+  duration_ms: number | null;
+  is_error: boolean;
+}
+
+export interface FileTouch {
+  session_id: string;
+  /** Parent of a sub-agent session */
+  parent_session_id: string | null;
+  project_path: string;
+  provider: ProviderId;
+  file_path: string;
+  tool_name: string;
+  touched_at: number | null;
+  /** User message the edit was made in response to */
+  prompt: string | null;
+  session_first_prompt: string | null;
+}
+
 export interface LiveUsageStats {
   session_percent: number;
   session_reset_at: string | null;
@@ -254,10 +471,42 @@ export interface SessionUpdateEvent {
   status: string;
 }
 
+export type TaskReadiness = "ready" | "waiting" | "unsatisfiable";
+
+/** A dependency that keeps a task from starting */
+export interface TaskBlocker {
+  task_id: string;
+  /** null when the dependency was deleted */
+  status: TaskStatus | null;
+  reason: string;
+}
+
+export interface TaskGraphNode extends Task {
+  readiness: TaskReadiness;
+  blocked_by: TaskBlocker[];
+}
+
+/** Edge from a dependency to the task that waits on it */
+export interface TaskGraphEdge {
+  from: string;
+  to: string;
+}
+
+export interface TaskGraph {
+  nodes: TaskGraphNode[];
+  edges: TaskGraphEdge[];
+}
+
+/** What the queue is doing; "waiting_for_reset" means queued tasks are held by usage limits */
+export type QueueState = "stopped" | "running" | "waiting_for_reset";
+
 export interface QueueStatusEvent {
   is_running: boolean;
-  current_task_id: string | null;
+  state: QueueState;
+  running_task_ids: string[];
   queued_count: number;
+  /** When tasks held by usage limits may start again */
+  resume_at: string | null;
 }
 
 // ---------------------------------------------------------------------------
@@ -173,7 +173,10 @@ export default function ActiveView({ onSessionCountChange }: ActiveViewProps) {
     return acc;
   }, {});
 
-  const projectList = Object.keys(projectGroups).sort();
+  // Sessions whose project could not be resolved only show under "All Projects"
+  const projectList = Object.keys(projectGroups)
+    .filter((project) => project !== "")
+    .sort();
   const isAllSelected = selectedProjects.size === 0;
 
   const filteredSessions = isAllSelected
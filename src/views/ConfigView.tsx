@@ -226,6 +226,7 @@ const NOTIFICATION_TOGGLES: ReadonlyArray<{ key: string; label: string; descript
   { key: "notifications.on_task_completed", label: "Task Completed" },
   { key: "notifications.on_task_error", label: "Task Error" },
   { key: "notifications.on_needs_input", label: "Input Needed" },
+  { key: "notifications.on_budget_exceeded", label: "Budget Exceeded" },
 ];
 
 const DATA_STATS = [
@@ -4,7 +4,7 @@ import { Clock, Search, SearchX, Filter, X, ChevronDown } from "lucide-react";
 import type { LucideIcon } from "lucide-react";
 import { GroupedVirtuoso } from "react-virtuoso";
 import SessionCard from "../components/SessionCard";
-import type { Session } from "../lib/types";
+import type { Session, SessionSearchHit } from "../lib/types";
 import { getModKey } from "../lib/platform";
 import { getProviderColor } from "../lib/provider-colors";
 
@@ -37,6 +37,7 @@ const STATUS_OPTIONS = [
   { value: "completed", label: "Completed" },
   { value: "error", label: "Error" },
   { value: "needs_input", label: "Needs Input" },
+  { value: "archived", label: "Archived" },
 ];
 
 const MODEL_OPTIONS = [
@@ -200,7 +201,7 @@ const HistoryView = forwardRef<HistoryViewRef>(function HistoryView(_, ref) {
   async function searchWithFilters(): Promise<void> {
     setSearching(true);
     try {
-      const result = await invoke<Session[]>("search_sessions_filtered", {
+      const result = await invoke<SessionSearchHit[]>("search_sessions_filtered", {
         query: searchQuery.trim() || null,
         ...filters,
         limit: 50,
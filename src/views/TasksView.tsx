@@ -430,7 +430,8 @@ export default function TasksView({ onTaskCountChange }: TasksViewProps): React.
   const [projects, setProjects] = useState<string[]>([]);
   const [showProjectPicker, setShowProjectPicker] = useState(false);
   const [queueRunning, setQueueRunning] = useState(false);
-  const [currentTaskId, setCurrentTaskId] = useState<string | null>(null);
+  const [runningTaskIds, setRunningTaskIds] = useState<string[]>([]);
+  const [usageResumeAt, setUsageResumeAt] = useState<string | null>(null);
   const [selectedTasks, setSelectedTasks] = useState<Set<string>>(new Set());
   const [lastSelectedId, setLastSelectedId] = useState<string | null>(null);
   const [activeId, setActiveId] = useState<string | null>(null);
@@ -499,7 +500,8 @@ export default function TasksView({ onTaskCountChange }: TasksViewProps): React.
 
     const unlisten = listen<QueueStatusEvent>("queue-status", (event) => {
       setQueueRunning(event.payload.is_running);
-      setCurrentTaskId(event.payload.current_task_id);
+      setRunningTaskIds(event.payload.running_task_ids);
+      setUsageResumeAt(event.payload.state === "waiting_for_reset" ? event.payload.resume_at : null);
       loadTasks();
     });
 
@@ -925,7 +927,16 @@ export default function TasksView({ onTaskCountChange }: TasksViewProps): React.
             <div className="flex items-center justify-between mb-2">
               <h3 className="text-xs font-semibold text-gray-400 uppercase tracking-wider flex items-center gap-2">
                 Queue ({queuedTasks.length})
-                {queueRunning && (
+                {queueRunning && usageResumeAt && (
+                  <span
+                    className="inline-flex items-center gap-1 text-[10px] text-amber-400 bg-amber-500/20 px-1.5 py-0.5 rounded"
+                    title={`Usage limit reached, resumes at ${new Date(usageResumeAt).toLocaleTimeString()}`}
+                  >
+                    <span className="w-1.5 h-1.5 bg-amber-400 rounded-full" />
+                    Waiting for reset
+                  </span>
+                )}
+                {queueRunning && !usageResumeAt && (
                   <span className="inline-flex items-center gap-1 text-[10px] text-blue-400 bg-blue-500/20 px-1.5 py-0.5 rounded">
                     <span className="w-1.5 h-1.5 bg-blue-400 rounded-full animate-pulse" />
                     Running
@@ -977,7 +988,7 @@ export default function TasksView({ onTaskCountChange }: TasksViewProps): React.
                         key={task.id}
                         task={task}
                         isLast={index === queuedTasks.length - 1}
-                        isCurrent={task.id === currentTaskId}
+                        isCurrent={runningTaskIds.includes(task.id)}
                         onRemove={() => updateTaskStatus(task.id, "backlog")}
                       />
                     ))}
@@ -852,7 +852,7 @@ export default function WorkspaceView({ onTaskCountChange, onActiveSessionCountC
   const [projects, setProjects] = useState<string[]>([]);
   const [showProjectPicker, setShowProjectPicker] = useState(false);
   const [queueRunning, setQueueRunning] = useState(false);
-  const [currentTaskId, setCurrentTaskId] = useState<string | null>(null);
+  const [runningTaskIds, setRunningTaskIds] = useState<string[]>([]);
   const [selectedTasks, setSelectedTasks] = useState<Set<string>>(new Set());
   const [lastSelectedId, setLastSelectedId] = useState<string | null>(null);
   const [dragActiveId, setDragActiveId] = useState<string | null>(null);
@@ -991,7 +991,7 @@ export default function WorkspaceView({ onTaskCountChange, onActiveSessionCountC
 
     const unlistenQueue = listen<QueueStatusEvent>("queue-status", (event) => {
       setQueueRunning(event.payload.is_running);
-      setCurrentTaskId(event.payload.current_task_id);
+      setRunningTaskIds(event.payload.running_task_ids);
       loadTasks();
     });
 
@@ -1220,7 +1220,10 @@ export default function WorkspaceView({ onTaskCountChange, onActiveSessionCountC
     return acc;
   }, {});
 
-  const projectList = Object.keys(projectGroups).sort();
+  // Sessions whose project could not be resolved only show under "All Projects"
+  const projectList = Object.keys(projectGroups)
+    .filter((project) => project !== "")
+    .sort();
   const isAllSelected = selectedProjects.size === 0;
 
   const filteredSessions = isAllSelected
@@ -1503,7 +1506,7 @@ export default function WorkspaceView({ onTaskCountChange, onActiveSessionCountC
                         key={task.id}
                         task={task}
                         isLast={index === queuedTasks.length - 1}
-                        isCurrent={task.id === currentTaskId}
+                        isCurrent={runningTaskIds.includes(task.id)}
                         onRemove={() => updateTaskStatus(task.id, "backlog")}
                       />
                     ))}